@@ -0,0 +1,37 @@
+// Times the RGB565 conversion with and without video_threaded_convert at common frame sizes, run with
+// cargo bench --bench rgb565_convert
+use rustro_arch::video::{convert_pixel_array_from_rgb565_to_xrgb8888_threaded, ByteOrder};
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+const ITERATIONS: u32 = 200;
+
+fn time(convert: impl Fn() -> Box<[u32]>) -> Duration {
+    convert(); // the first threaded run starts rayon's pool
+    let start = Instant::now();
+    for _ in 0..ITERATIONS {
+        black_box(convert());
+    }
+    start.elapsed() / ITERATIONS
+}
+
+fn main() {
+    println!("{} threads in rayon's pool", rayon::current_num_threads());
+    for (width, height) in [(160, 144), (256, 224), (320, 240), (640, 480), (1280, 720)] {
+        let row_bytes = width * 2;
+        let bytes: Vec<u8> = (0..row_bytes * height).map(|index| index as u8).collect();
+        // A pitch of 0 converts on this thread, without the log line the scalar function prints every frame
+        let scalar = time(|| convert_pixel_array_from_rgb565_to_xrgb8888_threaded(&bytes, ByteOrder::Little, 0));
+        let threaded = time(|| {
+            convert_pixel_array_from_rgb565_to_xrgb8888_threaded(&bytes, ByteOrder::Little, row_bytes)
+        });
+        println!(
+            "{}x{}: {:?} ({:.2}ns a pixel) scalar, {:?} threaded",
+            width,
+            height,
+            scalar,
+            scalar.as_nanos() as f64 / (width * height) as f64,
+            threaded
+        );
+    }
+}
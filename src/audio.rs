@@ -0,0 +1,348 @@
+use rodio::cpal::traits::HostTrait;
+use rodio::{cpal, DeviceTrait, OutputStream, Sink, Source};
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+const AUDIO_CHANNELS: usize = 2; // the cores always give us interleaved left and right samples
+
+// Number of stereo frames the ring buffer holds for the given latency
+pub fn audio_buffer_frames(sample_rate: u32, latency_ms: u32) -> usize {
+    (sample_rate as u64 * latency_ms as u64 / 1000) as usize
+}
+
+// Squeezes interleaved stereo audio into 1/factor of its length by averaging every `factor` frames,
+// so audio from several frames run at once plays in the time of one (at a higher pitch)
+pub fn speed_up_samples(samples: &[i16], factor: usize) -> Vec<i16> {
+    let factor = factor.max(1);
+    let mut output = Vec::with_capacity(samples.len() / factor + AUDIO_CHANNELS);
+    for frames in samples.chunks(factor * AUDIO_CHANNELS) {
+        let frame_count = (frames.len() / AUDIO_CHANNELS).max(1) as i32;
+        for channel in 0..AUDIO_CHANNELS {
+            let sum: i32 = frames
+                .iter()
+                .skip(channel)
+                .step_by(AUDIO_CHANNELS)
+                .map(|sample| *sample as i32)
+                .sum();
+            output.push((sum / frame_count) as i16);
+        }
+    }
+    output
+}
+
+// Samples from the core are pushed in on the main thread and pulled out by the audio device, its size
+// is the most audio that can be queued so it bounds the latency
+pub struct AudioRingBuffer {
+    samples: Mutex<VecDeque<i16>>,
+    capacity: usize,
+    underruns: AtomicUsize,
+}
+
+impl AudioRingBuffer {
+    pub fn new(capacity_frames: usize) -> AudioRingBuffer {
+        let capacity = capacity_frames.max(1) * AUDIO_CHANNELS;
+        AudioRingBuffer {
+            samples: Mutex::new(VecDeque::with_capacity(capacity)),
+            capacity,
+            underruns: AtomicUsize::new(0),
+        }
+    }
+
+    // When the buffer is full the oldest samples are dropped so we never fall further behind
+    pub fn push_samples(&self, new_samples: &[i16]) {
+        let mut samples = self.samples.lock().unwrap();
+        samples.extend(new_samples);
+        let overflow = samples.len().saturating_sub(self.capacity);
+        // Keep the left/right samples paired up
+        samples.drain(..overflow + overflow % AUDIO_CHANNELS);
+    }
+
+    // Stereo frames waiting to be played
+    pub fn queued_frames(&self) -> usize {
+        self.samples.lock().unwrap().len() / AUDIO_CHANNELS
+    }
+
+    // How full the buffer is, 0 when empty and 1 when full
+    pub fn fill_level(&self) -> f64 {
+        self.queued_frames() as f64 / (self.capacity / AUDIO_CHANNELS) as f64
+    }
+
+    // Blocks until the device has played enough that the buffer is at most half full, which is what
+    // sync_to_audio paces each frame with. Gives up after max_wait so a stalled device can't hang the
+    // emulator, returns whether the device caught up.
+    pub fn wait_for_playback(&self, max_wait: Duration) -> bool {
+        let start = Instant::now();
+        while self.queued_frames() * AUDIO_CHANNELS > self.capacity / 2 {
+            if start.elapsed() >= max_wait {
+                return false;
+            }
+            std::thread::sleep(Duration::from_micros(500));
+        }
+        true
+    }
+
+    fn pop_sample(&self) -> Option<i16> {
+        self.samples.lock().unwrap().pop_front()
+    }
+
+    // How many times the device has drained the buffer since the last call
+    pub fn take_underruns(&self) -> usize {
+        self.underruns.swap(0, Ordering::Relaxed)
+    }
+}
+
+// Converts interleaved stereo audio from the core's sample rate to the device's by linearly interpolating
+// between frames. The position carries over between batches so there are no clicks at the boundaries.
+pub struct LinearResampler {
+    base_step: f64, // input frames per output frame, the core's rate divided by the device's
+    step: f64,      // base_step with the rate_control_factor applied
+    position: f64,
+    last_frame: [i16; AUDIO_CHANNELS],
+}
+
+impl LinearResampler {
+    pub fn new(input_rate: u32, output_rate: u32) -> LinearResampler {
+        let step = input_rate as f64 / output_rate as f64;
+        LinearResampler {
+            base_step: step,
+            step,
+            position: 0.0,
+            last_frame: [0; AUDIO_CHANNELS],
+        }
+    }
+
+    // Scales how fast the input is consumed, above 1 makes fewer output frames and below 1 more
+    pub fn set_rate_factor(&mut self, factor: f64) {
+        self.step = self.base_step * factor;
+    }
+
+    pub fn process(&mut self, input: &[i16]) -> Vec<i16> {
+        let frames: Vec<&[i16]> = input.chunks_exact(AUDIO_CHANNELS).collect();
+        let mut output = Vec::with_capacity(
+            (frames.len() as f64 / self.step) as usize * AUDIO_CHANNELS + AUDIO_CHANNELS,
+        );
+        // Frame 0 is the last frame of the previous batch, frame n is frames[n - 1]
+        let frame = |index: usize| {
+            if index == 0 {
+                &self.last_frame[..]
+            } else {
+                frames[index - 1]
+            }
+        };
+        while self.position < frames.len() as f64 {
+            let index = self.position as usize;
+            let fraction = self.position - index as f64;
+            let (from, to) = (frame(index), frame(index + 1));
+            for channel in 0..AUDIO_CHANNELS {
+                let sample =
+                    from[channel] as f64 + (to[channel] as f64 - from[channel] as f64) * fraction;
+                output.push(sample.round() as i16);
+            }
+            self.position += self.step;
+        }
+        if let Some(last_frame) = frames.last() {
+            self.position -= frames.len() as f64;
+            self.last_frame.copy_from_slice(last_frame);
+        }
+        output
+    }
+}
+
+// Dynamic rate control, the libretro approach to keeping the ring buffer from running dry or overflowing
+// when the emulator runs a little fast or slow. The resampling rate is nudged by up to max_skew (e.g. 0.005
+// for +/-0.5%, too little to hear) in proportion to how far the buffer is from half full: an emptier
+// buffer gets more output frames and a fuller one fewer.
+pub fn rate_control_factor(fill_level: f64, max_skew: f64) -> f64 {
+    1.0 + max_skew * (2.0 * fill_level.clamp(0.0, 1.0) - 1.0)
+}
+
+// Plays whatever is in the ring buffer, silence is played while it's empty. After running dry it waits
+// for the buffer to refill to half full before playing again, otherwise a device pulling more than a
+// frame's worth of audio at a time would drain it every frame.
+pub struct RingBufferSource {
+    buffer: Arc<AudioRingBuffer>,
+    sample_rate: u32,
+    underrun: bool,
+}
+
+impl Iterator for RingBufferSource {
+    type Item = i16;
+
+    fn next(&mut self) -> Option<i16> {
+        if self.underrun && self.buffer.queued_frames() * AUDIO_CHANNELS < self.buffer.capacity / 2 {
+            return Some(0);
+        }
+        match self.buffer.pop_sample() {
+            Some(sample) => {
+                self.underrun = false;
+                Some(sample)
+            }
+            None => {
+                if !self.underrun {
+                    self.underrun = true;
+                    self.buffer.underruns.fetch_add(1, Ordering::Relaxed);
+                }
+                Some(0)
+            }
+        }
+    }
+}
+
+impl Source for RingBufferSource {
+    fn current_frame_len(&self) -> Option<usize> {
+        None
+    }
+
+    fn channels(&self) -> u16 {
+        AUDIO_CHANNELS as u16
+    }
+
+    fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        None
+    }
+}
+
+// The names of the host's output devices, for --list-audio-devices
+pub fn output_device_names() -> Result<Vec<String>, String> {
+    let devices = cpal::default_host()
+        .output_devices()
+        .map_err(|e| format!("Failed to list the audio devices: {}", e))?;
+    Ok(devices.filter_map(|device| device.name().ok()).collect())
+}
+
+// The first name containing `wanted`, ignoring case
+pub fn matching_device_name<'a>(names: &'a [String], wanted: &str) -> Option<&'a String> {
+    let wanted = wanted.to_lowercase();
+    names
+        .iter()
+        .find(|name| name.to_lowercase().contains(&wanted))
+}
+
+// The output device audio_device names, the default device when it's empty or nothing matches it
+fn find_output_device(device_name: &str) -> Result<cpal::Device, String> {
+    let host = cpal::default_host();
+    if !device_name.is_empty() {
+        let devices: Vec<cpal::Device> = host
+            .output_devices()
+            .map_err(|e| format!("Failed to list the audio devices: {}", e))?
+            .collect();
+        let names: Vec<String> = devices
+            .iter()
+            .map(|device| device.name().unwrap_or_default())
+            .collect();
+        match matching_device_name(&names, device_name) {
+            Some(name) => {
+                let index = names.iter().position(|other| other == name).unwrap();
+                return Ok(devices.into_iter().nth(index).unwrap());
+            }
+            None => println!(
+                "No audio device matches audio_device {}, using the default",
+                device_name
+            ),
+        }
+    }
+    host.default_output_device()
+        .ok_or("No audio device found".to_string())
+}
+
+// The audio device playing a ring buffer at the device's sample rate, the core's audio is resampled on
+// the way in. Recreate it if the core's sample rate changes.
+pub struct AudioOutput {
+    _stream: OutputStream,
+    _sink: Sink,
+    buffer: Arc<AudioRingBuffer>,
+    resampler: LinearResampler,
+    sample_rate: u32,
+    device_name: String,
+    max_timing_skew: f64,
+}
+
+impl AudioOutput {
+    // `device_name` (audio_device) picks the output device whose name contains it, empty for the default.
+    // `max_timing_skew` (audio_max_timing_skew) bounds the rate_control_factor, 0 turns it off.
+    pub fn new(
+        sample_rate: u32,
+        latency_ms: u32,
+        device_name: &str,
+        max_timing_skew: f64,
+    ) -> Result<AudioOutput, String> {
+        let device = find_output_device(device_name)?;
+        let device_config = device
+            .default_output_config()
+            .map_err(|e| format!("Failed to get the audio device's config: {}", e))?;
+        println!(
+            "Audio device: {} ({} channels, {} Hz, {:?})",
+            device.name().unwrap_or_default(),
+            device_config.channels(),
+            device_config.sample_rate().0,
+            device_config.sample_format()
+        );
+        let device_sample_rate = device_config.sample_rate().0;
+        let (stream, stream_handle) = OutputStream::try_from_device_config(&device, device_config)
+            .map_err(|e| format!("Failed to open audio device: {}", e))?;
+        let sink = Sink::try_new(&stream_handle)
+            .map_err(|e| format!("Failed to create audio sink: {}", e))?;
+        let buffer_frames = audio_buffer_frames(device_sample_rate, latency_ms);
+        println!(
+            "Audio buffer: {} frames ({} samples) for {}ms, resampling {} Hz to {} Hz",
+            buffer_frames,
+            buffer_frames * AUDIO_CHANNELS,
+            latency_ms,
+            sample_rate,
+            device_sample_rate
+        );
+        let buffer = Arc::new(AudioRingBuffer::new(buffer_frames));
+        sink.append(RingBufferSource {
+            buffer: buffer.clone(),
+            sample_rate: device_sample_rate,
+            underrun: true,
+        });
+        sink.play();
+        Ok(AudioOutput {
+            _stream: stream,
+            _sink: sink,
+            buffer,
+            resampler: LinearResampler::new(sample_rate, device_sample_rate),
+            sample_rate,
+            device_name: device_name.to_string(),
+            max_timing_skew,
+        })
+    }
+
+    // The audio_device this output was asked for, so it can be reopened on the same device
+    pub fn device_name(&self) -> &str {
+        &self.device_name
+    }
+
+    // The audio_max_timing_skew this output was created with
+    pub fn max_timing_skew(&self) -> f64 {
+        self.max_timing_skew
+    }
+
+    pub fn push_samples(&mut self, samples: &[i16]) {
+        self.resampler.set_rate_factor(rate_control_factor(
+            self.buffer.fill_level(),
+            self.max_timing_skew,
+        ));
+        self.buffer.push_samples(&self.resampler.process(samples));
+    }
+
+    pub fn wait_for_playback(&self, max_wait: Duration) -> bool {
+        self.buffer.wait_for_playback(max_wait)
+    }
+
+    // The core's sample rate this output was created for
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    pub fn take_underruns(&self) -> usize {
+        self.buffer.take_underruns()
+    }
+}
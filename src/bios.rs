@@ -0,0 +1,102 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+// A BIOS file a core needs in the system directory, from bios_check_<core> in the config
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BiosFile {
+    pub file_name: String,
+    pub md5: Option<String>, // lowercase hex, any file with the right name is accepted without one
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum BiosStatus {
+    Ok,
+    Missing,
+    Mismatch { actual_md5: String },
+}
+
+// The list is comma separated file names each optionally followed by :md5
+// e.g. bios_check_mgba_libretro = "gba_bios.bin:a860e8c0b6d573d191e4ec7db1b1e4f6"
+pub fn parse_bios_list(list: &str) -> Vec<BiosFile> {
+    list.split(',')
+        .map(|entry| entry.trim())
+        .filter(|entry| !entry.is_empty())
+        .map(|entry| match entry.split_once(':') {
+            Some((file_name, md5)) => BiosFile {
+                file_name: file_name.trim().to_string(),
+                md5: Some(md5.trim().to_lowercase()),
+            },
+            None => BiosFile {
+                file_name: entry.to_string(),
+                md5: None,
+            },
+        })
+        .collect()
+}
+
+pub fn check_bios_file(system_directory: &Path, bios_file: &BiosFile) -> BiosStatus {
+    let contents = match fs::read(system_directory.join(&bios_file.file_name)) {
+        Ok(contents) => contents,
+        Err(_) => return BiosStatus::Missing,
+    };
+    match &bios_file.md5 {
+        Some(expected_md5) => {
+            let actual_md5 = format!("{:x}", md5::compute(contents));
+            if &actual_md5 == expected_md5 {
+                BiosStatus::Ok
+            } else {
+                BiosStatus::Mismatch { actual_md5 }
+            }
+        }
+        None => BiosStatus::Ok,
+    }
+}
+
+// The config key listing a core's BIOS files is named after the core's file without its extension
+pub fn bios_config_key(core_path: &str) -> Option<String> {
+    let core_stem = Path::new(core_path).file_stem()?.to_str()?;
+    Some(format!("bios_check_{}", core_stem))
+}
+
+// Checks every BIOS file the config lists for the core before it's loaded, as cores that are missing
+// one tend to just fail in retro_load_game without saying why. Prints each file's status and returns
+// an error naming the missing and mismatched ones.
+pub fn verify_bios(
+    config: &HashMap<String, String>,
+    core_path: &str,
+    system_directory: &Path,
+) -> Result<(), String> {
+    let bios_files = match bios_config_key(core_path).and_then(|key| config.get(&key)) {
+        Some(list) => parse_bios_list(list),
+        None => return Ok(()),
+    };
+    let mut problems = Vec::new();
+    for bios_file in &bios_files {
+        let status = check_bios_file(system_directory, bios_file);
+        match &status {
+            BiosStatus::Ok => println!("BIOS {}: OK", bios_file.file_name),
+            BiosStatus::Missing => {
+                println!("BIOS {}: Missing", bios_file.file_name);
+                problems.push(format!("{} is missing", bios_file.file_name));
+            }
+            BiosStatus::Mismatch { actual_md5 } => {
+                println!(
+                    "BIOS {}: MD5 {} doesn't match {}",
+                    bios_file.file_name,
+                    actual_md5,
+                    bios_file.md5.as_deref().unwrap_or_default()
+                );
+                problems.push(format!("{} has the wrong MD5", bios_file.file_name));
+            }
+        }
+    }
+    if problems.is_empty() {
+        return Ok(());
+    }
+    Err(format!(
+        "BIOS check failed in {}: {}",
+        system_directory.display(),
+        problems.join(", ")
+    ))
+}
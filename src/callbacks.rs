@@ -0,0 +1,217 @@
+use crate::state::CURRENT_EMULATOR_STATE;
+use crate::input::{
+    LightgunState, DEVICE_ID_LIGHTGUN_IS_OFFSCREEN, DEVICE_ID_LIGHTGUN_RELOAD,
+    DEVICE_ID_LIGHTGUN_SCREEN_X, DEVICE_ID_LIGHTGUN_SCREEN_Y,
+};
+use crate::replay::buttons_to_mask;
+use crate::video::{
+    convert_pixel_array_from_0rgb1555_to_xrgb8888, convert_pixel_array_from_rgb565_to_xrgb8888,
+    convert_pixel_array_from_rgb565_to_xrgb8888_threaded,
+    convert_pixel_array_from_xrgb8888, swap_red_blue,
+};
+use crate::rumble::RumbleState;
+use libretro_sys::{LogLevel, PixelFormat, RumbleEffect};
+use std::ffi::{CStr, CString};
+use std::panic::{self, AssertUnwindSafe};
+use std::ptr;
+
+////////////////////////
+// Utility FUnctions
+////////////////////////
+
+// Convert the input String to a CString, but be-careful with memory management when sending this to a core..
+pub(crate) fn convert_to_cstring(input: String) -> CString {
+    CString::new(input).expect("Failed to convert to CString")
+}
+
+// print_c_string simply takes in a Cstring(libc::c_char pointer) and prints it to the console
+pub(crate) fn print_c_string(c_string_ptr: *const libc::c_char) {
+    unsafe {
+        if !c_string_ptr.is_null() {
+            let c_str = CStr::from_ptr(c_string_ptr);
+            if let Ok(rust_string) = c_str.to_str() {
+                println!("{}", rust_string);
+            }
+        }
+    }
+}
+
+// A panic must not unwind out of an extern "C" callback into the core, so the callback bodies run through
+// this. The panic is reported, the core is flagged as crashed and `default` is handed back to the core.
+pub(crate) fn catch_callback_panic<R>(callback_name: &str, default: R, body: impl FnOnce() -> R) -> R {
+    match panic::catch_unwind(AssertUnwindSafe(body)) {
+        Ok(result) => result,
+        Err(_) => {
+            println!("Core crashed in the {} callback", callback_name);
+            unsafe { CURRENT_EMULATOR_STATE.core_crashed = true };
+            default
+        }
+    }
+}
+
+///////////////////////
+// Core Callbacks
+///////////////////////
+
+pub(crate) unsafe extern "C" fn libretro_set_video_refresh_callback(
+    frame_buffer_data: *const libc::c_void,
+    width: libc::c_uint,
+    height: libc::c_uint,
+    pitch: libc::size_t,
+) {
+    catch_callback_panic("video refresh", (), || {
+        println!("libretro_set_video_refresh_callback width: {} height: {} pitch: {}", width, height, pitch);
+        if (frame_buffer_data == ptr::null()) {
+            println!("frame_buffer_data was null");
+            return;
+        }
+        // The pitch is already in bytes so this is the whole frame including any row padding
+        let length_of_frame_buffer = (pitch as u32) * height;
+            println!("length_of_frame_buffer: {}", length_of_frame_buffer);
+        let buffer_slice = std::slice::from_raw_parts(
+            frame_buffer_data as *const u8,
+            length_of_frame_buffer as usize,
+        );
+        println!("got buffer_slice");
+        // Said once, on the first frame, so the log shows why a minimal core's colors look the way they do
+        if !CURRENT_EMULATOR_STATE.pixel_format_negotiated {
+            println!("Core didn't call ENVIRONMENT_SET_PIXEL_FORMAT, assuming the libretro default RETRO_PIXEL_FORMAT_0RGB1555");
+            CURRENT_EMULATOR_STATE.pixel_format_negotiated = true;
+        }
+        let mut result = match CURRENT_EMULATOR_STATE.pixel_format {
+            PixelFormat::ARGB1555 => Vec::from(convert_pixel_array_from_0rgb1555_to_xrgb8888(
+                buffer_slice,
+                CURRENT_EMULATOR_STATE.rgb565_byte_order,
+            )),
+            PixelFormat::RGB565 if CURRENT_EMULATOR_STATE.threaded_convert => {
+                Vec::from(convert_pixel_array_from_rgb565_to_xrgb8888_threaded(
+                    buffer_slice,
+                    CURRENT_EMULATOR_STATE.rgb565_byte_order,
+                    pitch,
+                ))
+            }
+            PixelFormat::RGB565 => Vec::from(convert_pixel_array_from_rgb565_to_xrgb8888(
+                buffer_slice,
+                CURRENT_EMULATOR_STATE.rgb565_byte_order,
+            )),
+            PixelFormat::ARGB8888 => {
+                println!("ARGB8888 len:{} w*h*p: {}",  buffer_slice.len(), width * height);
+                Vec::from(convert_pixel_array_from_xrgb8888(buffer_slice))
+            },
+        };
+        println!("Middle of libretro_set_video_refresh_callback");
+        if CURRENT_EMULATOR_STATE.swap_red_blue {
+            swap_red_blue(&mut result);
+        }
+
+        // Wrap the Vec<u8> in an Option and assign it to the frame_buffer field
+        CURRENT_EMULATOR_STATE.frame_buffer = Some(result);
+        CURRENT_EMULATOR_STATE.screen_height = height;
+        CURRENT_EMULATOR_STATE.screen_width = width;
+        CURRENT_EMULATOR_STATE.screen_pitch = pitch as u32;
+        println!("End of libretro_set_video_refresh_callback")
+    })
+}
+
+pub(crate) unsafe extern "C" fn libretro_set_input_poll_callback() {
+    println!("libretro_set_input_poll_callback")
+}
+
+// Asks for every joypad button at once as a bitmask, used by cores after ENVIRONMENT_GET_INPUT_BITMASKS
+const DEVICE_ID_JOYPAD_MASK: libc::c_uint = 256;
+
+// Only the absolute screen position is supported, the old relative DEVICE_ID_LIGHTGUN_X/Y always read 0
+fn lightgun_input_state(lightgun: &LightgunState, id: libc::c_uint) -> i16 {
+    match id {
+        DEVICE_ID_LIGHTGUN_SCREEN_X => lightgun.screen_x,
+        DEVICE_ID_LIGHTGUN_SCREEN_Y => lightgun.screen_y,
+        DEVICE_ID_LIGHTGUN_IS_OFFSCREEN => lightgun.offscreen as i16,
+        libretro_sys::DEVICE_ID_LIGHTGUN_TRIGGER => lightgun.trigger as i16,
+        DEVICE_ID_LIGHTGUN_RELOAD => lightgun.reload as i16,
+        _ => 0,
+    }
+}
+
+pub(crate) unsafe extern "C" fn libretro_set_input_state_callback(
+    port: libc::c_uint,
+    device: libc::c_uint,
+    index: libc::c_uint,
+    id: libc::c_uint,
+) -> i16 {
+    catch_callback_panic("input state", 0, || {
+        // println!("libretro_set_input_state_callback port: {} device: {} index: {} id: {}", port, device, index, id);
+        if device & libretro_sys::DEVICE_MASK == libretro_sys::DEVICE_LIGHTGUN && port == 0 {
+            if let Some(lightgun) = CURRENT_EMULATOR_STATE.lightgun {
+                return lightgun_input_state(&lightgun, id);
+            }
+        }
+        if device & libretro_sys::DEVICE_MASK == libretro_sys::DEVICE_KEYBOARD {
+            let keyboard = &*std::ptr::addr_of!(CURRENT_EMULATOR_STATE.keyboard);
+            return keyboard.as_ref().is_some_and(|keys| keys.contains(&id)) as i16;
+        }
+        let ports = &*std::ptr::addr_of!(CURRENT_EMULATOR_STATE.buttons_pressed);
+        let is_pressed = match ports.get(port as usize) {
+            Some(buttons_pressed) if id == DEVICE_ID_JOYPAD_MASK => buttons_to_mask(buttons_pressed) as i16,
+            Some(buttons_pressed) => buttons_pressed.get(id as usize).copied().unwrap_or(0),
+            None => 0,
+        };
+
+        return is_pressed;
+    })
+}
+
+// The frontend plays whatever was last set here after each frame, so this only records it
+pub(crate) unsafe extern "C" fn libretro_set_rumble_state_callback(
+    port: libc::c_uint,
+    effect: RumbleEffect,
+    strength: u16,
+) -> bool {
+    catch_callback_panic("rumble", false, || {
+        let rumble = &mut *std::ptr::addr_of_mut!(CURRENT_EMULATOR_STATE.rumble);
+        if rumble.len() <= port as usize {
+            rumble.resize(port as usize + 1, RumbleState::default());
+        }
+        let motor = match effect {
+            RumbleEffect::Strong => &mut rumble[port as usize].strong,
+            RumbleEffect::Weak => &mut rumble[port as usize].weak,
+        };
+        if *motor != strength {
+            *motor = strength;
+            CURRENT_EMULATOR_STATE.rumble_changed = true;
+        }
+        true
+    })
+}
+
+// Cores that send one stereo frame at a time go into the same buffer as the batches, in the order sent
+pub(crate) unsafe extern "C" fn libretro_set_audio_sample_callback(left: i16, right: i16) {
+    catch_callback_panic("audio sample", (), || {
+        (*std::ptr::addr_of_mut!(CURRENT_EMULATOR_STATE.audio_data))
+            .get_or_insert_with(Vec::new)
+            .extend_from_slice(&[left, right]);
+    })
+}
+
+const AUDIO_CHANNELS: usize = 2; // left and right
+// Returns how many frames were consumed, the frame's audio buffer grows to fit so that's all of them
+pub(crate) unsafe extern "C" fn libretro_set_audio_sample_batch_callback(
+    audio_data: *const i16,
+    frames: libc::size_t,
+) -> libc::size_t {
+    catch_callback_panic("audio sample batch", 0, || {
+        if audio_data.is_null() {
+            return 0;
+        }
+        let audio_slice = std::slice::from_raw_parts(audio_data, frames * AUDIO_CHANNELS);
+        (*std::ptr::addr_of_mut!(CURRENT_EMULATOR_STATE.audio_data))
+            .get_or_insert_with(Vec::new)
+            .extend_from_slice(audio_slice);
+        frames
+    })
+}
+
+pub(crate) unsafe extern "C" fn libretro_log_print_callback(level: LogLevel, fmt: *const libc::c_char) {
+    print!("{:?}: ", level);
+    print_c_string(fmt);
+}
+
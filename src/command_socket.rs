@@ -0,0 +1,95 @@
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::thread;
+
+// Commands accepted on the --command-socket, one per line, e.g. `SAVE 3`
+#[derive(Clone, Debug, PartialEq)]
+pub enum Command {
+    Save(u8),
+    Load(u8),
+    Screenshot,
+    Pause,
+    Reset,
+    Quit,
+}
+
+// A parsed command waiting for the main loop, the result is sent back to the client as OK/ERR
+pub struct CommandRequest {
+    pub command: Command,
+    pub response: Sender<Result<String, String>>,
+}
+
+fn parse_slot(argument: Option<&str>) -> Result<u8, String> {
+    let argument = argument.ok_or("Missing save slot")?;
+    argument
+        .parse::<u8>()
+        .map_err(|_| format!("Invalid save slot '{}'", argument))
+}
+
+pub fn parse_command(line: &str) -> Result<Command, String> {
+    let mut parts = line.split_whitespace();
+    let name = parts.next().ok_or("Empty command")?.to_uppercase();
+    let command = match name.as_str() {
+        "SAVE" => Command::Save(parse_slot(parts.next())?),
+        "LOAD" => Command::Load(parse_slot(parts.next())?),
+        "SCREENSHOT" => Command::Screenshot,
+        "PAUSE" => Command::Pause,
+        "RESET" => Command::Reset,
+        "QUIT" => Command::Quit,
+        _ => return Err(format!("Unknown command '{}'", name)),
+    };
+    if parts.next().is_some() {
+        return Err(format!("Too many arguments for {}", name));
+    }
+    Ok(command)
+}
+
+fn handle_client(stream: TcpStream, commands: Sender<CommandRequest>) {
+    let mut writer = match stream.try_clone() {
+        Ok(writer) => writer,
+        Err(err) => return println!("Command socket error: {}", err),
+    };
+    for line in BufReader::new(stream).lines() {
+        let line = match line {
+            Ok(line) => line,
+            Err(_) => return,
+        };
+        if line.trim().is_empty() {
+            continue;
+        }
+        let result = parse_command(&line).and_then(|command| {
+            // The core is not thread safe so the command is run by the main loop and we wait for its answer
+            let (response, result) = channel();
+            commands
+                .send(CommandRequest { command, response })
+                .map_err(|_| "Emulator is shutting down".to_string())?;
+            result
+                .recv()
+                .unwrap_or_else(|_| Err("Emulator is shutting down".to_string()))
+        });
+        let reply = match result {
+            Ok(message) if message.is_empty() => "OK\n".to_string(),
+            Ok(message) => format!("OK {}\n", message),
+            Err(err) => format!("ERR {}\n", err),
+        };
+        if writer.write_all(reply.as_bytes()).is_err() {
+            return;
+        }
+    }
+}
+
+// Listens on a TCP address such as 127.0.0.1:55355, the returned Receiver should be polled once per frame
+pub fn spawn_command_listener(address: &str) -> Result<Receiver<CommandRequest>, String> {
+    let listener = TcpListener::bind(address)
+        .map_err(|e| format!("Failed to open command socket on {}: {}", address, e))?;
+    println!("Listening for commands on {}", address);
+    let (sender, receiver) = channel();
+    thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            let commands = sender.clone();
+            thread::spawn(move || handle_client(stream, commands));
+        }
+    });
+    Ok(receiver)
+}
@@ -0,0 +1,649 @@
+use crate::language::{language_from_name, LANGUAGE_ENGLISH};
+use crate::osd::{FpsDisplay, LoadFailAlert};
+use crate::region::Region;
+use crate::save_state::StateCompression;
+use crate::video::{
+    AspectMode, ByteOrder, ColorCorrection, OverscanCrop, VideoFilter, WindowSizeLimits,
+};
+use std::collections::HashMap;
+use std::env;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+///////////////////////
+// Config Functions
+///////////////////////
+pub fn get_retroarch_config_path() -> PathBuf {
+    return match std::env::consts::OS {
+        "windows" => PathBuf::from(env::var("APPDATA").ok().unwrap()).join("retroarch"),
+        "macos" => PathBuf::from(env::var("HOME").ok().unwrap())
+            .join("Library/Application Support/RetroArch"),
+        _ => PathBuf::from(env::var("XDG_CONFIG_HOME").ok().unwrap()).join("retroarch"),
+    };
+}
+
+// Expands a leading ~ to the home directory and $VAR, ${VAR} and %VAR% to the environment variable's value,
+// so paths like ~/roms/game.gb or %APPDATA%\cores work. Variables that aren't set are left as they are.
+pub fn expand_path(path: &str) -> PathBuf {
+    let mut expanded = String::with_capacity(path.len());
+    let mut rest = path;
+    if let Some(after_tilde) = path.strip_prefix('~') {
+        if after_tilde.is_empty() || after_tilde.starts_with(['/', '\\']) {
+            if let Ok(home) = env::var("HOME").or_else(|_| env::var("USERPROFILE")) {
+                expanded.push_str(&home);
+                rest = after_tilde;
+            }
+        }
+    }
+    while let Some(start) = rest.find(['$', '%']) {
+        expanded.push_str(&rest[..start]);
+        let variable = &rest[start..];
+        // The variable's name and how much of the path it takes up
+        let (name, length) = if let Some(braced) = variable.strip_prefix("${") {
+            braced.find('}').map_or(("", 0), |end| (&braced[..end], end + 3))
+        } else if let Some(percent) = variable.strip_prefix('%') {
+            percent.find('%').map_or(("", 0), |end| (&percent[..end], end + 2))
+        } else {
+            let end = variable[1..]
+                .find(|character: char| !character.is_ascii_alphanumeric() && character != '_')
+                .unwrap_or(variable.len() - 1);
+            (&variable[1..end + 1], end + 1)
+        };
+        let value = Some(name)
+            .filter(|name| !name.is_empty() && !name.contains(['=', '\0']))
+            .and_then(|name| env::var(name).ok());
+        match value {
+            Some(value) => {
+                expanded.push_str(&value);
+                rest = &variable[length..];
+            }
+            None => {
+                expanded.push_str(&variable[..1]);
+                rest = &variable[1..];
+            }
+        }
+    }
+    expanded.push_str(rest);
+    PathBuf::from(expanded)
+}
+
+pub fn parse_retroarch_config(config_file: &Path) -> Result<HashMap<String, String>, String> {
+    let file = File::open(config_file).map_err(|e| format!("Failed to open file: {}", e))?;
+    let reader = BufReader::new(file);
+    let mut config_map = HashMap::new();
+    for line in reader.lines() {
+        let line = line.map_err(|e| format!("Failed to read line: {}", e))?;
+        if let Some((key, value)) = line.split_once("=") {
+            config_map.insert(
+                key.trim().to_string(),
+                value.trim().replace("\"", "").to_string(),
+            );
+        }
+    }
+    Ok(config_map)
+}
+
+// Per core defaults shipped in ./profiles, named after the core's library_name e.g. profiles/Gambatte.cfg
+const PROFILES_DIRECTORY: &str = "./profiles";
+
+// The profile for a core, matched against its library_name ignoring case
+pub fn find_core_profile(profiles_directory: &Path, library_name: &str) -> Option<PathBuf> {
+    std::fs::read_dir(profiles_directory)
+        .ok()?
+        .flatten()
+        .map(|entry| entry.path())
+        .find(|path| {
+            path.extension().is_some_and(|extension| extension == "cfg")
+                && path
+                    .file_stem()
+                    .and_then(|stem| stem.to_str())
+                    .is_some_and(|stem| stem.eq_ignore_ascii_case(library_name))
+        })
+}
+
+pub fn setup_config() -> Result<HashMap<String, String>, String> {
+    setup_config_for_core(None)
+}
+
+// The built in defaults, every setting the frontend reads has one
+pub fn default_config() -> HashMap<String, String> {
+    HashMap::from([
+        ("input_player1_a", "a"),
+        ("input_player1_b", "s"),
+        ("input_player1_x", "z"),
+        ("input_player1_y", "x"),
+        ("input_player1_l", "q"),
+        ("input_player1_r", "w"),
+        ("input_player1_down", "down"),
+        ("input_player1_up", "up"),
+        ("input_player1_left", "left"),
+        ("input_player1_right", "right"),
+        ("input_player1_select", "space"),
+        ("input_player1_start", "enter"),
+        ("input_reset", "h"),
+        ("input_hotkey_enable", "nul"),
+        ("input_swap_core", "nul"),
+        ("input_save_state", "f2"),
+        ("input_load_state", "f4"),
+        ("input_screenshot", "f8"),
+        ("input_screenshot_clipboard", "nul"),
+        ("input_hold_fast_forward", "l"),
+        // A key bound to a game button and a hotkey presses the button, true makes it the hotkey instead
+        ("hotkey_priority", "false"),
+        ("input_debug_dump", "f10"),
+        ("input_toggle_filter", "f9"),
+        ("savestate_directory", "./states"),
+        ("system_directory", "System"),
+        ("frontend_language", "english"),
+        ("force_region", ""),
+        ("video_rotation", ""),
+        ("video_scale", ""),
+        ("video_scale_max", "6"),
+        // WIDTHxHEIGHT the window is kept at least / at most as big as, empty for no limit
+        ("window_min_size", ""),
+        ("window_max_size", ""),
+        ("input_scale_increase", "nul"),
+        ("input_scale_decrease", "nul"),
+        ("screenshot_directory", "./screenshots"),
+        ("input_state_slot_decrease", "f6"),
+        ("input_state_slot_increase", "f7"),
+        ("savestate_max_slots", "10"),
+        // Flips the current slot between 0 and quick_swap_slot
+        ("input_quick_swap_slot", "nul"),
+        ("quick_swap_slot", "1"),
+        ("savestate_slot_wrap", "true"),
+        // Moves to the next slot after each save and loads from the highest occupied one
+        ("savestate_auto_index", "false"),
+        ("savestate_compression", "none"),
+        ("autoload_slot", ""),
+        ("savestate_load_fallback", "false"),
+        // Also writes the frame to {game}_{slot}.png next to each save state
+        ("screenshot_on_savestate", "false"),
+        ("savestate_load_fail_osd", "true"),
+        ("savestate_load_fail_alert", "none"),
+        ("input_analog_deadzone", "0.5"),
+        ("input_player1_gamepad_name", ""),
+        ("input_player1_joypad_index", "0"),
+        // Empty picks from the devices the core lists for the port
+        ("input_libretro_device_p1", ""),
+        ("input_libretro_device_p2", ""),
+        ("input_libretro_device_p3", ""),
+        ("input_libretro_device_p4", ""),
+        ("cores_directory", "./cores"),
+        ("roms_directory", ""),
+        ("audio_enabled", "true"),
+        ("audio_latency_ms", "64"),
+        // How far the audio rate may be nudged to keep the buffer half full, 0.005 is +/-0.5% and 0 turns it off
+        ("audio_max_timing_skew", "0.005"),
+        // Part of the name of the output device to play to (see --list-audio-devices), empty for the default
+        ("audio_device", ""),
+        ("fast_forward_frames", "4"),
+        ("fast_forward_mute", "true"),
+        ("sync_to_audio", "false"),
+        ("pause_on_focus_loss", "false"),
+        // Escape has to be pressed twice within a second to quit
+        ("confirm_exit", "false"),
+        ("run_ahead_enabled", "false"),
+        ("run_ahead_frames", "1"),
+        // Keeps snapshots of the last few seconds to step back through while input_rewind is held
+        ("rewind_enable", "false"),
+        ("input_rewind", "r"),
+        // A snapshot every this many frames, bigger goes further back in the same memory but in coarser steps
+        ("rewind_granularity", "1"),
+        // Snapshots (compressed with savestate_compression) past this many MB are dropped oldest first
+        ("rewind_buffer_size_mb", "20"),
+        ("video_rgb565_byte_order", "native"),
+        ("video_rgb565_swap_bytes", "false"),
+        ("video_swap_rb", "false"),
+        // Converts RGB565 frames on every CPU core, benches/rgb565_convert.rs shows if it helps on this machine
+        ("video_threaded_convert", "false"),
+        ("video_filter", "nearest"),
+        ("video_aspect_mode", "stretch"),
+        // Scales frames to the window's size with video_filter instead of leaving the window to stretch them
+        ("video_scale_to_window", "false"),
+        // With video_scale_to_window, only scales by whole multiples of the frame's height
+        ("video_scale_integer", "false"),
+        ("video_gamma", "1.0"),
+        ("video_brightness", "1.0"),
+        ("input_toggle_aspect", "nul"),
+        ("input_swap_ab", "false"),
+        ("input_swap_xy", "false"),
+        ("input_toggle_swap_ab", "nul"),
+        ("video_crop_top", "0"),
+        ("video_crop_bottom", "0"),
+        ("video_crop_left", "0"),
+        ("video_crop_right", "0"),
+        ("video_fps_display", "title"),
+        ("video_show_frame_count", "false"),
+        ("fps_sample_interval_ms", "1000"),
+        ("rom_use_mmap", "false"),
+        ("max_rom_size_mb", "512"),
+        ("game_info_meta", ""),
+        // ("audio_enable", "true"),
+    ])
+    .iter()
+    .map(|(k, v)| (k.to_string(), v.to_string()))
+    .collect()
+}
+
+// Each of these overrides the ones before it:
+//   1. the built in defaults, default_config
+//   2. the core's profile, profiles/{library_name}.cfg
+//   3. RetroArch's retroarch.cfg
+//   4. our ./rustroarch.cfg
+// Command line options like --core-option and --scale-filter override all of them.
+pub fn setup_config_for_core(core_library_name: Option<&str>) -> Result<HashMap<String, String>, String> {
+    let retro_arch_config_path = get_retroarch_config_path();
+    let our_config = parse_retroarch_config(Path::new("./rustroarch.cfg"));
+    let retro_arch_config =
+        parse_retroarch_config(&retro_arch_config_path.join("config/retroarch.cfg"));
+    let mut merged_config = default_config();
+    if let Some(library_name) = core_library_name {
+        match find_core_profile(Path::new(PROFILES_DIRECTORY), library_name) {
+            Some(profile_path) => match parse_retroarch_config(&profile_path) {
+                Ok(profile) => {
+                    println!("Using the {} profile: {}", library_name, profile_path.display());
+                    merged_config.extend(profile);
+                }
+                Err(err) => println!("Failed to read profile {}: {}", profile_path.display(), err),
+            },
+            None => println!("No profile for {} in {}", library_name, PROFILES_DIRECTORY),
+        }
+    }
+    match retro_arch_config {
+        Ok(config) => merged_config.extend(config),
+        _ => println!("We don't have RetroArch config"),
+    }
+    match our_config {
+        Ok(config) => merged_config.extend(config),
+        _ => println!("We don't have RustroArch config",),
+    }
+    // Directories can be given as ~/roms, $HOME/roms etc.
+    for (_, directory) in merged_config.iter_mut().filter(|(key, _)| key.ends_with("_directory")) {
+        *directory = expand_path(directory).to_string_lossy().into_owned();
+    }
+    // println!("retro_arch_config_path: {} merged_config: {:?}", retro_arch_config_path.join("config/retroarch.cfg").display(), merged_config);
+    Ok(merged_config.clone())
+}
+
+// savestate_max_slots is the number of slots available, so valid slots are 0..max_slots
+pub fn get_max_save_slots(config: &HashMap<String, String>) -> u16 {
+    match config["savestate_max_slots"].parse::<u16>() {
+        Ok(max_slots) => max_slots.clamp(1, 256),
+        Err(_) => {
+            println!(
+                "Invalid savestate_max_slots: {} using 256",
+                config["savestate_max_slots"]
+            );
+            256
+        }
+    }
+}
+
+// The slot input_quick_swap_slot flips to from slot 0, it has to be one of the other savestate_max_slots
+pub fn get_quick_swap_slot(config: &HashMap<String, String>, max_slots: u16) -> u8 {
+    let default = 1.min(max_slots - 1) as u8;
+    match config["quick_swap_slot"].parse::<u8>() {
+        Ok(slot) if slot > 0 && (slot as u16) < max_slots => slot,
+        _ => {
+            println!(
+                "Invalid quick_swap_slot: {} using {}",
+                config["quick_swap_slot"], default
+            );
+            default
+        }
+    }
+}
+
+// How far (0.0-1.0) the analog stick has to be pushed before it presses the dpad
+pub fn get_analog_deadzone(config: &HashMap<String, String>) -> f32 {
+    match config["input_analog_deadzone"].parse::<f32>() {
+        Ok(deadzone) if (0.0..=1.0).contains(&deadzone) => deadzone,
+        _ => {
+            println!(
+                "Invalid input_analog_deadzone: {} using 0.5",
+                config["input_analog_deadzone"]
+            );
+            0.5
+        }
+    }
+}
+
+// The audio ring buffer holds this much audio, more latency means fewer crackles from the buffer running dry
+pub fn get_audio_latency_ms(config: &HashMap<String, String>) -> u32 {
+    match config["audio_latency_ms"].parse::<u32>() {
+        Ok(latency_ms) => latency_ms.clamp(16, 256),
+        Err(_) => {
+            println!(
+                "Invalid audio_latency_ms: {} using 64",
+                config["audio_latency_ms"]
+            );
+            64
+        }
+    }
+}
+
+// The most dynamic rate control may change the audio rate by, capped at 5% as past that the pitch change is easy to hear
+pub fn get_audio_max_timing_skew(config: &HashMap<String, String>) -> f64 {
+    match config["audio_max_timing_skew"].parse::<f64>() {
+        Ok(skew) if skew.is_finite() => skew.clamp(0.0, 0.05),
+        _ => {
+            println!(
+                "Invalid audio_max_timing_skew: {} using 0.005",
+                config["audio_max_timing_skew"]
+            );
+            0.005
+        }
+    }
+}
+
+// ROMs bigger than this aren't read into memory, 0 turns the limit off
+pub fn get_max_rom_size_mb(config: &HashMap<String, String>) -> u64 {
+    match config["max_rom_size_mb"].parse::<u64>() {
+        Ok(max_size_mb) => max_size_mb,
+        Err(_) => {
+            println!(
+                "Invalid max_rom_size_mb: {} using 512",
+                config["max_rom_size_mb"]
+            );
+            512
+        }
+    }
+}
+
+// How many frames are run for every frame shown while fast forward is held
+pub fn get_fast_forward_frames(config: &HashMap<String, String>) -> u32 {
+    match config["fast_forward_frames"].parse::<u32>() {
+        Ok(frames) => frames.clamp(2, 16),
+        Err(_) => {
+            println!(
+                "Invalid fast_forward_frames: {} using 4",
+                config["fast_forward_frames"]
+            );
+            4
+        }
+    }
+}
+
+// A rewind snapshot is taken every this many frames
+pub fn get_rewind_granularity(config: &HashMap<String, String>) -> u32 {
+    match config["rewind_granularity"].parse::<u32>() {
+        Ok(frames) => frames.clamp(1, 3600),
+        Err(_) => {
+            println!(
+                "Invalid rewind_granularity: {} using 1",
+                config["rewind_granularity"]
+            );
+            1
+        }
+    }
+}
+
+// The most memory the rewind snapshots may take up, in bytes
+pub fn get_rewind_buffer_size(config: &HashMap<String, String>) -> usize {
+    let megabytes = match config["rewind_buffer_size_mb"].parse::<usize>() {
+        Ok(megabytes) => megabytes.clamp(1, 4096),
+        Err(_) => {
+            println!(
+                "Invalid rewind_buffer_size_mb: {} using 20",
+                config["rewind_buffer_size_mb"]
+            );
+            20
+        }
+    };
+    megabytes * 1024 * 1024
+}
+
+// Frames to run ahead of what's shown to hide the core's own input lag, 0 when run_ahead_enabled is off
+pub fn get_run_ahead_frames(config: &HashMap<String, String>) -> u32 {
+    if config["run_ahead_enabled"] != "true" {
+        return 0;
+    }
+    match config["run_ahead_frames"].parse::<u32>() {
+        Ok(frames) => frames.clamp(1, 6),
+        Err(_) => {
+            println!(
+                "Invalid run_ahead_frames: {} using 1",
+                config["run_ahead_frames"]
+            );
+            1
+        }
+    }
+}
+
+// nearest, bilinear or scale2x
+pub fn get_video_filter(config: &HashMap<String, String>) -> VideoFilter {
+    VideoFilter::from_name(&config["video_filter"]).unwrap_or_else(|| {
+        println!(
+            "Invalid video_filter: {} using nearest",
+            config["video_filter"]
+        );
+        VideoFilter::Nearest
+    })
+}
+
+pub fn get_aspect_mode(config: &HashMap<String, String>) -> AspectMode {
+    AspectMode::from_name(&config["video_aspect_mode"]).unwrap_or_else(|| {
+        println!(
+            "Invalid video_aspect_mode: {} using stretch",
+            config["video_aspect_mode"]
+        );
+        AspectMode::Stretch
+    })
+}
+
+// title, overlay or off
+pub fn get_fps_display(config: &HashMap<String, String>) -> FpsDisplay {
+    FpsDisplay::from_name(&config["video_fps_display"]).unwrap_or_else(|| {
+        println!(
+            "Invalid video_fps_display: {} using title",
+            config["video_fps_display"]
+        );
+        FpsDisplay::Title
+    })
+}
+
+pub fn get_load_fail_alert(config: &HashMap<String, String>) -> LoadFailAlert {
+    LoadFailAlert::from_name(&config["savestate_load_fail_alert"]).unwrap_or_else(|| {
+        println!(
+            "Invalid savestate_load_fail_alert: {} using none",
+            config["savestate_load_fail_alert"]
+        );
+        LoadFailAlert::None
+    })
+}
+
+// The FPS shown is the average over this long, shorter updates faster but jumps around more
+pub fn get_fps_sample_interval(config: &HashMap<String, String>) -> Duration {
+    match config["fps_sample_interval_ms"].parse::<u64>() {
+        Ok(interval_ms) => Duration::from_millis(interval_ms.clamp(100, 10000)),
+        Err(_) => {
+            println!(
+                "Invalid fps_sample_interval_ms: {} using 1000",
+                config["fps_sample_interval_ms"]
+            );
+            Duration::from_millis(1000)
+        }
+    }
+}
+
+// video_crop_top, video_crop_bottom, video_crop_left and video_crop_right in pixels
+pub fn get_overscan_crop(config: &HashMap<String, String>) -> OverscanCrop {
+    let crop = |key: &str| match config[key].parse::<usize>() {
+        Ok(pixels) => pixels,
+        Err(_) => {
+            println!("Invalid {}: {} using 0", key, config[key]);
+            0
+        }
+    };
+    OverscanCrop {
+        top: crop("video_crop_top"),
+        bottom: crop("video_crop_bottom"),
+        left: crop("video_crop_left"),
+        right: crop("video_crop_right"),
+    }
+}
+
+// video_gamma and video_brightness, both have to be above 0
+pub fn get_color_correction(config: &HashMap<String, String>) -> ColorCorrection {
+    let factor = |key: &str| match config[key].parse::<f64>() {
+        Ok(factor) if factor > 0.0 && factor.is_finite() => factor,
+        _ => {
+            println!("Invalid {}: {} using 1.0", key, config[key]);
+            1.0
+        }
+    };
+    ColorCorrection::new(factor("video_gamma"), factor("video_brightness"))
+}
+
+// RetroArch writes "default" when no system directory has been chosen
+pub fn get_system_directory(config: &HashMap<String, String>) -> &str {
+    match config["system_directory"].as_str() {
+        "" | "default" => "System",
+        system_directory => system_directory,
+    }
+}
+
+// none or zstd
+pub fn get_savestate_compression(config: &HashMap<String, String>) -> StateCompression {
+    match config["savestate_compression"].to_ascii_lowercase().as_str() {
+        "none" => StateCompression::None,
+        "zstd" => StateCompression::Zstd,
+        _ => {
+            println!(
+                "Invalid savestate_compression: {} using none",
+                config["savestate_compression"]
+            );
+            StateCompression::None
+        }
+    }
+}
+
+// The save state slot to load as soon as the game has loaded, empty to boot normally
+pub fn get_autoload_slot(config: &HashMap<String, String>) -> Option<u8> {
+    let autoload_slot = config["autoload_slot"].trim();
+    if autoload_slot.is_empty() {
+        return None;
+    }
+    match autoload_slot.parse::<u8>() {
+        Ok(slot) => Some(slot),
+        Err(_) => {
+            println!("Invalid autoload_slot: {} booting normally", autoload_slot);
+            None
+        }
+    }
+}
+
+// Quarter turns counter-clockwise from video_rotation (0, 90, 180 or 270 degrees), None to use the
+// rotation the core asks for
+pub fn get_video_rotation(config: &HashMap<String, String>) -> Option<u32> {
+    let video_rotation = config["video_rotation"].trim();
+    if video_rotation.is_empty() {
+        return None;
+    }
+    match video_rotation.parse::<u32>() {
+        Ok(degrees) if degrees % 90 == 0 && degrees < 360 => Some(degrees / 90),
+        _ => {
+            println!(
+                "Invalid video_rotation: {} using the core's rotation",
+                video_rotation
+            );
+            None
+        }
+    }
+}
+
+// The window is the core's base size times video_scale, None (the default) for a 640x480 window
+pub fn get_video_scale(config: &HashMap<String, String>) -> Option<u32> {
+    let video_scale = config["video_scale"].trim();
+    if video_scale.is_empty() {
+        return None;
+    }
+    match video_scale.parse::<u32>() {
+        Ok(scale) if scale > 0 => Some(scale.min(get_video_scale_max(config))),
+        _ => {
+            println!("Invalid video_scale: {} using a 640x480 window", video_scale);
+            None
+        }
+    }
+}
+
+// The largest scale input_scale_increase goes up to
+pub fn get_video_scale_max(config: &HashMap<String, String>) -> u32 {
+    match config["video_scale_max"].parse::<u32>() {
+        Ok(scale) if scale > 0 => scale,
+        _ => {
+            println!("Invalid video_scale_max: {} using 6", config["video_scale_max"]);
+            6
+        }
+    }
+}
+
+// window_min_size and window_max_size, each WIDTHxHEIGHT or empty for no limit
+pub fn get_window_size_limits(config: &HashMap<String, String>) -> WindowSizeLimits {
+    let size = |key: &str| {
+        let value = config[key].trim();
+        if value.is_empty() {
+            return None;
+        }
+        let parsed = value
+            .to_ascii_lowercase()
+            .split_once('x')
+            .and_then(|(width, height)| {
+                Some((width.trim().parse::<usize>().ok()?, height.trim().parse::<usize>().ok()?))
+            })
+            .filter(|(width, height)| *width > 0 && *height > 0);
+        if parsed.is_none() {
+            println!("Invalid {}: {} using no limit", key, value);
+        }
+        parsed
+    };
+    WindowSizeLimits {
+        min: size("window_min_size"),
+        max: size("window_max_size"),
+    }
+}
+
+// video_rgb565_byte_order (native, little or big), flipped by video_rgb565_swap_bytes
+pub fn get_rgb565_byte_order(config: &HashMap<String, String>) -> ByteOrder {
+    let byte_order = ByteOrder::from_name(&config["video_rgb565_byte_order"]).unwrap_or_else(|| {
+        println!(
+            "Invalid video_rgb565_byte_order: {} using native",
+            config["video_rgb565_byte_order"]
+        );
+        ByteOrder::native()
+    });
+    if config["video_rgb565_swap_bytes"] == "true" {
+        byte_order.swapped()
+    } else {
+        byte_order
+    }
+}
+
+// force_region (ntsc or pal) replaces the region the core reports, None (empty) to use the core's
+pub fn get_forced_region(config: &HashMap<String, String>) -> Option<Region> {
+    let force_region = config["force_region"].trim();
+    if force_region.is_empty() {
+        return None;
+    }
+    Region::from_name(force_region).or_else(|| {
+        println!("Invalid force_region: {} using the core's region", force_region);
+        None
+    })
+}
+
+// The RETRO_LANGUAGE_* value for frontend_language, cores that are localized use it to pick their language
+pub fn get_frontend_language(config: &HashMap<String, String>) -> u32 {
+    match language_from_name(&config["frontend_language"]) {
+        Some(language) => language,
+        None => {
+            println!(
+                "Invalid frontend_language: {} using english",
+                config["frontend_language"]
+            );
+            LANGUAGE_ENGLISH
+        }
+    }
+}
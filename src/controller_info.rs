@@ -0,0 +1,108 @@
+use crate::core_info::c_string_to_string;
+use libretro_sys::{
+    ControllerInfo, DEVICE_ANALOG, DEVICE_JOYPAD, DEVICE_KEYBOARD, DEVICE_LIGHTGUN, DEVICE_MASK,
+    DEVICE_MOUSE, DEVICE_NONE, DEVICE_POINTER,
+};
+
+// The generic libretro devices by name, the names input_libretro_device_p* takes besides numbers
+const DEVICE_NAMES: [(&str, u32); 7] = [
+    ("none", DEVICE_NONE),
+    ("joypad", DEVICE_JOYPAD),
+    ("mouse", DEVICE_MOUSE),
+    ("keyboard", DEVICE_KEYBOARD),
+    ("lightgun", DEVICE_LIGHTGUN),
+    ("analog", DEVICE_ANALOG),
+    ("pointer", DEVICE_POINTER),
+];
+
+// A device the core said a port can take through ENVIRONMENT_SET_CONTROLLER_INFO, the id may be a
+// subclass of a generic device e.g. a "DualShock" analog pad
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ControllerType {
+    pub description: String,
+    pub id: u32,
+}
+
+// Copies the core's array of ports, which ends with an entry that has no types
+/// # Safety
+/// `info` must be null or point to an array of ControllerInfo terminated by a zeroed entry.
+pub(crate) unsafe fn parse_controller_info(
+    info: *const ControllerInfo,
+) -> Vec<Vec<ControllerType>> {
+    let mut ports = Vec::new();
+    if info.is_null() {
+        return ports;
+    }
+    let mut index = 0;
+    loop {
+        let port = &*info.add(index);
+        if port.types.is_null() || port.num_types == 0 {
+            break;
+        }
+        ports.push(
+            std::slice::from_raw_parts(port.types, port.num_types as usize)
+                .iter()
+                .map(|controller| ControllerType {
+                    description: c_string_to_string(controller.desc),
+                    id: controller.id,
+                })
+                .collect(),
+        );
+        index += 1;
+    }
+    ports
+}
+
+// What a port is set to when the config doesn't say: the analog pad the core lists, then its joypad,
+// then whatever it lists first that isn't nothing at all. None leaves the core's own default alone.
+pub fn default_device(types: &[ControllerType]) -> Option<u32> {
+    let of_kind = |kind: u32| {
+        types
+            .iter()
+            .find(|controller| controller.id & DEVICE_MASK == kind)
+    };
+    of_kind(DEVICE_ANALOG)
+        .or_else(|| of_kind(DEVICE_JOYPAD))
+        .or_else(|| types.iter().find(|controller| controller.id != DEVICE_NONE))
+        .map(|controller| controller.id)
+}
+
+// input_libretro_device_p* is a device number (RetroArch's, 1 joypad, 4 lightgun), a generic device name
+// or the description of one of the port's types e.g. "DualShock". Empty picks the default_device.
+pub fn parse_device(device: &str, types: &[ControllerType]) -> Result<Option<u32>, String> {
+    let device = device.trim();
+    if device.is_empty() {
+        return Ok(default_device(types));
+    }
+    if let Ok(id) = device.parse::<u32>() {
+        return Ok(Some(id));
+    }
+    DEVICE_NAMES
+        .iter()
+        .find(|(name, _)| name.eq_ignore_ascii_case(device))
+        .map(|(_, id)| *id)
+        .or_else(|| {
+            types
+                .iter()
+                .find(|controller| controller.description.eq_ignore_ascii_case(device))
+                .map(|controller| controller.id)
+        })
+        .map(Some)
+        .ok_or(format!("Unknown device: {}", device))
+}
+
+// e.g. "DualShock (id 261)"
+pub fn describe_device(id: u32, types: &[ControllerType]) -> String {
+    let name = types
+        .iter()
+        .find(|controller| controller.id == id)
+        .map(|controller| controller.description.as_str())
+        .or_else(|| {
+            DEVICE_NAMES
+                .iter()
+                .find(|(_, device)| *device == id)
+                .map(|(name, _)| *name)
+        })
+        .unwrap_or("unknown");
+    format!("{} (id {})", name, id)
+}
@@ -0,0 +1,40 @@
+use libretro_sys::CoreAPI;
+use std::ffi::c_void;
+
+// The parts of a libretro core the frontend logic drives. CoreAPI is the real implementation, tests
+// can provide their own to exercise save states etc. without loading a core or touching the global state.
+pub trait LibretroCore {
+    fn run(&mut self);
+    fn reset(&mut self);
+    fn serialize_size(&self) -> usize;
+    fn serialize(&self, data: &mut [u8]) -> bool;
+    fn unserialize(&mut self, data: &[u8]) -> bool;
+}
+
+// The function pointers are only valid while the core's Library is loaded, which Emulator guarantees
+impl LibretroCore for CoreAPI {
+    fn run(&mut self) {
+        unsafe { (self.retro_run)() }
+    }
+
+    fn reset(&mut self) {
+        unsafe { (self.retro_reset)() }
+    }
+
+    fn serialize_size(&self) -> usize {
+        unsafe { (self.retro_serialize_size)() }
+    }
+
+    fn serialize(&self, data: &mut [u8]) -> bool {
+        // libretro_sys drops the bool retro_serialize returns, so all we can check is that there was room
+        if data.len() < self.serialize_size() {
+            return false;
+        }
+        unsafe { (self.retro_serialize)(data.as_mut_ptr() as *mut c_void, data.len()) };
+        true
+    }
+
+    fn unserialize(&mut self, data: &[u8]) -> bool {
+        unsafe { (self.retro_unserialize)(data.as_ptr() as *const c_void, data.len()) }
+    }
+}
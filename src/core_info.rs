@@ -0,0 +1,165 @@
+use crate::config::expand_path;
+use crate::controller_info::ControllerType;
+use crate::environment::libretro_environment_callback;
+use crate::rom::{is_url, url_file_name};
+use crate::state::{EmulatorState, CURRENT_EMULATOR_STATE};
+use crate::subsystem::Subsystem;
+use libloading::Library;
+use libretro_sys::{CoreAPI, EnvironmentFn, SystemInfo};
+use std::ffi::CStr;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::ptr;
+
+// What a core reports about itself through retro_get_system_info
+#[derive(Clone, Debug)]
+pub struct CoreInfo {
+    pub library_name: String,
+    pub library_version: String,
+    pub valid_extensions: Vec<String>,
+    pub need_fullpath: bool,
+    pub block_extract: bool,
+}
+
+impl CoreInfo {
+    // Goes by the ROM's extension (of the URL's file name for a download). A core that lists no extensions
+    // is assumed to load anything. The error lists what the core does load.
+    pub fn check_rom_extension(&self, rom_path: &str) -> Result<(), String> {
+        if self.valid_extensions.is_empty() {
+            return Ok(());
+        }
+        let file_name = match is_url(rom_path) {
+            true => url_file_name(rom_path),
+            false => rom_path.to_string(),
+        };
+        let extension = Path::new(&file_name)
+            .extension()
+            .map(|extension| extension.to_string_lossy().to_lowercase());
+        if extension.as_ref().is_some_and(|extension| self.valid_extensions.contains(extension)) {
+            return Ok(());
+        }
+        Err(format!(
+            "{} doesn't look like a ROM for {}, it loads {}",
+            rom_path,
+            self.library_name,
+            self.valid_extensions
+                .iter()
+                .map(|extension| format!(".{}", extension))
+                .collect::<Vec<_>>()
+                .join(" ")
+        ))
+    }
+}
+
+pub(crate) unsafe fn c_string_to_string(c_string: *const libc::c_char) -> String {
+    if c_string.is_null() {
+        return String::new();
+    }
+    CStr::from_ptr(c_string).to_string_lossy().into_owned()
+}
+
+// Asks an already loaded core whether it can only load content from a path rather than from memory
+pub(crate) unsafe fn core_needs_fullpath(core_api: &CoreAPI) -> bool {
+    let mut system_info = SystemInfo {
+        library_name: ptr::null(),
+        library_version: ptr::null(),
+        valid_extensions: ptr::null(),
+        need_fullpath: false,
+        block_extract: false,
+    };
+    (core_api.retro_get_system_info)(&mut system_info);
+    system_info.need_fullpath
+}
+
+// Loads the core just long enough to ask for its system info, retro_get_system_info is allowed to
+// be called before retro_init so the core is never initialised
+pub fn read_core_info(library_path: &Path) -> Result<CoreInfo, String> {
+    unsafe {
+        let dylib = Library::new(expand_path(&library_path.to_string_lossy()))
+            .map_err(|e| format!("Failed to load Core {}: {}", library_path.display(), e))?;
+        let retro_get_system_info = *dylib
+            .get::<unsafe extern "C" fn(info: *mut SystemInfo)>(b"retro_get_system_info")
+            .map_err(|e| format!("Not a libretro core {}: {}", library_path.display(), e))?;
+        let mut system_info = SystemInfo {
+            library_name: ptr::null(),
+            library_version: ptr::null(),
+            valid_extensions: ptr::null(),
+            need_fullpath: false,
+            block_extract: false,
+        };
+        retro_get_system_info(&mut system_info);
+        // Copy everything out before the library (which owns these strings) is closed
+        Ok(CoreInfo {
+            library_name: c_string_to_string(system_info.library_name),
+            library_version: c_string_to_string(system_info.library_version),
+            valid_extensions: c_string_to_string(system_info.valid_extensions)
+                .split('|')
+                .filter(|extension| !extension.is_empty())
+                .map(|extension| extension.to_lowercase())
+                .collect(),
+            need_fullpath: system_info.need_fullpath,
+            block_extract: system_info.block_extract,
+        })
+    }
+}
+
+// Cores advertise their subsystems and controllers from inside retro_set_environment, so this hands the core
+// our environment callback and takes out what it set. It goes through the global state so no Emulator can
+// be running.
+fn read_from_set_environment<T>(
+    library_path: &Path,
+    take: impl FnOnce(&mut EmulatorState) -> T,
+) -> Result<T, String> {
+    unsafe {
+        let dylib = Library::new(expand_path(&library_path.to_string_lossy()))
+            .map_err(|e| format!("Failed to load Core {}: {}", library_path.display(), e))?;
+        let retro_set_environment = *dylib
+            .get::<unsafe extern "C" fn(callback: EnvironmentFn)>(b"retro_set_environment")
+            .map_err(|e| format!("Not a libretro core {}: {}", library_path.display(), e))?;
+        retro_set_environment(libretro_environment_callback);
+        let value = take(&mut *std::ptr::addr_of_mut!(CURRENT_EMULATOR_STATE));
+        CURRENT_EMULATOR_STATE = EmulatorState::new();
+        Ok(value)
+    }
+}
+
+pub fn read_core_subsystems(library_path: &Path) -> Result<Vec<Subsystem>, String> {
+    read_from_set_environment(library_path, |state| std::mem::take(&mut state.subsystems))
+}
+
+// Some cores only list their controllers once a game is loaded, for those this is empty
+pub fn read_core_controller_types(library_path: &Path) -> Result<Vec<Vec<ControllerType>>, String> {
+    read_from_set_environment(library_path, |state| std::mem::take(&mut state.controller_types))
+}
+
+// The first core in the directory (by name) that lists the ROM's extension, for --menu when there's no -L
+pub fn find_core_for_rom(cores_directory: &Path, rom_path: &str) -> Option<PathBuf> {
+    find_cores(cores_directory).ok()?.into_iter().find(|core_path| {
+        read_core_info(core_path).is_ok_and(|core_info| {
+            !core_info.valid_extensions.is_empty() && core_info.check_rom_extension(rom_path).is_ok()
+        })
+    })
+}
+
+// Every shared library in the directory (.so, .dll or .dylib depending on the platform), sorted by name
+pub fn find_cores(cores_directory: &Path) -> Result<Vec<PathBuf>, String> {
+    let entries = fs::read_dir(cores_directory).map_err(|e| {
+        format!(
+            "Failed to read cores directory {}: {}",
+            cores_directory.display(),
+            e
+        )
+    })?;
+    let mut cores: Vec<PathBuf> = entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.is_file()
+                && path.extension().and_then(|extension| extension.to_str())
+                    == Some(std::env::consts::DLL_EXTENSION)
+        })
+        .collect();
+    cores.sort();
+    Ok(cores)
+}
+
@@ -0,0 +1,237 @@
+use crate::config::parse_retroarch_config;
+use crate::core_info::c_string_to_string;
+use crate::save_state::write_file_atomically;
+use libretro_sys::Variable;
+use std::collections::{BTreeMap, HashMap};
+use std::ffi::c_void;
+use std::path::{Path, PathBuf};
+
+// A setting the core declared through ENVIRONMENT_SET_VARIABLES or one of the SET_CORE_OPTIONS commands
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CoreVariable {
+    pub key: String,
+    pub description: String,
+    pub values: Vec<String>,
+    pub value: String,
+}
+
+// The core options version we answer ENVIRONMENT_GET_CORE_OPTIONS_VERSION with, cores that see 1 or more
+// use SET_CORE_OPTIONS (v1) or SET_CORE_OPTIONS_V2 instead of the legacy SET_VARIABLES
+pub const CORE_OPTIONS_VERSION: u32 = 2;
+
+// Every core option definition has a fixed size array of values ending with a null value
+const NUM_CORE_OPTION_VALUES_MAX: usize = 128;
+
+// The libretro core options structs, libretro_sys predates them
+#[repr(C)]
+pub(crate) struct CoreOptionValue {
+    pub value: *const libc::c_char,
+    pub label: *const libc::c_char,
+}
+
+#[repr(C)]
+pub(crate) struct CoreOptionDefinition {
+    pub key: *const libc::c_char,
+    pub desc: *const libc::c_char,
+    pub info: *const libc::c_char,
+    pub values: [CoreOptionValue; NUM_CORE_OPTION_VALUES_MAX],
+    pub default_value: *const libc::c_char,
+}
+
+#[repr(C)]
+pub(crate) struct CoreOptionsIntl {
+    pub us: *const CoreOptionDefinition,
+    pub local: *const CoreOptionDefinition,
+}
+
+#[repr(C)]
+pub(crate) struct CoreOptionV2Definition {
+    pub key: *const libc::c_char,
+    pub desc: *const libc::c_char,
+    pub desc_categorized: *const libc::c_char,
+    pub info: *const libc::c_char,
+    pub info_categorized: *const libc::c_char,
+    pub category_key: *const libc::c_char,
+    pub values: [CoreOptionValue; NUM_CORE_OPTION_VALUES_MAX],
+    pub default_value: *const libc::c_char,
+}
+
+// Categories are only used to group options in a menu so they aren't read
+#[repr(C)]
+pub(crate) struct CoreOptionsV2 {
+    pub categories: *const c_void,
+    pub definitions: *const CoreOptionV2Definition,
+}
+
+#[repr(C)]
+pub(crate) struct CoreOptionsV2Intl {
+    pub us: *const CoreOptionsV2,
+    pub local: *const CoreOptionsV2,
+}
+
+// The default is used if it's one of the values, otherwise the first value like the legacy variables
+pub fn core_option(
+    key: &str,
+    description: &str,
+    values: Vec<String>,
+    default_value: &str,
+) -> CoreVariable {
+    let value = if values.iter().any(|value| value == default_value) {
+        default_value.to_string()
+    } else {
+        values.first().cloned().unwrap_or_default()
+    };
+    CoreVariable {
+        key: key.to_string(),
+        description: description.to_string(),
+        values,
+        value,
+    }
+}
+
+// The legacy value string looks like "Description; first option|second option", the first option is the default
+pub fn parse_variable_definition(key: &str, definition: &str) -> CoreVariable {
+    let (description, options) = definition.split_once("; ").unwrap_or(("", definition));
+    let values: Vec<String> = options.split('|').map(String::from).collect();
+    core_option(key, description, values, "")
+}
+
+// Copies the core's array of variables, which ends with an entry that has a null key
+/// # Safety
+/// `variables` must be null or point to an array of Variable terminated by a zeroed entry.
+pub(crate) unsafe fn parse_variables(variables: *const Variable) -> Vec<CoreVariable> {
+    let mut core_variables = Vec::new();
+    if variables.is_null() {
+        return core_variables;
+    }
+    let mut index = 0;
+    loop {
+        let variable = &*variables.add(index);
+        if variable.key.is_null() {
+            break;
+        }
+        core_variables.push(parse_variable_definition(
+            &c_string_to_string(variable.key),
+            &c_string_to_string(variable.value),
+        ));
+        index += 1;
+    }
+    core_variables
+}
+
+unsafe fn parse_option_values(
+    values: &[CoreOptionValue; NUM_CORE_OPTION_VALUES_MAX],
+) -> Vec<String> {
+    values
+        .iter()
+        .take_while(|value| !value.value.is_null())
+        .map(|value| c_string_to_string(value.value))
+        .collect()
+}
+
+// Copies the core's v1 option definitions (ENVIRONMENT_SET_CORE_OPTIONS), ending with a null key
+/// # Safety
+/// `definitions` must be null or point to an array of CoreOptionDefinition terminated by a zeroed entry.
+pub(crate) unsafe fn parse_core_option_definitions(
+    definitions: *const CoreOptionDefinition,
+) -> Vec<CoreVariable> {
+    let mut core_variables = Vec::new();
+    if definitions.is_null() {
+        return core_variables;
+    }
+    let mut index = 0;
+    loop {
+        let definition = &*definitions.add(index);
+        if definition.key.is_null() {
+            break;
+        }
+        core_variables.push(core_option(
+            &c_string_to_string(definition.key),
+            &c_string_to_string(definition.desc),
+            parse_option_values(&definition.values),
+            &c_string_to_string(definition.default_value),
+        ));
+        index += 1;
+    }
+    core_variables
+}
+
+// Copies the core's v2 option definitions (ENVIRONMENT_SET_CORE_OPTIONS_V2), ending with a null key
+/// # Safety
+/// `options` must be null or point to a CoreOptionsV2 whose definitions are terminated by a zeroed entry.
+pub(crate) unsafe fn parse_core_options_v2(options: *const CoreOptionsV2) -> Vec<CoreVariable> {
+    let mut core_variables = Vec::new();
+    if options.is_null() || (*options).definitions.is_null() {
+        return core_variables;
+    }
+    let definitions = (*options).definitions;
+    let mut index = 0;
+    loop {
+        let definition = &*definitions.add(index);
+        if definition.key.is_null() {
+            break;
+        }
+        core_variables.push(core_option(
+            &c_string_to_string(definition.key),
+            &c_string_to_string(definition.desc),
+            parse_option_values(&definition.values),
+            &c_string_to_string(definition.default_value),
+        ));
+        index += 1;
+    }
+    core_variables
+}
+
+// Sets each variable that has a value in `values` (the config with any --core-option overrides), values the
+// core didn't list as an option are ignored with a warning
+pub fn apply_core_option_values(variables: &mut [CoreVariable], values: &BTreeMap<String, String>) {
+    for variable in variables {
+        match values.get(&variable.key) {
+            Some(value) if variable.values.contains(value) => variable.value = value.clone(),
+            Some(value) => println!(
+                "Core option {} = {} isn't one of {:?}, using {}",
+                variable.key, value, variable.values, variable.value
+            ),
+            None => {}
+        }
+    }
+}
+
+// A --core-option argument, e.g. "stub_palette=amber"
+pub fn parse_core_option_argument(argument: &str) -> Result<(String, String), String> {
+    match argument.split_once('=') {
+        Some((key, value)) if !key.trim().is_empty() => {
+            Ok((key.trim().to_string(), value.trim().to_string()))
+        }
+        _ => Err(format!(
+            "Invalid core option: {} expected key=value",
+            argument
+        )),
+    }
+}
+
+// Per game core options live next to the ROM as {rom without its extension}.opt, in the same key = "value"
+// format as the config. They override the config for that game and --core-option overrides them.
+pub fn game_options_path(rom_path: &str) -> PathBuf {
+    Path::new(rom_path).with_extension("opt")
+}
+
+// Empty when the game has no options file yet
+pub fn load_game_options(path: &Path) -> Result<HashMap<String, String>, String> {
+    if !path.exists() {
+        return Ok(HashMap::new());
+    }
+    parse_retroarch_config(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))
+}
+
+// Every option the core declared with its current value, sorted by key. Written atomically so a game
+// launched while it's being saved reads either the old file or the new one.
+pub fn save_game_options(path: &Path, variables: &[CoreVariable]) -> Result<(), String> {
+    let mut variables: Vec<&CoreVariable> = variables.iter().collect();
+    variables.sort_by(|a, b| a.key.cmp(&b.key));
+    let contents: String = variables
+        .iter()
+        .map(|variable| format!("{} = \"{}\"\n", variable.key, variable.value))
+        .collect();
+    write_file_atomically(path, contents.as_bytes())
+}
@@ -0,0 +1,798 @@
+use crate::bios::verify_bios;
+use crate::config::{
+    get_forced_region, get_frontend_language, get_max_save_slots, get_rgb565_byte_order, get_savestate_compression,
+    get_system_directory,
+};
+use crate::controller_info::{default_device, describe_device, parse_device, ControllerType};
+use crate::core::LibretroCore;
+use crate::core_info::core_needs_fullpath;
+use crate::core_options::CoreVariable;
+use crate::frame_hash::frame_crc32;
+use crate::input::{key_to_retrok, LightgunState};
+use crate::libretro::{init_core, load_core, load_rom_file, load_subsystem_rom_files};
+use crate::ram_watch::{self, WatchEntry};
+use crate::recording::RawAudioDump;
+use crate::region::{self, Region};
+use crate::replay::{self, InputPlayback};
+use crate::save_state::{self, StateVerification};
+use crate::rom::RomLoadOptions;
+use crate::rumble::RumbleState;
+use crate::screenshot;
+use crate::state::{EmulatorState, CURRENT_EMULATOR_STATE};
+use crate::video;
+use libloading::Library;
+use libretro_sys::{CoreAPI, GameGeometry, PixelFormat, SystemAvInfo, SystemTiming};
+use minifb::Key;
+use std::collections::HashMap;
+use std::ffi::CString;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+// A loaded core + ROM that can be driven one frame at a time without any window or audio device,
+// the RustroArch binary is just one user of this. Because the libretro callbacks share a global
+// state only one Emulator should exist at a time.
+//
+// To swap cores at runtime drop the current Emulator before creating the next one. Dropping it
+// unloads the game, deinitialises the core, clears the global state (which may hold pointers into
+// the core's memory) and only then closes the Library, so once the drop has finished nothing
+// references the old core and its callbacks can no longer be invoked.
+pub struct Emulator {
+    core_api: CoreAPI,
+    // Owns the code core_api points into, it must outlive every call made through core_api.
+    // None when the core wasn't loaded from a shared library (from_core_api)
+    library: Option<Library>,
+    config: HashMap<String, String>,
+    av_info: SystemAvInfo,
+    region: Region,
+    last_frame_start: Option<Instant>, // when the last measured frame was run, for the frame time callback
+    fixed_frame_time: bool,
+    audio_dump: Option<RawAudioDump>, // --dump-audio
+}
+
+impl Emulator {
+    pub fn new(
+        core_path: &str,
+        rom_path: &str,
+        config: HashMap<String, String>,
+    ) -> Result<Emulator, String> {
+        Emulator::load(core_path, &[rom_path.to_string()], None, config)
+    }
+
+    // Loads several ROMs at once through retro_load_game_special, `subsystem` is the ident or id of one of
+    // the subsystems the core supports (e.g. "sgb" for the Super Game Boy)
+    pub fn new_with_subsystem(
+        core_path: &str,
+        subsystem: &str,
+        rom_paths: &[String],
+        config: HashMap<String, String>,
+    ) -> Result<Emulator, String> {
+        Emulator::load(core_path, rom_paths, Some(subsystem), config)
+    }
+
+    // Drives a core that isn't in a shared library, e.g. one written as extern "C" functions in a test
+    /// # Safety
+    /// Every function in core_api must stay callable until the returned Emulator has been dropped.
+    pub unsafe fn from_core_api(
+        core_api: CoreAPI,
+        rom_path: &str,
+        config: HashMap<String, String>,
+    ) -> Result<Emulator, String> {
+        Emulator::start(core_api, None, "CoreAPI", &[rom_path.to_string()], None, config)
+    }
+
+    fn load(
+        core_path: &str,
+        rom_paths: &[String],
+        subsystem: Option<&str>,
+        config: HashMap<String, String>,
+    ) -> Result<Emulator, String> {
+        println!("Setting up Core");
+        let (core_api, library) = unsafe { load_core(&core_path.to_string())? };
+        Emulator::start(core_api, Some(library), core_path, rom_paths, subsystem, config)
+    }
+
+    fn start(
+        core_api: CoreAPI,
+        library: Option<Library>,
+        core_name: &str,
+        rom_paths: &[String],
+        subsystem: Option<&str>,
+        config: HashMap<String, String>,
+    ) -> Result<Emulator, String> {
+        unsafe {
+            // The first ROM names the save states, RAM watches etc.
+            CURRENT_EMULATOR_STATE.rom_name = rom_paths.first().ok_or("No ROM was given")?.clone();
+            CURRENT_EMULATOR_STATE.core_name = core_name.to_string();
+            CURRENT_EMULATOR_STATE.buttons_pressed = vec![vec![0; 16]];
+            CURRENT_EMULATOR_STATE.rgb565_byte_order = get_rgb565_byte_order(&config);
+            CURRENT_EMULATOR_STATE.swap_red_blue = config["video_swap_rb"] == "true";
+            CURRENT_EMULATOR_STATE.threaded_convert = config["video_threaded_convert"] == "true";
+            CURRENT_EMULATOR_STATE.language = get_frontend_language(&config);
+            CURRENT_EMULATOR_STATE.core_option_values =
+                config.iter().map(|(key, value)| (key.clone(), value.clone())).collect();
+            // Environment variables
+            let system_directory = get_system_directory(&config);
+            if let Err(err) = verify_bios(&config, core_name, Path::new(system_directory)) {
+                CURRENT_EMULATOR_STATE = EmulatorState::new();
+                return Err(err);
+            }
+            CURRENT_EMULATOR_STATE.system_directory = Some(
+                CString::new(system_directory)
+                    .map_err(|e| format!("Invalid system_directory {}: {}", system_directory, e))?,
+            );
+
+            if let Err(err) = init_core(&core_api) {
+                CURRENT_EMULATOR_STATE = EmulatorState::new();
+                return Err(err);
+            }
+
+            println!("About to load ROM: {:?}", &*std::ptr::addr_of!(CURRENT_EMULATOR_STATE.rom_name));
+            let mut rom_load_options = RomLoadOptions::from_config(&config);
+            rom_load_options.need_fullpath = core_needs_fullpath(&core_api);
+            let load_result = match subsystem {
+                Some(subsystem) => {
+                    load_subsystem_rom_files(&core_api, subsystem, rom_paths, &rom_load_options)
+                }
+                None => load_rom_file(&core_api, &*std::ptr::addr_of!(CURRENT_EMULATOR_STATE.rom_name), &rom_load_options),
+            };
+            if let Err(err) = load_result {
+                (core_api.retro_deinit)();
+                CURRENT_EMULATOR_STATE = EmulatorState::new();
+                return Err(err);
+            }
+
+            set_controller_devices(&core_api, &config);
+
+            let mut av_info = SystemAvInfo {
+                geometry: GameGeometry {
+                    base_width: 0,
+                    base_height: 0,
+                    max_width: 0,
+                    max_height: 0,
+                    aspect_ratio: 0.0,
+                },
+                timing: SystemTiming {
+                    fps: 0.0,
+                    sample_rate: 0.0,
+                },
+            };
+            (core_api.retro_get_system_av_info)(&mut av_info);
+            println!("AV Info: {:?}", &av_info);
+            let region = match get_forced_region(&config) {
+                Some(region) => {
+                    println!("Region forced to {} (force_region)", region.name());
+                    region
+                }
+                None => {
+                    let retro_region = (core_api.retro_get_region)();
+                    Region::from_retro_region(retro_region).unwrap_or_else(|| {
+                        println!("Core reported an unknown region {}, assuming NTSC", retro_region);
+                        Region::Ntsc
+                    })
+                }
+            };
+            println!("Content region: {}", region.name());
+            use_region_fps_if_unusable(&mut av_info, region);
+            CURRENT_EMULATOR_STATE.av_info = Some(av_info.clone());
+
+            Ok(Emulator {
+                core_api,
+                library,
+                config,
+                av_info,
+                region,
+                last_frame_start: None,
+                fixed_frame_time: false,
+                audio_dump: None,
+            })
+        }
+    }
+
+    // Tears down this core and loads another one (and its ROM) in its place with `config`, which has the new
+    // game's core options. If that fails the previous core is already gone so the caller decides whether to
+    // reload it or give up.
+    pub fn swap_core(
+        self,
+        core_path: &str,
+        rom_path: &str,
+        config: HashMap<String, String>,
+    ) -> Result<Emulator, String> {
+        // The old core has to be fully unloaded first as the callbacks share the global state
+        drop(self);
+        Emulator::new(core_path, rom_path, config)
+    }
+
+    // Call the libRetro core to emulate a single frame
+    // Returns an error once the core has crashed (one of our callbacks panicked while it was running),
+    // the core's state can't be trusted after that so it shouldn't be run again
+    pub fn run_frame(&mut self) -> Result<(), String> {
+        self.run_core_frame(true)?;
+        // Run ahead's hidden frames don't come through here so their audio, which is thrown away, isn't dumped
+        if let Some(audio_dump) = self.audio_dump.as_mut() {
+            let sample_rate = self.av_info.timing.sample_rate as u32;
+            let samples = unsafe { (*std::ptr::addr_of!(CURRENT_EMULATOR_STATE.audio_data)).as_deref().unwrap_or(&[]) };
+            if let Err(err) = audio_dump.write_samples(samples, sample_rate) {
+                println!("{}, audio dump stopped", err);
+                self.audio_dump = None;
+            }
+        }
+        Ok(())
+    }
+
+    // Writes every sample the core sends from now on to `path` as raw s16le stereo
+    pub fn dump_audio(&mut self, path: &Path) -> Result<(), String> {
+        self.audio_dump = Some(RawAudioDump::create(path)?);
+        Ok(())
+    }
+
+    // The number of stereo frames dumped, None when --dump-audio wasn't used or the dump already stopped
+    pub fn finish_audio_dump(&mut self) -> Option<Result<u64, String>> {
+        self.audio_dump.take().map(RawAudioDump::finish)
+    }
+
+    // `measured` frames tell a core using the frame time callback how long it really was since the last
+    // one, the rest (run ahead's hidden frames) get the reference time
+    fn run_core_frame(&mut self, measured: bool) -> Result<(), String> {
+        unsafe {
+            // Cores can send audio in several batches per frame, start collecting them afresh
+            CURRENT_EMULATOR_STATE.audio_data = None;
+            let frame_time = (*std::ptr::addr_of!(CURRENT_EMULATOR_STATE.frame_time_callback)).clone().map(|frame_time| {
+                let usec = if measured {
+                    self.measure_frame_time(frame_time.reference)
+                } else {
+                    frame_time.reference
+                };
+                (frame_time.callback, usec)
+            });
+            let result = panic::catch_unwind(AssertUnwindSafe(|| {
+                if let Some((callback, usec)) = frame_time {
+                    callback(usec);
+                }
+                self.core_api.run()
+            }));
+            if result.is_err() || CURRENT_EMULATOR_STATE.core_crashed {
+                CURRENT_EMULATOR_STATE.core_crashed = true;
+                return Err(format!(
+                    "Core crashed: {} while running: {}",
+                    self.core_name(),
+                    self.rom_name()
+                ));
+            }
+            CURRENT_EMULATOR_STATE.frame_count += 1;
+            Ok(())
+        }
+    }
+
+    // Microseconds since the last measured frame started, or `reference` for the first frame and while the
+    // frame time is fixed
+    fn measure_frame_time(&mut self, reference: i64) -> i64 {
+        let now = Instant::now();
+        let usec = match self.last_frame_start.filter(|_| !self.fixed_frame_time) {
+            Some(last_frame_start) => now.duration_since(last_frame_start).as_micros() as i64,
+            None => reference,
+        };
+        self.last_frame_start = (!self.fixed_frame_time).then_some(now);
+        usec
+    }
+
+    // While set, cores using ENVIRONMENT_SET_FRAME_TIME_CALLBACK are told every frame took exactly their
+    // reference time, as the real time means nothing while fast forwarding or paused. The first frame after
+    // it's cleared gets the reference time too so the pause isn't counted.
+    pub fn set_fixed_frame_time(&mut self, fixed: bool) {
+        self.fixed_frame_time = fixed;
+    }
+
+    // Run ahead (RetroArch style): runs the real frame, then `frames` more with the same input to show what
+    // the game will look like once its own input lag has passed, then rolls back to the real frame with a
+    // save state. The audio is the real frame's and the frame counter only counts the real frame.
+    pub fn run_frame_ahead(&mut self, frames: u32) -> Result<(), String> {
+        self.run_frame()?;
+        let state = self.serialize();
+        let audio = unsafe { (*std::ptr::addr_of_mut!(CURRENT_EMULATOR_STATE.audio_data)).take() };
+        for _ in 0..frames {
+            self.run_core_frame(false)?;
+        }
+        if !self.unserialize(&state) {
+            return Err("Core failed to load the run ahead save state".to_string());
+        }
+        unsafe {
+            CURRENT_EMULATOR_STATE.audio_data = audio;
+            CURRENT_EMULATOR_STATE.frame_count -= frames as u64;
+        }
+        Ok(())
+    }
+
+    // How many frames have been run since the game was loaded or reset
+    pub fn frame_count(&self) -> u64 {
+        unsafe { CURRENT_EMULATOR_STATE.frame_count }
+    }
+
+    // Runs frames without presenting them until `frame` frames have been run, then returns what the core
+    // drew. Port 0's input comes from `playback` while it lasts, after that (or without one) nothing is
+    // pressed. Handy for seeking to a point in a replay or setting up a test.
+    pub fn run_to_frame(
+        &mut self,
+        frame: u64,
+        mut playback: Option<&mut InputPlayback>,
+    ) -> Result<&[u32], String> {
+        while self.frame_count() < frame {
+            let mask = playback.as_mut().and_then(|playback| playback.next_frame());
+            self.set_input(0, &replay::mask_to_buttons(mask.unwrap_or(0)));
+            self.run_frame()?;
+        }
+        Ok(self.frame_buffer())
+    }
+
+    // Sets the buttons for `port`, runs one frame and returns what the core drew, handy for driving a core
+    // from tests and scripts
+    pub fn step_with_input(&mut self, port: usize, buttons: &[i16]) -> Result<&[u32], String> {
+        self.set_input(port, buttons);
+        self.run_frame()?;
+        Ok(self.frame_buffer())
+    }
+
+    // --verify-savestate, saves a state, runs `frames` frames, loads the state and runs the same frames
+    // again with the same input. A core with reliable save states draws the same frames both times and
+    // ends up in the same state. Port 0's input comes from `playback` while it lasts, otherwise nothing
+    // is pressed.
+    pub fn verify_save_state(
+        &mut self,
+        frames: u64,
+        mut playback: Option<&mut InputPlayback>,
+    ) -> Result<StateVerification, String> {
+        let state = self.serialize();
+        if state.is_empty() {
+            return Err("The core doesn't support save states".to_string());
+        }
+        let masks: Vec<u16> = (0..frames)
+            .map(|_| playback.as_mut().and_then(|playback| playback.next_frame()).unwrap_or(0))
+            .collect();
+        let start_frame = self.frame_count();
+        let first_hashes = self.frame_hashes_with_input(&masks)?;
+        let first_state = self.serialize();
+
+        if !self.unserialize(&state) {
+            return Err("The core failed to load the save state".to_string());
+        }
+        unsafe { CURRENT_EMULATOR_STATE.frame_count = start_frame };
+        let second_hashes = self.frame_hashes_with_input(&masks)?;
+        let mismatch = first_hashes.iter().zip(&second_hashes).position(|(first, second)| first != second);
+        if let Some(index) = mismatch {
+            return Ok(StateVerification::FrameMismatch(start_frame + index as u64 + 1));
+        }
+        if self.serialize() != first_state {
+            return Ok(StateVerification::StateMismatch);
+        }
+        Ok(StateVerification::Passed)
+    }
+
+    // CRC32s of the frames drawn with each input mask in turn on port 0
+    fn frame_hashes_with_input(&mut self, masks: &[u16]) -> Result<Vec<Option<u32>>, String> {
+        let mut hashes = Vec::with_capacity(masks.len());
+        for mask in masks {
+            self.set_input(0, &replay::mask_to_buttons(*mask));
+            self.run_frame()?;
+            hashes.push(self.visible_frame().map(|frame| frame_crc32(&frame)));
+        }
+        Ok(hashes)
+    }
+
+    // The frame counter starts again from 0 along with the game. Loading a save state leaves it alone as
+    // libretro states don't say which frame they were saved on, it keeps counting the frames run.
+    pub fn reset(&mut self) {
+        self.core_api.reset();
+        unsafe { CURRENT_EMULATOR_STATE.frame_count = 0 };
+    }
+
+    pub fn save_state(&mut self, slot: u8) {
+        let rom_name = self.rom_name().to_string();
+        let compression = get_savestate_compression(&self.config);
+        let state_path = save_state::save_state(
+            &self.core_api,
+            &self.config["savestate_directory"],
+            &rom_name,
+            slot,
+            compression,
+        );
+        if let Some(state_path) = state_path.filter(|_| self.config["screenshot_on_savestate"] == "true") {
+            self.save_state_screenshot(state_path.with_extension("png"));
+        }
+    }
+
+    // Encoded on another thread with a copy of the frame so saving a state doesn't wait on the PNG
+    fn save_state_screenshot(&self, path: PathBuf) {
+        let (width, height) = (self.screen_width() as usize, self.screen_height() as usize);
+        let Some(pixels) = self.visible_frame() else {
+            println!("No frame has been drawn yet, no screenshot saved with the save state");
+            return;
+        };
+        std::thread::spawn(move || match screenshot::save_screenshot(&path, &pixels, width, height) {
+            Ok(()) => println!("Save state screenshot saved to: {}", path.display()),
+            Err(err) => println!("{}", err),
+        });
+    }
+
+    // Returns the slot that was loaded, with savestate_load_fallback an empty slot loads the nearest slot
+    // that has a save state instead
+    pub fn load_state(&mut self, slot: u8) -> Result<u8, String> {
+        let rom_name = self.rom_name().to_string();
+        let mut loaded_slot = slot;
+        if self.config["savestate_load_fallback"] == "true" && !self.has_save_state(slot) {
+            let max_slots = get_max_save_slots(&self.config);
+            if let Some(nearest) = save_state::nearest_occupied_slot(slot, max_slots, |slot| self.has_save_state(slot)) {
+                println!("Save state slot {} is empty, loading slot {} instead", slot, nearest);
+                loaded_slot = nearest;
+            }
+        }
+        save_state::load_state(
+            &mut self.core_api,
+            &self.config["savestate_directory"],
+            &rom_name,
+            loaded_slot,
+        )?;
+        Ok(loaded_slot)
+    }
+
+    // The CRC32 of the loaded ROM, the first one for a subsystem. libretro has no environment call that
+    // hands it to cores, those that want it work it out from GameInfo.data themselves.
+    pub fn content_crc32(&self) -> Option<u32> {
+        unsafe { (*std::ptr::addr_of!(CURRENT_EMULATOR_STATE.loaded_content)).first() }
+            .map(|content| content.crc32)
+    }
+
+    pub fn has_save_state(&self, slot: u8) -> bool {
+        save_state::get_save_state_path(&self.config["savestate_directory"], self.rom_name(), slot)
+            .is_some_and(|path| path.exists())
+    }
+
+    // Writes the current frame to a PNG in the screenshot_directory and returns where it was saved
+    pub fn screenshot(&self) -> Result<PathBuf, String> {
+        let (width, height) = (self.screen_width() as usize, self.screen_height() as usize);
+        let pixels = self.visible_frame().ok_or("No frame has been drawn yet")?;
+        let path = screenshot::get_screenshot_path(&self.config["screenshot_directory"], self.rom_name())
+            .ok_or("Failed to create the screenshot directory")?;
+        screenshot::save_screenshot(&path, &pixels, width, height)?;
+        Ok(path)
+    }
+
+    // Copies the current frame to the clipboard, saving it as a PNG instead if the clipboard can't take it
+    pub fn screenshot_to_clipboard(&self) -> Result<String, String> {
+        let (width, height) = (self.screen_width() as usize, self.screen_height() as usize);
+        let pixels = self.visible_frame().ok_or("No frame has been drawn yet")?;
+        match screenshot::copy_screenshot_to_clipboard(&pixels, width, height) {
+            Ok(()) => Ok("clipboard".to_string()),
+            Err(err) => {
+                println!("{}, saving the screenshot to a file instead", err);
+                self.screenshot().map(|path| path.display().to_string())
+            }
+        }
+    }
+
+    pub fn serialize(&self) -> Vec<u8> {
+        save_state::serialize_state(&self.core_api)
+    }
+
+    pub fn unserialize(&mut self, state: &[u8]) -> bool {
+        self.core_api.unserialize(state)
+    }
+
+    // Ignored unless port 0 is a lightgun (input_libretro_device_p1)
+    pub fn set_lightgun(&mut self, lightgun: LightgunState) {
+        unsafe {
+            if (*std::ptr::addr_of!(CURRENT_EMULATOR_STATE.lightgun)).is_some() {
+                CURRENT_EMULATOR_STATE.lightgun = Some(lightgun);
+            }
+        }
+    }
+
+    // Ignored unless a port is a keyboard (input_libretro_device_p* = keyboard), so joypad cores never see them
+    pub fn set_keyboard(&mut self, keys: &[Key]) {
+        unsafe {
+            let keyboard = &mut *std::ptr::addr_of_mut!(CURRENT_EMULATOR_STATE.keyboard);
+            if keyboard.is_some() {
+                *keyboard = Some(keys.iter().filter_map(key_to_retrok).collect());
+            }
+        }
+    }
+
+    // The buttons are indexed by DEVICE_ID_JOYPAD_* with 1 meaning pressed, read by the core on the next run_frame
+    pub fn set_input(&mut self, port: usize, buttons: &[i16]) {
+        unsafe {
+            let buttons_pressed = &mut *std::ptr::addr_of_mut!(CURRENT_EMULATOR_STATE.buttons_pressed);
+            if buttons_pressed.len() <= port {
+                buttons_pressed.resize(port + 1, vec![0; 16]);
+            }
+            buttons_pressed[port] = buttons.to_vec();
+        }
+    }
+
+    // The last frame the core sent us as XRGB8888, empty until the first frame has been drawn
+    pub fn frame_buffer(&self) -> &[u32] {
+        unsafe { (*std::ptr::addr_of!(CURRENT_EMULATOR_STATE.frame_buffer)).as_deref().unwrap_or(&[]) }
+    }
+
+    // Number of pixels between the start of each row in frame_buffer, can be more than screen_width
+    // when the core pads its rows
+    pub fn frame_stride(&self) -> usize {
+        unsafe {
+            let bytes_per_pixel = CURRENT_EMULATOR_STATE.bytes_per_pixel.max(1) as u32;
+            (CURRENT_EMULATOR_STATE.screen_pitch / bytes_per_pixel) as usize
+        }
+    }
+
+    // Just the screen_width x screen_height pixels of the last frame without any row padding
+    pub fn visible_frame(&self) -> Option<Vec<u32>> {
+        video::crop_frame(
+            self.frame_buffer(),
+            self.screen_width() as usize,
+            self.screen_height() as usize,
+            self.frame_stride(),
+        )
+    }
+
+    pub fn screen_width(&self) -> u32 {
+        unsafe { CURRENT_EMULATOR_STATE.screen_width }
+    }
+
+    pub fn screen_height(&self) -> u32 {
+        unsafe { CURRENT_EMULATOR_STATE.screen_height }
+    }
+
+    pub fn pixel_format(&self) -> PixelFormat {
+        unsafe { CURRENT_EMULATOR_STATE.pixel_format }
+    }
+
+    // Quarter turns counter-clockwise the core wants its frames rotated by before they're shown
+    pub fn rotation(&self) -> u32 {
+        unsafe { CURRENT_EMULATOR_STATE.rotation }
+    }
+
+    // The level the core gave through ENVIRONMENT_SET_PERFORMANCE_LEVEL, None if it never said
+    pub fn performance_level(&self) -> Option<u32> {
+        unsafe { CURRENT_EMULATOR_STATE.performance_level }
+    }
+
+    pub fn screen_pitch(&self) -> u32 {
+        unsafe { CURRENT_EMULATOR_STATE.screen_pitch }
+    }
+
+    pub fn bytes_per_pixel(&self) -> u8 {
+        unsafe { CURRENT_EMULATOR_STATE.bytes_per_pixel }
+    }
+
+    // The audio samples (interleaved left/right) the core sent us during the last frame
+    pub fn audio_data(&self) -> &[i16] {
+        unsafe { (*std::ptr::addr_of!(CURRENT_EMULATOR_STATE.audio_data)).as_deref().unwrap_or(&[]) }
+    }
+
+    pub fn av_info(&self) -> &SystemAvInfo {
+        &self.av_info
+    }
+
+    // NTSC or PAL, from the core or force_region
+    pub fn region(&self) -> Region {
+        self.region
+    }
+
+    // Returns true once after the core has changed its fps, sample rate or geometry at runtime
+    // (ENVIRONMENT_SET_SYSTEM_AV_INFO) so the frame pacing and audio can be updated to match
+    pub fn take_av_info_changed(&mut self) -> bool {
+        unsafe {
+            if !CURRENT_EMULATOR_STATE.av_info_changed {
+                return false;
+            }
+            CURRENT_EMULATOR_STATE.av_info_changed = false;
+            if let Some(av_info) = &*std::ptr::addr_of!(CURRENT_EMULATOR_STATE.av_info) {
+                self.av_info = av_info.clone();
+                use_region_fps_if_unusable(&mut self.av_info, self.region);
+            }
+            true
+        }
+    }
+
+    // Returns true once after the core has changed just its geometry (ENVIRONMENT_SET_GEOMETRY), the fps
+    // and sample rate are unchanged
+    pub fn take_geometry_changed(&mut self) -> bool {
+        unsafe {
+            if !CURRENT_EMULATOR_STATE.geometry_changed {
+                return false;
+            }
+            CURRENT_EMULATOR_STATE.geometry_changed = false;
+            if let Some(av_info) = &*std::ptr::addr_of!(CURRENT_EMULATOR_STATE.av_info) {
+                self.av_info.geometry = av_info.geometry.clone();
+            }
+            true
+        }
+    }
+
+    // The core asked the frontend to quit (ENVIRONMENT_SHUTDOWN), e.g. "power off" in a menu core
+    pub fn should_quit(&self) -> bool {
+        unsafe { CURRENT_EMULATOR_STATE.should_quit }
+    }
+
+    // True once after the core changes the rumble of any port
+    pub fn take_rumble_changed(&mut self) -> bool {
+        unsafe { std::mem::take(&mut *std::ptr::addr_of_mut!(CURRENT_EMULATOR_STATE.rumble_changed)) }
+    }
+
+    pub fn rumble_state(&self, port: usize) -> RumbleState {
+        let rumble = unsafe { &*std::ptr::addr_of!(CURRENT_EMULATOR_STATE.rumble) };
+        rumble.get(port).copied().unwrap_or_default()
+    }
+
+    // The devices the core said each port can take, empty when it didn't say
+    pub fn controller_types(&self) -> &[Vec<ControllerType>] {
+        unsafe { &*std::ptr::addr_of!(CURRENT_EMULATOR_STATE.controller_types) }
+    }
+
+    // The settings the core declared with ENVIRONMENT_SET_VARIABLES and their current values
+    pub fn core_variables(&self) -> &[CoreVariable] {
+        unsafe { &*std::ptr::addr_of!(CURRENT_EMULATOR_STATE.core_variables) }
+    }
+
+    // A read only summary of the core's video, memory and variables for bug reports (input_debug_dump)
+    pub fn debug_info(&self) -> String {
+        let state = unsafe { &*std::ptr::addr_of!(CURRENT_EMULATOR_STATE) };
+        let geometry = &self.av_info.geometry;
+        let mut lines = vec![
+            format!("Core: {}", self.core_name()),
+            format!("ROM: {}", self.rom_name()),
+            format!("Frame: {}", state.frame_count),
+            format!("Region: {}", self.region.name()),
+            format!(
+                "Pixel format: {:?} ({} bytes per pixel)",
+                state.pixel_format, state.bytes_per_pixel
+            ),
+            format!(
+                "Frame size: {}x{} pitch {}",
+                state.screen_width, state.screen_height, state.screen_pitch
+            ),
+            format!(
+                "Geometry: base {}x{} max {}x{} aspect ratio {:.3}",
+                geometry.base_width,
+                geometry.base_height,
+                geometry.max_width,
+                geometry.max_height,
+                geometry.aspect_ratio
+            ),
+            format!(
+                "Timing: {:.3} fps {} Hz",
+                self.av_info.timing.fps, self.av_info.timing.sample_rate
+            ),
+            format!("Serialize size: {} bytes", self.core_api.serialize_size()),
+        ];
+        let memory_types = [
+            ("Save RAM", libretro_sys::MEMORY_SAVE_RAM),
+            ("RTC", libretro_sys::MEMORY_RTC),
+            ("System RAM", libretro_sys::MEMORY_SYSTEM_RAM),
+            ("Video RAM", libretro_sys::MEMORY_VIDEO_RAM),
+        ];
+        for (name, memory_type) in memory_types {
+            let size = unsafe { (self.core_api.retro_get_memory_size)(memory_type) };
+            lines.push(format!("Memory {}: {} bytes", name, size));
+        }
+        for (port, types) in state.controller_types.iter().enumerate() {
+            let devices: Vec<String> = types.iter().map(|controller| describe_device(controller.id, types)).collect();
+            lines.push(format!("Port {} devices: {}", port + 1, devices.join(", ")));
+        }
+        lines.push(format!("Core variables: {}", state.core_variables.len()));
+        for variable in &state.core_variables {
+            lines.push(format!("  {} = {}", variable.key, variable.value));
+        }
+        lines.join("\n")
+    }
+
+    pub fn config(&self) -> &HashMap<String, String> {
+        &self.config
+    }
+
+    pub fn rom_name(&self) -> &str {
+        unsafe { &*std::ptr::addr_of!(CURRENT_EMULATOR_STATE.rom_name) }
+    }
+
+    pub fn core_name(&self) -> &str {
+        unsafe { &*std::ptr::addr_of!(CURRENT_EMULATOR_STATE.core_name) }
+    }
+
+    // memory_type is one of libretro_sys::MEMORY_*, empty if the core doesn't expose it
+    pub fn memory(&self, memory_type: u32) -> &[u8] {
+        unsafe {
+            let data = (self.core_api.retro_get_memory_data)(memory_type);
+            let size = (self.core_api.retro_get_memory_size)(memory_type);
+            if data.is_null() {
+                &[]
+            } else {
+                std::slice::from_raw_parts(data as *const u8, size)
+            }
+        }
+    }
+
+    pub fn read_ram_watch(&self, entry: &WatchEntry) -> Option<u32> {
+        unsafe {
+            ram_watch::read_watch_value(
+                entry,
+                &*std::ptr::addr_of!(CURRENT_EMULATOR_STATE.memory_descriptors),
+                self.memory(libretro_sys::MEMORY_SYSTEM_RAM),
+            )
+        }
+    }
+
+    pub fn core_api(&self) -> &CoreAPI {
+        &self.core_api
+    }
+}
+
+// Ports that input_libretro_device_p{n} can be set for
+const MAX_PORTS: usize = 4;
+
+// Each port gets the device from input_libretro_device_p{n}, or when that's empty the best one the core
+// listed for the port. Ports with neither are left on the core's default. A lightgun on port 0 is aimed
+// with the mouse.
+unsafe fn set_controller_devices(core_api: &CoreAPI, config: &HashMap<String, String>) {
+    let controller_types = (*std::ptr::addr_of!(CURRENT_EMULATOR_STATE)).controller_types.clone();
+    for port in 0..MAX_PORTS {
+        let types = controller_types.get(port).map_or(&[][..], Vec::as_slice);
+        let key = format!("input_libretro_device_p{}", port + 1);
+        let configured = config.get(&key).map_or("", String::as_str);
+        let device = parse_device(configured, types).unwrap_or_else(|err| {
+            println!("Invalid {}: {} ({}) using the default", key, configured, err);
+            default_device(types)
+        });
+        let Some(device) = device else {
+            continue;
+        };
+        println!("Port {} device: {}", port + 1, describe_device(device, types));
+        (core_api.retro_set_controller_port_device)(port as u32, device);
+        if port == 0 && device & libretro_sys::DEVICE_MASK == libretro_sys::DEVICE_LIGHTGUN {
+            println!("Port 1 is a lightgun, aim with the mouse, left click fires and right click reloads");
+            CURRENT_EMULATOR_STATE.lightgun = Some(LightgunState::default());
+        }
+        if device & libretro_sys::DEVICE_MASK == libretro_sys::DEVICE_KEYBOARD {
+            println!("Port {} is a keyboard, every key held down is passed to the core", port + 1);
+            CURRENT_EMULATOR_STATE.keyboard = Some(Vec::new());
+        }
+    }
+}
+
+// Adds the core and ROM that were running to the panic message, which is usually the first thing
+// needed when a core we haven't tested takes us down
+pub fn install_panic_hook() {
+    let default_hook = panic::take_hook();
+    panic::set_hook(Box::new(move |panic_info| {
+        unsafe {
+            println!(
+                "RustroArch panicked while running core: {} with ROM: {}",
+                *std::ptr::addr_of!(CURRENT_EMULATOR_STATE.core_name),
+                *std::ptr::addr_of!(CURRENT_EMULATOR_STATE.rom_name)
+            );
+        }
+        default_hook(panic_info);
+    }));
+}
+
+// Frames are paced by the core's fps, when it reports one that can't be used the region's is used instead
+fn use_region_fps_if_unusable(av_info: &mut SystemAvInfo, region: Region) {
+    if !region::is_usable_fps(av_info.timing.fps) {
+        println!(
+            "Core reported {} fps, using the {} rate of {} fps",
+            av_info.timing.fps,
+            region.name(),
+            region.fps()
+        );
+        av_info.timing.fps = region::pacing_fps(av_info.timing.fps, region);
+    }
+}
+
+impl Drop for Emulator {
+    fn drop(&mut self) {
+        println!("Unloading Core: {}", self.core_name());
+        unsafe {
+            (self.core_api.retro_unload_game)();
+            (self.core_api.retro_deinit)();
+            CURRENT_EMULATOR_STATE = EmulatorState::new();
+        }
+        // Only now that nothing can call into the core is it safe to close its Library
+        drop(self.library.take());
+    }
+}
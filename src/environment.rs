@@ -0,0 +1,368 @@
+use crate::callbacks::{
+    catch_callback_panic, libretro_log_print_callback, libretro_set_rumble_state_callback,
+};
+use crate::controller_info::parse_controller_info;
+use crate::core_info::c_string_to_string;
+use crate::core_options::{
+    apply_core_option_values, parse_core_option_definitions, parse_core_options_v2, parse_variables,
+    CoreOptionDefinition, CoreOptionsIntl, CoreOptionsV2, CoreOptionsV2Intl, CoreVariable,
+    CORE_OPTIONS_VERSION,
+};
+use crate::state::CURRENT_EMULATOR_STATE;
+use crate::subsystem::parse_subsystem_info;
+use libretro_sys::{
+    ControllerInfo, FrameTimeCallback, GameGeometry, LogCallback, MemoryMap, PixelFormat, RumbleInterface, SubsystemInfo,
+    SystemAvInfo, Variable,
+};
+use std::ffi::{c_void, CString};
+use std::ptr;
+
+// retro_game_info_ext wasn't in libretro-sys package so declaring it here
+pub struct GameInfoExt {
+    pub full_path: *const libc::c_char,
+    pub archive_path: *const libc::c_char,
+    pub archive_file: *const libc::c_char,
+    pub dir: *const libc::c_char,
+    pub name: *const libc::c_char,
+    pub ext: *const libc::c_char,
+    pub meta: *const libc::c_char,
+    pub data: *const libc::c_void,
+    /* Size of game content memory buffer, in bytes */
+    pub size: libc::size_t,
+    pub file_in_archive: bool,
+    pub persistent_data: bool,
+}
+
+// Not in libretro_sys yet, the core asks if it can read all the joypad buttons at once with DEVICE_ID_JOYPAD_MASK
+const ENVIRONMENT_GET_INPUT_BITMASKS: u32 = 51 | libretro_sys::ENVIRONMENT_EXPERIMENTAL;
+
+// Core options commands newer than libretro_sys
+const ENVIRONMENT_GET_CORE_OPTIONS_VERSION: u32 = 52;
+const ENVIRONMENT_SET_CORE_OPTIONS: u32 = 53;
+const ENVIRONMENT_SET_CORE_OPTIONS_INTL: u32 = 54;
+const ENVIRONMENT_SET_CORE_OPTIONS_V2: u32 = 67;
+const ENVIRONMENT_SET_CORE_OPTIONS_V2_INTL: u32 = 68;
+
+// Whichever way the core declared its options they're stored the same way, with the config and any
+// --core-option values applied. GET_VARIABLE hands out pointers into the values so they're only rebuilt
+// when the options are declared again.
+unsafe fn set_core_variables(mut core_variables: Vec<CoreVariable>) {
+    apply_core_option_values(&mut core_variables, &*std::ptr::addr_of!(CURRENT_EMULATOR_STATE.core_option_values));
+    println!("Core declared {} options", core_variables.len());
+    CURRENT_EMULATOR_STATE.core_variable_values = core_variables
+        .iter()
+        .map(|variable| (variable.key.clone(), CString::new(variable.value.clone()).unwrap_or_default()))
+        .collect();
+    CURRENT_EMULATOR_STATE.core_variables = core_variables;
+}
+
+// NOTE: In the implementation of this function make sure you only send CString's to return_data, otherwise the core will not know when the String ends!
+pub(crate) unsafe extern "C" fn libretro_environment_callback(command: u32, return_data: *mut c_void) -> bool {
+    catch_callback_panic("environment", false, || {
+        println!("libretro_environment_callback command:{}", command);
+        return match command {
+            libretro_sys::ENVIRONMENT_GET_CAN_DUPE => {
+                *(return_data as *mut bool) = true; // Set the return_data to the value true
+                println!("Set ENVIRONMENT_GET_CAN_DUPE to true");
+                false
+            }
+            // The frontend can show the three formats libretro defines: 0RGB1555 (the default until this is
+            // called), XRGB8888 and RGB565, each converted to XRGB8888 in the video refresh callback. Anything
+            // else is refused with false, which leaves the format as it was so the core can try another one.
+            libretro_sys::ENVIRONMENT_SET_PIXEL_FORMAT => {
+                if return_data.is_null() {
+                    return false;
+                }
+                let pixel_format = *(return_data as *const u32);
+                let Some(pixel_format_as_enum) = PixelFormat::from_uint(pixel_format) else {
+                    println!(
+                        "Core asked for pixel format {} which isn't supported, only 0RGB1555, XRGB8888 and RGB565 are",
+                        pixel_format
+                    );
+                    return false;
+                };
+                CURRENT_EMULATOR_STATE.pixel_format = pixel_format_as_enum;
+                CURRENT_EMULATOR_STATE.pixel_format_negotiated = true;
+                CURRENT_EMULATOR_STATE.bytes_per_pixel = match pixel_format_as_enum {
+                    PixelFormat::ARGB1555 => {
+                        println!("Core will send us pixel data in the RETRO_PIXEL_FORMAT_0RGB1555 format");
+                        2
+                    }
+                    PixelFormat::RGB565 => {
+                        println!("Core will send us pixel data in the RETRO_PIXEL_FORMAT_RGB565 format");
+                        2
+                    }
+                    PixelFormat::ARGB8888 => {
+                        println!("Core will send us pixel data in the RETRO_PIXEL_FORMAT_XRGB8888 format");
+                        4
+                    }
+                };
+                true
+            }
+            libretro_sys::ENVIRONMENT_SET_MEMORY_MAPS => {
+                let memory_map = &*(return_data as *const MemoryMap);
+                CURRENT_EMULATOR_STATE.memory_descriptors = if memory_map.descriptors.is_null() {
+                    Vec::new()
+                } else {
+                    std::slice::from_raw_parts(memory_map.descriptors, memory_map.num_descriptors as usize).to_vec()
+                };
+                println!("Core provided {} memory descriptors", memory_map.num_descriptors);
+                true
+            }
+            libretro_sys::ENVIRONMENT_SET_CONTROLLER_INFO => {
+                let controller_types = parse_controller_info(return_data as *const ControllerInfo);
+                println!("Core listed the devices for {} ports", controller_types.len());
+                CURRENT_EMULATOR_STATE.controller_types = controller_types;
+                true
+            }
+            libretro_sys::ENVIRONMENT_GET_VARIABLE_UPDATE => {
+                println!("INFO: Ignoring ENVIRONMENT_GET_VARIABLE_UPDATE");
+                // Return true when we have changed variables that the core needs to know about, but we don't change anything yet
+                false
+            }
+            // All the GETs not currently supported
+            libretro_sys::ENVIRONMENT_GET_CAMERA_INTERFACE => {
+                println!("TODO: Handle ENVIRONMENT_GET_CAMERA_INTERFACE");
+                true
+            }
+            libretro_sys::ENVIRONMENT_GET_CORE_ASSETS_DIRECTORY => {
+                println!("TODO: Handle ENVIRONMENT_GET_CORE_ASSETS_DIRECTORY");
+                true
+            }
+            libretro_sys::ENVIRONMENT_GET_CURRENT_SOFTWARE_FRAMEBUFFER => {
+                println!("TODO: Handle ENVIRONMENT_GET_CURRENT_SOFTWARE_FRAMEBUFFER");
+                true
+            }
+            libretro_sys::ENVIRONMENT_GET_HW_RENDER_INTERFACE => {
+                println!("TODO: Handle ENVIRONMENT_GET_HW_RENDER_INTERFACE");
+                true
+            }
+            libretro_sys::ENVIRONMENT_GET_INPUT_DEVICE_CAPABILITIES => {
+                println!("TODO: Handle ENVIRONMENT_GET_INPUT_DEVICE_CAPABILITIES");
+                true
+            }
+            libretro_sys::ENVIRONMENT_GET_LANGUAGE => {
+                *(return_data as *mut libc::c_uint) = CURRENT_EMULATOR_STATE.language;
+                true
+            }
+            libretro_sys::ENVIRONMENT_GET_LIBRETRO_PATH => {
+                println!("TODO: Handle ENVIRONMENT_GET_LIBRETRO_PATH");
+                true
+            }
+            libretro_sys::ENVIRONMENT_GET_LOCATION_INTERFACE => {
+                println!("TODO: Handle ENVIRONMENT_GET_LOCATION_INTERFACE");
+                true
+            }
+            libretro_sys::ENVIRONMENT_GET_LOG_INTERFACE => {
+                println!("TODO: Handle ENVIRONMENT_GET_LOG_INTERFACE");
+                (*(return_data as *mut LogCallback)).log = libretro_log_print_callback;
+                true
+            }
+            libretro_sys::ENVIRONMENT_GET_OVERSCAN => {
+                println!("TODO: Handle ENVIRONMENT_GET_OVERSCAN");
+                true
+            }
+            libretro_sys::ENVIRONMENT_GET_PERF_INTERFACE => {
+                println!("TODO: Handle ENVIRONMENT_GET_PERF_INTERFACE");
+                true
+            }
+            libretro_sys::ENVIRONMENT_GET_RUMBLE_INTERFACE => {
+                println!("Set ENVIRONMENT_GET_RUMBLE_INTERFACE");
+                (*(return_data as *mut RumbleInterface)).set_rumble_state = libretro_set_rumble_state_callback;
+                true
+            }
+            libretro_sys::ENVIRONMENT_GET_SAVE_DIRECTORY => {
+                println!("TODO: Handle ENVIRONMENT_GET_SAVE_DIRECTORY");
+                *(return_data as *mut *const libc::c_char) = CURRENT_EMULATOR_STATE.system_directory.as_ref().unwrap().as_ptr() as *const i8;  // TODO use CString otherwise this will segfault
+                true
+            }
+            libretro_sys::ENVIRONMENT_GET_SENSOR_INTERFACE => {
+                println!("TODO: Handle ENVIRONMENT_GET_SENSOR_INTERFACE");
+                true
+            }
+            libretro_sys::ENVIRONMENT_GET_SYSTEM_DIRECTORY => {
+                println!("TODO: Handle ENVIRONMENT_GET_SYSTEM_DIRECTORY");
+                println!("Rom name: {:?}", CURRENT_EMULATOR_STATE.rom_name);
+                println!("Pointer: {:?}", CURRENT_EMULATOR_STATE.rom_name.as_ptr());
+           
+                *(return_data as *mut *const libc::c_char) = CURRENT_EMULATOR_STATE.system_directory.as_ref().unwrap().as_ptr() as *const i8;
+                println!("return_data: {:?}", return_data);
+                true
+            }
+            libretro_sys::ENVIRONMENT_GET_USERNAME => {
+                println!("TODO: Handle ENVIRONMENT_GET_USERNAME");
+                true
+            }
+            libretro_sys::ENVIRONMENT_GET_VARIABLE => {
+                let variable = &mut *(return_data as *mut Variable);
+                let key = c_string_to_string(variable.key);
+                match (*std::ptr::addr_of!(CURRENT_EMULATOR_STATE.core_variable_values)).get(&key) {
+                    Some(value) => {
+                        variable.value = value.as_ptr();
+                        true
+                    }
+                    None => {
+                        variable.value = ptr::null();
+                        false
+                    }
+                }
+            }
+            // Rest of the SET_
+            libretro_sys::ENVIRONMENT_SET_DISK_CONTROL_INTERFACE=> {
+                println!("TODO: Handle ENVIRONMENT_SET_DISK_CONTROL_INTERFACE");
+                true
+            }
+            libretro_sys::ENVIRONMENT_SET_FRAME_TIME_CALLBACK=> {
+                let frame_time_callback = (*(return_data as *const FrameTimeCallback)).clone();
+                println!("Core wants the frame time, one frame is {} usec", frame_time_callback.reference);
+                CURRENT_EMULATOR_STATE.frame_time_callback = Some(frame_time_callback);
+                true
+            }
+            libretro_sys::ENVIRONMENT_SET_GEOMETRY=> {
+                // Only the base size and aspect ratio can change here, the max size, fps and sample rate stay put
+                let geometry = &*(return_data as *const GameGeometry);
+                match (*std::ptr::addr_of_mut!(CURRENT_EMULATOR_STATE.av_info)).as_mut() {
+                    Some(av_info) => {
+                        av_info.geometry.base_width = geometry.base_width;
+                        av_info.geometry.base_height = geometry.base_height;
+                        av_info.geometry.aspect_ratio = geometry.aspect_ratio;
+                        CURRENT_EMULATOR_STATE.geometry_changed = true;
+                        true
+                    }
+                    None => false,
+                }
+            }
+            libretro_sys::ENVIRONMENT_SET_HW_RENDER=> {
+                println!("TODO: Handle ENVIRONMENT_SET_HW_RENDER");
+                true
+            }
+            libretro_sys::ENVIRONMENT_SET_INPUT_DESCRIPTORS=> {
+                println!("TODO: Handle ENVIRONMENT_SET_INPUT_DESCRIPTORS");
+                true
+            }
+            libretro_sys::ENVIRONMENT_SET_KEYBOARD_CALLBACK=> {
+                println!("TODO: Handle ENVIRONMENT_SET_KEYBOARD_CALLBACK");
+                true
+            }
+            libretro_sys::ENVIRONMENT_SET_MESSAGE=> {
+                println!("TODO: Handle ENVIRONMENT_SET_MESSAGE");
+                true
+            }
+            libretro_sys::ENVIRONMENT_SET_PERFORMANCE_LEVEL=> {
+                if return_data.is_null() {
+                    return false;
+                }
+                // The scale is the core's own, higher means more demanding. It's only used to explain a
+                // slowdown.
+                let level = *(return_data as *const libc::c_uint);
+                println!("Core says it needs performance level {}", level);
+                CURRENT_EMULATOR_STATE.performance_level = Some(level);
+                true
+            }
+            libretro_sys::ENVIRONMENT_SET_PROC_ADDRESS_CALLBACK=> {
+                println!("TODO: Handle ENVIRONMENT_SET_PROC_ADDRESS_CALLBACK");
+                true
+            }
+            libretro_sys::ENVIRONMENT_SET_ROTATION=> {
+                let rotation = *(return_data as *const libc::c_uint) % 4;
+                println!("Core asked for the screen to be rotated {} degrees", rotation * 90);
+                CURRENT_EMULATOR_STATE.rotation = rotation;
+                true
+            }
+            libretro_sys::ENVIRONMENT_SET_SUBSYSTEM_INFO=> {
+                CURRENT_EMULATOR_STATE.subsystems = parse_subsystem_info(return_data as *const SubsystemInfo);
+                println!("Core supports {} subsystems", (*std::ptr::addr_of!(CURRENT_EMULATOR_STATE.subsystems)).len());
+                true
+            }
+            libretro_sys::ENVIRONMENT_SET_SUPPORT_NO_GAME=> {
+                println!("TODO: Handle ENVIRONMENT_SET_SUPPORT_NO_GAME");
+                true
+            }
+            libretro_sys::ENVIRONMENT_SET_SYSTEM_AV_INFO=> {
+                let av_info = (*(return_data as *const SystemAvInfo)).clone();
+                println!("Core changed its AV Info: {:?}", &av_info);
+                CURRENT_EMULATOR_STATE.av_info = Some(av_info);
+                CURRENT_EMULATOR_STATE.av_info_changed = true;
+                true
+            }
+            libretro_sys::ENVIRONMENT_SET_VARIABLES=> {
+                set_core_variables(parse_variables(return_data as *const Variable));
+                true
+            }
+            ENVIRONMENT_GET_CORE_OPTIONS_VERSION => {
+                *(return_data as *mut libc::c_uint) = CORE_OPTIONS_VERSION;
+                true
+            }
+            ENVIRONMENT_SET_CORE_OPTIONS => {
+                set_core_variables(parse_core_option_definitions(return_data as *const CoreOptionDefinition));
+                true
+            }
+            // The translated descriptions aren't shown anywhere so only the US English options are read
+            ENVIRONMENT_SET_CORE_OPTIONS_INTL => {
+                let options = &*(return_data as *const CoreOptionsIntl);
+                set_core_variables(parse_core_option_definitions(options.us));
+                true
+            }
+            ENVIRONMENT_SET_CORE_OPTIONS_V2 => {
+                set_core_variables(parse_core_options_v2(return_data as *const CoreOptionsV2));
+                true
+            }
+            ENVIRONMENT_SET_CORE_OPTIONS_V2_INTL => {
+                let options = &*(return_data as *const CoreOptionsV2Intl);
+                set_core_variables(parse_core_options_v2(options.us));
+                true
+            }
+            libretro_sys::ENVIRONMENT_EXPERIMENTAL => {
+                println!("TODO: Handle ENVIRONMENT_EXPERIMENTAL");
+                true
+            }
+            libretro_sys::ENVIRONMENT_PRIVATE => {
+                println!("TODO: Handle ENVIRONMENT_PRIVATE");
+                true
+            }
+            libretro_sys::ENVIRONMENT_SHUTDOWN => {
+                println!("Core requested shutdown");
+                CURRENT_EMULATOR_STATE.should_quit = true;
+                true
+            }
+            ENVIRONMENT_GET_INPUT_BITMASKS => {
+                println!("Core will use input bitmasks");
+                true
+            }
+            55 => {
+                println!("TODO: Handle RETRO_ENVIRONMENT_SET_CORE_OPTIONS_DISPLAY");
+                false
+            }
+            66 => {
+                // TODO: need to return retro_game_info_ext retro_game_info_ext
+                println!("TODO: Handle ENVIRONMENT_GET_GAME_INFO_EXT");
+                let game_info = CURRENT_EMULATOR_STATE.game_info.clone().unwrap_unchecked();
+    
+                let data = (return_data as *mut GameInfoExt);
+                (*(return_data as *mut GameInfoExt)).full_path = CURRENT_EMULATOR_STATE.rom_name.as_ptr() as *const i8;
+                (*(return_data as *mut GameInfoExt)).archive_path = ptr::null();
+                (*(return_data as *mut GameInfoExt)).archive_file = ptr::null();
+                (*(return_data as *mut GameInfoExt)).ext = CURRENT_EMULATOR_STATE.rom_name.as_ptr() as *const i8;
+                (*(return_data as *mut GameInfoExt)).meta = ptr::null();
+                (*(return_data as *mut GameInfoExt)).dir = CURRENT_EMULATOR_STATE.rom_name.as_ptr() as *const i8; // TODO: Convert to Cstring
+                (*(return_data as *mut GameInfoExt)).name = CURRENT_EMULATOR_STATE.rom_name.as_ptr() as *const i8; // TODO: Convert to Cstring
+                (*(return_data as *mut GameInfoExt)).file_in_archive = false;
+                (*(return_data as *mut GameInfoExt)).persistent_data = true;
+                (*(return_data as *mut GameInfoExt)).size = CURRENT_EMULATOR_STATE.game_info.as_ref().unwrap().size;
+                (*(return_data as *mut GameInfoExt)).data = CURRENT_EMULATOR_STATE.game_info.as_ref().unwrap().data;
+                println!("Data size {}",  (*(return_data as *mut GameInfoExt)).size);
+
+
+                true
+            }
+            _ => {
+                println!(
+                    "libretro_environment_callback Called with command: {}",
+                    command
+                );
+                false
+            }
+        };
+    })
+}
+
@@ -0,0 +1,7 @@
+// CRC32 of a frame's XRGB8888 pixels as little-endian bytes. The frame buffer is always converted to
+// XRGB8888 whatever format the core draws in, so the hashes of --hash-frames can be compared across core
+// versions and machines.
+pub fn frame_crc32(pixels: &[u32]) -> u32 {
+    let bytes: Vec<u8> = pixels.iter().flat_map(|pixel| pixel.to_le_bytes()).collect();
+    crc32fast::hash(&bytes)
+}
@@ -0,0 +1,573 @@
+use gilrs::{Axis, Button, GamepadId, Gilrs};
+use minifb::Key;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+// Config key names are the lowercase minifb key names e.g. Key::F2 => "f2"
+pub fn key_to_config_name(key: &Key) -> String {
+    format!("{:?}", key).to_ascii_lowercase()
+}
+
+// A binding can list several keys separated by commas e.g. input_player1_start = enter,p
+pub fn bound_keys(binding: &str) -> impl Iterator<Item = &str> {
+    binding
+        .split(',')
+        .map(|key| key.trim())
+        .filter(|key| !key.is_empty() && *key != "nul")
+}
+
+pub fn is_key_bound(binding: &str, key_name: &str) -> bool {
+    bound_keys(binding).any(|key| key == key_name)
+}
+
+// The config keys binding keyboard keys to player 1's buttons
+const GAME_KEY_BINDINGS: [(&str, u32); 12] = [
+    ("input_player1_a", libretro_sys::DEVICE_ID_JOYPAD_A),
+    ("input_player1_b", libretro_sys::DEVICE_ID_JOYPAD_B),
+    ("input_player1_x", libretro_sys::DEVICE_ID_JOYPAD_X),
+    ("input_player1_y", libretro_sys::DEVICE_ID_JOYPAD_Y),
+    ("input_player1_l", libretro_sys::DEVICE_ID_JOYPAD_L),
+    ("input_player1_r", libretro_sys::DEVICE_ID_JOYPAD_R),
+    ("input_player1_down", libretro_sys::DEVICE_ID_JOYPAD_DOWN),
+    ("input_player1_up", libretro_sys::DEVICE_ID_JOYPAD_UP),
+    ("input_player1_right", libretro_sys::DEVICE_ID_JOYPAD_RIGHT),
+    ("input_player1_left", libretro_sys::DEVICE_ID_JOYPAD_LEFT),
+    ("input_player1_start", libretro_sys::DEVICE_ID_JOYPAD_START),
+    ("input_player1_select", libretro_sys::DEVICE_ID_JOYPAD_SELECT),
+];
+
+// The config keys binding keyboard keys to hotkeys
+pub const HOTKEY_BINDINGS: [&str; 17] = [
+    "input_reset",
+    "input_swap_core",
+    "input_save_state",
+    "input_load_state",
+    "input_screenshot",
+    "input_screenshot_clipboard",
+    "input_hold_fast_forward",
+    "input_rewind",
+    "input_debug_dump",
+    "input_toggle_filter",
+    "input_toggle_aspect",
+    "input_toggle_swap_ab",
+    "input_scale_increase",
+    "input_scale_decrease",
+    "input_state_slot_increase",
+    "input_state_slot_decrease",
+    "input_quick_swap_slot",
+];
+
+// Maps each bound key name to the libretro button it presses
+pub fn setup_key_device_map(config: &HashMap<String, String>) -> HashMap<String, usize> {
+    let mut key_device_map = HashMap::new();
+    for (config_key, button) in GAME_KEY_BINDINGS {
+        for key in bound_keys(&config[config_key]) {
+            key_device_map.insert(key.to_string(), button as usize);
+        }
+    }
+    key_device_map
+}
+
+// A key bound to a game button and a hotkey at once, only one of them can have it (hotkey_priority)
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct KeyCollision {
+    pub key: String,
+    pub game_input: String,
+    pub hotkey: String,
+}
+
+pub fn find_key_collisions(config: &HashMap<String, String>) -> Vec<KeyCollision> {
+    let mut collisions = Vec::new();
+    for (game_input, _) in GAME_KEY_BINDINGS {
+        for key in bound_keys(&config[game_input]) {
+            for hotkey in HOTKEY_BINDINGS {
+                if config.get(hotkey).is_some_and(|binding| is_key_bound(binding, key)) {
+                    collisions.push(KeyCollision {
+                        key: key.to_string(),
+                        game_input: game_input.to_string(),
+                        hotkey: hotkey.to_string(),
+                    });
+                }
+            }
+        }
+    }
+    collisions
+}
+
+// The libretro buttons with RetroArch input_player1_{name}_btn and input_player1_{name}_axis bindings
+const JOYPAD_BINDING_NAMES: [(&str, u32); 16] = [
+    ("a", libretro_sys::DEVICE_ID_JOYPAD_A),
+    ("b", libretro_sys::DEVICE_ID_JOYPAD_B),
+    ("x", libretro_sys::DEVICE_ID_JOYPAD_X),
+    ("y", libretro_sys::DEVICE_ID_JOYPAD_Y),
+    ("l", libretro_sys::DEVICE_ID_JOYPAD_L),
+    ("r", libretro_sys::DEVICE_ID_JOYPAD_R),
+    ("l2", libretro_sys::DEVICE_ID_JOYPAD_L2),
+    ("r2", libretro_sys::DEVICE_ID_JOYPAD_R2),
+    ("l3", libretro_sys::DEVICE_ID_JOYPAD_L3),
+    ("r3", libretro_sys::DEVICE_ID_JOYPAD_R3),
+    ("down", libretro_sys::DEVICE_ID_JOYPAD_DOWN),
+    ("up", libretro_sys::DEVICE_ID_JOYPAD_UP),
+    ("right", libretro_sys::DEVICE_ID_JOYPAD_RIGHT),
+    ("left", libretro_sys::DEVICE_ID_JOYPAD_LEFT),
+    ("start", libretro_sys::DEVICE_ID_JOYPAD_START),
+    ("select", libretro_sys::DEVICE_ID_JOYPAD_SELECT),
+];
+
+// RetroArch button numbers follow the SDL2 game controller layout (what its sdl2 joypad driver uses), which
+// is also the layout gilrs maps every gamepad to. Hats are given as e.g. h0up.
+fn retroarch_button(binding: &str) -> Option<Button> {
+    let button = match binding {
+        "0" => Button::South,
+        "1" => Button::East,
+        "2" => Button::West,
+        "3" => Button::North,
+        "4" => Button::Select,
+        "5" => Button::Mode,
+        "6" => Button::Start,
+        "7" => Button::LeftThumb,
+        "8" => Button::RightThumb,
+        "9" => Button::LeftTrigger,
+        "10" => Button::RightTrigger,
+        "11" | "h0up" => Button::DPadUp,
+        "12" | "h0down" => Button::DPadDown,
+        "13" | "h0left" => Button::DPadLeft,
+        "14" | "h0right" => Button::DPadRight,
+        _ => return None,
+    };
+    Some(button)
+}
+
+// An axis pushed past the deadzone in one direction presses a libretro button, e.g. input_player1_l2_axis = +4
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct AxisBinding {
+    pub axis: Axis,
+    pub positive: bool,
+    pub button: usize,
+}
+
+impl AxisBinding {
+    pub fn is_pressed(&self, value: f32, deadzone: f32) -> bool {
+        if self.positive {
+            value > deadzone
+        } else {
+            value < -deadzone
+        }
+    }
+}
+
+// RetroArch axes are "+n" or "-n" with the SDL2 numbering, where stick y is positive when pushed down
+// unlike gilrs
+fn retroarch_axis(binding: &str) -> Option<(Axis, bool)> {
+    let (positive, index) = match binding.split_at_checked(1)? {
+        ("+", index) => (true, index),
+        ("-", index) => (false, index),
+        _ => return None,
+    };
+    match index {
+        "0" => Some((Axis::LeftStickX, positive)),
+        "1" => Some((Axis::LeftStickY, !positive)),
+        "2" => Some((Axis::RightStickX, positive)),
+        "3" => Some((Axis::RightStickY, !positive)),
+        "4" => Some((Axis::LeftZ, positive)),
+        "5" => Some((Axis::RightZ, positive)),
+        _ => None,
+    }
+}
+
+// A RetroArch binding that's unset ("nul" or empty) is ignored, anything we can't map is ignored with a warning
+fn joypad_binding<T>(
+    config: &HashMap<String, String>,
+    config_key: &str,
+    parse: fn(&str) -> Option<T>,
+) -> Option<T> {
+    let binding = config.get(config_key)?.trim();
+    if binding.is_empty() || binding == "nul" {
+        return None;
+    }
+    let parsed = parse(binding);
+    if parsed.is_none() {
+        println!("Unsupported joypad binding {} = {}, ignoring it", config_key, binding);
+    }
+    parsed
+}
+
+// Gamepad buttons to libretro buttons, any input_player1_*_btn bindings in the config (e.g. from
+// retroarch.cfg) replace the default button for that libretro button
+pub fn setup_joypad_device_map(config: &HashMap<String, String>) -> HashMap<Button, usize> {
+    let mut joypad_device_map = default_joypad_device_map();
+    let bindings: Vec<(Button, usize)> = JOYPAD_BINDING_NAMES
+        .iter()
+        .filter_map(|(name, libretro_button)| {
+            let config_key = format!("input_player1_{}_btn", name);
+            joypad_binding(config, &config_key, retroarch_button)
+                .map(|button| (button, *libretro_button as usize))
+        })
+        .collect();
+    joypad_device_map.retain(|_, libretro_button| {
+        !bindings.iter().any(|(_, bound)| bound == libretro_button)
+    });
+    joypad_device_map.extend(bindings);
+    joypad_device_map
+}
+
+// The input_player1_*_axis bindings, there are none by default as the left stick already drives the dpad
+pub fn setup_joypad_axis_bindings(config: &HashMap<String, String>) -> Vec<AxisBinding> {
+    JOYPAD_BINDING_NAMES
+        .iter()
+        .filter_map(|(name, libretro_button)| {
+            let config_key = format!("input_player1_{}_axis", name);
+            joypad_binding(config, &config_key, retroarch_axis).map(|(axis, positive)| AxisBinding {
+                axis,
+                positive,
+                button: *libretro_button as usize,
+            })
+        })
+        .collect()
+}
+
+fn default_joypad_device_map() -> HashMap<Button, usize> {
+    return HashMap::from([
+        (
+            Button::South,
+            libretro_sys::DEVICE_ID_JOYPAD_A as usize,
+        ),
+        (
+            Button::East,
+            libretro_sys::DEVICE_ID_JOYPAD_B as usize,
+        ),
+        (
+            Button::West,
+            libretro_sys::DEVICE_ID_JOYPAD_X as usize,
+        ),
+        (
+            Button::North,
+            libretro_sys::DEVICE_ID_JOYPAD_Y as usize,
+        ),
+        (
+            Button::LeftTrigger,
+            libretro_sys::DEVICE_ID_JOYPAD_L as usize,
+        ),
+        (
+            Button::LeftTrigger2,
+            libretro_sys::DEVICE_ID_JOYPAD_L2 as usize,
+        ),
+        (
+            Button::RightTrigger,
+            libretro_sys::DEVICE_ID_JOYPAD_R as usize,
+        ),
+        (
+            Button::RightTrigger2,
+            libretro_sys::DEVICE_ID_JOYPAD_R2 as usize,
+        ),
+        (
+            Button::DPadDown,            
+            libretro_sys::DEVICE_ID_JOYPAD_DOWN as usize,
+        ),
+        (
+            Button::DPadUp,
+            libretro_sys::DEVICE_ID_JOYPAD_UP as usize,
+        ),
+        (
+            Button::DPadRight,
+            libretro_sys::DEVICE_ID_JOYPAD_RIGHT as usize,
+        ),
+        (
+            Button::DPadLeft,
+            libretro_sys::DEVICE_ID_JOYPAD_LEFT as usize,
+        ),
+        (
+            Button::Start,
+            libretro_sys::DEVICE_ID_JOYPAD_START as usize,
+        ),
+        (
+            Button::Select,
+            libretro_sys::DEVICE_ID_JOYPAD_SELECT as usize,
+        ),
+    ]);
+}
+
+// Presses the dpad directions the analog stick is pushed past the deadzone in, each axis is checked
+// on its own so pushing diagonally presses both directions. Stick y is positive when pushed up.
+pub fn analog_to_dpad(stick_x: f32, stick_y: f32, deadzone: f32, buttons: &mut [i16]) {
+    let directions = [
+        (stick_x < -deadzone, libretro_sys::DEVICE_ID_JOYPAD_LEFT),
+        (stick_x > deadzone, libretro_sys::DEVICE_ID_JOYPAD_RIGHT),
+        (stick_y > deadzone, libretro_sys::DEVICE_ID_JOYPAD_UP),
+        (stick_y < -deadzone, libretro_sys::DEVICE_ID_JOYPAD_DOWN),
+    ];
+    for (pressed, button) in directions {
+        if pressed {
+            buttons[button as usize] = 1;
+        }
+    }
+}
+
+// input_swap_ab and input_swap_xy flip the face buttons for players used to the other layout, applied to
+// the buttons gathered from the keyboard and gamepad so no binding has to change
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct FaceButtonSwap {
+    pub ab: bool,
+    pub xy: bool,
+}
+
+impl FaceButtonSwap {
+    pub fn from_config(config: &HashMap<String, String>) -> FaceButtonSwap {
+        FaceButtonSwap {
+            ab: config["input_swap_ab"] == "true",
+            xy: config["input_swap_xy"] == "true",
+        }
+    }
+
+    pub fn apply(&self, buttons: &mut [i16]) {
+        let swaps = [
+            (self.ab, libretro_sys::DEVICE_ID_JOYPAD_A, libretro_sys::DEVICE_ID_JOYPAD_B),
+            (self.xy, libretro_sys::DEVICE_ID_JOYPAD_X, libretro_sys::DEVICE_ID_JOYPAD_Y),
+        ];
+        for (swap, first, second) in swaps {
+            if swap && buttons.len() > first.max(second) as usize {
+                buttons.swap(first as usize, second as usize);
+            }
+        }
+    }
+}
+
+// Every button gilrs maps gamepads to, so --test-input can show the ones that aren't bound too
+pub const GAMEPAD_BUTTONS: [Button; 19] = [
+    Button::South,
+    Button::East,
+    Button::North,
+    Button::West,
+    Button::C,
+    Button::Z,
+    Button::LeftTrigger,
+    Button::LeftTrigger2,
+    Button::RightTrigger,
+    Button::RightTrigger2,
+    Button::Select,
+    Button::Start,
+    Button::Mode,
+    Button::LeftThumb,
+    Button::RightThumb,
+    Button::DPadUp,
+    Button::DPadDown,
+    Button::DPadLeft,
+    Button::DPadRight,
+];
+
+// The name a libretro button has in the input_player1_{name} bindings e.g. DEVICE_ID_JOYPAD_A => "a"
+pub fn joypad_button_name(libretro_button: usize) -> &'static str {
+    JOYPAD_BINDING_NAMES
+        .iter()
+        .find(|(_, button)| *button as usize == libretro_button)
+        .map_or("unknown", |(name, _)| name)
+}
+
+// One line of --test-input, what a key or gamepad input is bound to e.g. "key z: a id 8"
+pub fn describe_input(input_name: &str, libretro_button: Option<usize>) -> String {
+    match libretro_button {
+        Some(button) => format!("{}: {} id {}", input_name, joypad_button_name(button), button),
+        None => format!("{}: not bound", input_name),
+    }
+}
+
+pub fn print_gamepads(gilrs: &Gilrs) {
+    for (index, (_, gamepad)) in gilrs.gamepads().enumerate() {
+        println!("Gamepad {}: {}", index, gamepad.name());
+    }
+}
+
+// Picks player 1's gamepad, the first whose name contains input_player1_gamepad_name (ignoring case) so the
+// right controller is used even if it reconnects with a different index, otherwise the one at
+// input_player1_joypad_index
+pub fn select_gamepad(gilrs: &Gilrs, config: &HashMap<String, String>) -> Option<GamepadId> {
+    let gamepad_name = config["input_player1_gamepad_name"].to_lowercase();
+    if !gamepad_name.is_empty() {
+        let named_gamepad = gilrs
+            .gamepads()
+            .find(|(_, gamepad)| gamepad.name().to_lowercase().contains(&gamepad_name));
+        if let Some((id, _)) = named_gamepad {
+            return Some(id);
+        }
+    }
+    let index = config["input_player1_joypad_index"].parse::<usize>().unwrap_or(0);
+    gilrs.gamepads().nth(index).map(|(id, _)| id)
+}
+
+// Hotkeys should fire once per physical press, so this compares the keys held this frame against the last
+// frame's rather than relying on key repeat events
+#[derive(Default)]
+pub struct KeyPressTracker {
+    held_keys: Vec<Key>,
+}
+
+impl KeyPressTracker {
+    pub fn new() -> KeyPressTracker {
+        KeyPressTracker::default()
+    }
+
+    // The keys held now that weren't held last frame
+    pub fn newly_pressed(&mut self, held_keys: &[Key]) -> Vec<Key> {
+        let newly_pressed = held_keys
+            .iter()
+            .filter(|key| !self.held_keys.contains(key))
+            .copied()
+            .collect();
+        self.held_keys = held_keys.to_vec();
+        newly_pressed
+    }
+}
+
+// confirm_exit, the exit key only quits when it's pressed a second time within `window` of the first
+pub struct ExitConfirmation {
+    window: Duration,
+    first_press: Option<Instant>,
+}
+
+impl ExitConfirmation {
+    pub fn new(window: Duration) -> ExitConfirmation {
+        ExitConfirmation {
+            window,
+            first_press: None,
+        }
+    }
+
+    // True when this press should quit, otherwise it's the first press and the prompt should be shown
+    pub fn press(&mut self, now: Instant) -> bool {
+        match self.first_press {
+            Some(first_press) if now.saturating_duration_since(first_press) <= self.window => true,
+            _ => {
+                self.first_press = Some(now);
+                false
+            }
+        }
+    }
+}
+
+// Lightgun ids newer than libretro_sys
+pub const DEVICE_ID_LIGHTGUN_SCREEN_X: u32 = 13;
+pub const DEVICE_ID_LIGHTGUN_SCREEN_Y: u32 = 14;
+pub const DEVICE_ID_LIGHTGUN_IS_OFFSCREEN: u32 = 15;
+pub const DEVICE_ID_LIGHTGUN_RELOAD: u32 = 16;
+
+// Where the lightgun is aimed, screen_x/y go from -0x7FFF at the left/top edge of the frame to 0x7FFF at
+// the right/bottom edge
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct LightgunState {
+    pub screen_x: i16,
+    pub screen_y: i16,
+    pub offscreen: bool,
+    pub trigger: bool,
+    pub reload: bool,
+}
+
+// The frame is stretched over the whole window so the mouse position relative to the window is also its
+// position on the frame. No position (the mouse is outside the window) counts as aiming off screen.
+pub fn lightgun_from_mouse(
+    mouse_position: Option<(f32, f32)>,
+    window_size: (usize, usize),
+    trigger: bool,
+    reload: bool,
+) -> LightgunState {
+    let to_screen = |position: f32, size: usize| {
+        let fraction = (position / size.max(1) as f32).clamp(0.0, 1.0);
+        (fraction * 2.0 * 0x7FFF as f32 - 0x7FFF as f32).round() as i16
+    };
+    match mouse_position {
+        Some((x, y)) => LightgunState {
+            screen_x: to_screen(x, window_size.0),
+            screen_y: to_screen(y, window_size.1),
+            offscreen: false,
+            trigger,
+            reload,
+        },
+        None => LightgunState {
+            offscreen: true,
+            trigger,
+            reload,
+            ..LightgunState::default()
+        },
+    }
+}
+
+// The RETROK_* id a core set to RETRO_DEVICE_KEYBOARD asks for each key by. Printable keys are their
+// lowercase ASCII value, the rest are numbered from 256 the way SDL 1.2 did.
+pub fn key_to_retrok(key: &Key) -> Option<u32> {
+    let letters = [
+        Key::A, Key::B, Key::C, Key::D, Key::E, Key::F, Key::G, Key::H, Key::I, Key::J, Key::K, Key::L, Key::M,
+        Key::N, Key::O, Key::P, Key::Q, Key::R, Key::S, Key::T, Key::U, Key::V, Key::W, Key::X, Key::Y, Key::Z,
+    ];
+    let digits = [
+        Key::Key0, Key::Key1, Key::Key2, Key::Key3, Key::Key4, Key::Key5, Key::Key6, Key::Key7, Key::Key8, Key::Key9,
+    ];
+    let keypad = [
+        Key::NumPad0, Key::NumPad1, Key::NumPad2, Key::NumPad3, Key::NumPad4, Key::NumPad5, Key::NumPad6,
+        Key::NumPad7, Key::NumPad8, Key::NumPad9,
+    ];
+    let function_keys = [
+        Key::F1, Key::F2, Key::F3, Key::F4, Key::F5, Key::F6, Key::F7, Key::F8, Key::F9, Key::F10, Key::F11,
+        Key::F12, Key::F13, Key::F14, Key::F15,
+    ];
+    let position = |keys: &[Key]| keys.iter().position(|k| k == key).map(|index| index as u32);
+    if let Some(index) = position(&letters) {
+        return Some(97 + index);
+    }
+    if let Some(index) = position(&digits) {
+        return Some(48 + index);
+    }
+    if let Some(index) = position(&keypad) {
+        return Some(256 + index);
+    }
+    if let Some(index) = position(&function_keys) {
+        return Some(282 + index);
+    }
+    let retrok = match key {
+        Key::Backspace => 8,
+        Key::Tab => 9,
+        Key::Enter => 13,
+        Key::Pause => 19,
+        Key::Escape => 27,
+        Key::Space => 32,
+        Key::Apostrophe => 39,
+        Key::Comma => 44,
+        Key::Minus => 45,
+        Key::Period => 46,
+        Key::Slash => 47,
+        Key::Semicolon => 59,
+        Key::Equal => 61,
+        Key::LeftBracket => 91,
+        Key::Backslash => 92,
+        Key::RightBracket => 93,
+        Key::Backquote => 96,
+        Key::Delete => 127,
+        Key::NumPadDot => 266,
+        Key::NumPadSlash => 267,
+        Key::NumPadAsterisk => 268,
+        Key::NumPadMinus => 269,
+        Key::NumPadPlus => 270,
+        Key::NumPadEnter => 271,
+        Key::Up => 273,
+        Key::Down => 274,
+        Key::Right => 275,
+        Key::Left => 276,
+        Key::Insert => 277,
+        Key::Home => 278,
+        Key::End => 279,
+        Key::PageUp => 280,
+        Key::PageDown => 281,
+        Key::NumLock => 300,
+        Key::CapsLock => 301,
+        Key::ScrollLock => 302,
+        Key::RightShift => 303,
+        Key::LeftShift => 304,
+        Key::RightCtrl => 305,
+        Key::LeftCtrl => 306,
+        Key::RightAlt => 307,
+        Key::LeftAlt => 308,
+        Key::LeftSuper => 311,
+        Key::RightSuper => 312,
+        Key::Menu => 319,
+        _ => return None,
+    };
+    Some(retrok)
+}
@@ -0,0 +1,78 @@
+use std::io::Read;
+use std::net::{TcpListener, TcpStream};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::thread;
+
+// --input-server protocol, a client streams fixed size packets (little-endian):
+//   u32 frame  the client's frame number, packets that aren't newer than the last one are dropped
+//   u16 mask   bit n is DEVICE_ID_JOYPAD_n held, as in replay files
+// The mask is held until the next packet. One client is served at a time, when it disconnects its
+// buttons are released and the next client can connect.
+pub const INPUT_PACKET_SIZE: usize = 6;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RemoteInput {
+    pub frame: u32,
+    pub mask: u16,
+}
+
+impl RemoteInput {
+    pub fn from_bytes(bytes: &[u8; INPUT_PACKET_SIZE]) -> RemoteInput {
+        RemoteInput {
+            frame: u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]),
+            mask: u16::from_le_bytes([bytes[4], bytes[5]]),
+        }
+    }
+
+    pub fn to_bytes(self) -> [u8; INPUT_PACKET_SIZE] {
+        let mut bytes = [0; INPUT_PACKET_SIZE];
+        bytes[..4].copy_from_slice(&self.frame.to_le_bytes());
+        bytes[4..].copy_from_slice(&self.mask.to_le_bytes());
+        bytes
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum InputServerEvent {
+    Input(RemoteInput),
+    Disconnected,
+}
+
+// Returns false once the main loop has gone away
+fn handle_client(mut stream: TcpStream, events: &Sender<InputServerEvent>) -> bool {
+    let mut last_frame = None;
+    let mut packet = [0; INPUT_PACKET_SIZE];
+    while stream.read_exact(&mut packet).is_ok() {
+        let input = RemoteInput::from_bytes(&packet);
+        if last_frame.is_some_and(|last_frame| input.frame <= last_frame) {
+            continue;
+        }
+        last_frame = Some(input.frame);
+        if events.send(InputServerEvent::Input(input)).is_err() {
+            return false;
+        }
+    }
+    println!(
+        "Input client disconnected after frame {}, releasing its buttons",
+        last_frame.map_or("none".to_string(), |frame| frame.to_string())
+    );
+    events.send(InputServerEvent::Disconnected).is_ok()
+}
+
+// Listens on a TCP address such as 127.0.0.1:55400, the returned Receiver should be drained once per frame
+pub fn spawn_input_server(address: &str) -> Result<Receiver<InputServerEvent>, String> {
+    let listener = TcpListener::bind(address)
+        .map_err(|e| format!("Failed to open input server on {}: {}", address, e))?;
+    println!("Listening for remote input on {}", address);
+    let (sender, receiver) = channel();
+    thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            let peer = stream.peer_addr().map_or("unknown".to_string(), |peer| peer.to_string());
+            println!("Input client connected: {}", peer);
+            if !handle_client(stream, &sender) {
+                return;
+            }
+        }
+    });
+    Ok(receiver)
+}
@@ -0,0 +1,52 @@
+// The RETRO_LANGUAGE_* values answered to ENVIRONMENT_GET_LANGUAGE, libretro_sys doesn't have them.
+// Indexed by the enum value so the names can be looked up both ways.
+pub const LANGUAGE_NAMES: [&str; 33] = [
+    "english",
+    "japanese",
+    "french",
+    "spanish",
+    "german",
+    "italian",
+    "dutch",
+    "portuguese_brazil",
+    "portuguese_portugal",
+    "russian",
+    "korean",
+    "chinese_traditional",
+    "chinese_simplified",
+    "esperanto",
+    "polish",
+    "vietnamese",
+    "arabic",
+    "greek",
+    "turkish",
+    "slovak",
+    "persian",
+    "hebrew",
+    "asturian",
+    "finnish",
+    "indonesian",
+    "swedish",
+    "ukrainian",
+    "czech",
+    "catalan_valencia",
+    "catalan",
+    "british_english",
+    "hungarian",
+    "belarusian",
+];
+
+pub const LANGUAGE_ENGLISH: u32 = 0;
+
+// Takes a name like "japanese" (ignoring case, spaces or dashes for underscores) or the number RetroArch
+// itself writes for user_language
+pub fn language_from_name(name: &str) -> Option<u32> {
+    let name = name.trim().to_ascii_lowercase().replace([' ', '-'], "_");
+    if let Ok(language) = name.parse::<u32>() {
+        return (language < LANGUAGE_NAMES.len() as u32).then_some(language);
+    }
+    LANGUAGE_NAMES
+        .iter()
+        .position(|language_name| *language_name == name)
+        .map(|language| language as u32)
+}
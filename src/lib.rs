@@ -0,0 +1,41 @@
+extern crate libc;
+extern crate libloading;
+
+pub mod audio;
+pub mod bios;
+mod callbacks;
+pub mod command_socket;
+pub mod config;
+pub mod controller_info;
+pub mod core;
+pub mod core_info;
+pub mod core_options;
+mod emulator;
+mod environment;
+pub mod frame_hash;
+pub mod input;
+pub mod input_server;
+pub mod language;
+pub mod menu;
+mod libretro;
+pub mod osd;
+pub mod ram_watch;
+pub mod recent;
+pub mod region;
+pub mod recording;
+pub mod replay;
+pub mod rewind;
+mod rom;
+pub mod rom_scan;
+pub mod rumble;
+pub mod save_state;
+pub mod screenshot;
+pub mod status;
+mod state;
+pub mod subsystem;
+pub mod video;
+pub mod video_capture;
+
+pub use emulator::{install_panic_hook, Emulator};
+pub use environment::GameInfoExt;
+pub use rom::{download_rom, game_info_meta, is_url, resolve_rom_path, url_file_name};
@@ -0,0 +1,161 @@
+use crate::callbacks::*;
+use crate::config::expand_path;
+use crate::environment::libretro_environment_callback;
+use crate::state::CURRENT_EMULATOR_STATE;
+use crate::rom::{content_path, LoadedContent, RomLoadOptions};
+use crate::subsystem::find_subsystem;
+use libloading::Library;
+use libretro_sys::{CoreAPI, GameInfo};
+
+const EXPECTED_LIB_RETRO_VERSION: u32 = 1;
+
+// The function pointers in the returned CoreAPI point into the returned Library, so the Library
+// must be kept alive until the core has been deinitialised and the CoreAPI is no longer used.
+// The core still needs to be set up with init_core.
+pub(crate) unsafe fn load_core(library_path: &String) -> Result<(CoreAPI, Library), String> {
+    unsafe {
+        let dylib = Library::new(expand_path(library_path))
+            .map_err(|e| format!("Failed to load Core {}: {}", library_path, e))?;
+
+        let core_api = CoreAPI {
+            retro_set_environment: *(dylib.get(b"retro_set_environment").unwrap()),
+            retro_set_video_refresh: *(dylib.get(b"retro_set_video_refresh").unwrap()),
+            retro_set_audio_sample: *(dylib.get(b"retro_set_audio_sample").unwrap()),
+            retro_set_audio_sample_batch: *(dylib.get(b"retro_set_audio_sample_batch").unwrap()),
+            retro_set_input_poll: *(dylib.get(b"retro_set_input_poll").unwrap()),
+            retro_set_input_state: *(dylib.get(b"retro_set_input_state").unwrap()),
+
+            retro_init: *(dylib.get(b"retro_init").unwrap()),
+            retro_deinit: *(dylib.get(b"retro_deinit").unwrap()),
+
+            retro_api_version: *(dylib.get(b"retro_api_version").unwrap()),
+
+            retro_get_system_info: *(dylib.get(b"retro_get_system_info").unwrap()),
+            retro_get_system_av_info: *(dylib.get(b"retro_get_system_av_info").unwrap()),
+            retro_set_controller_port_device: *(dylib
+                .get(b"retro_set_controller_port_device")
+                .unwrap()),
+
+            retro_reset: *(dylib.get(b"retro_reset").unwrap()),
+            retro_run: *(dylib.get(b"retro_run").unwrap()),
+
+            retro_serialize_size: *(dylib.get(b"retro_serialize_size").unwrap()),
+            retro_serialize: *(dylib.get(b"retro_serialize").unwrap()),
+            retro_unserialize: *(dylib.get(b"retro_unserialize").unwrap()),
+
+            retro_cheat_reset: *(dylib.get(b"retro_cheat_reset").unwrap()),
+            retro_cheat_set: *(dylib.get(b"retro_cheat_set").unwrap()),
+
+            retro_load_game: *(dylib.get(b"retro_load_game").unwrap()),
+            retro_load_game_special: *(dylib.get(b"retro_load_game_special").unwrap()),
+            retro_unload_game: *(dylib.get(b"retro_unload_game").unwrap()),
+
+            retro_get_region: *(dylib.get(b"retro_get_region").unwrap()),
+            retro_get_memory_data: *(dylib.get(b"retro_get_memory_data").unwrap()),
+            retro_get_memory_size: *(dylib.get(b"retro_get_memory_size").unwrap()),
+        };
+
+        Ok((core_api, dylib))
+    }
+}
+
+// Checks the core's API version and hands it all of our callbacks, must be called once before the game is loaded
+pub(crate) unsafe fn init_core(core_api: &CoreAPI) -> Result<(), String> {
+    unsafe {
+        let api_version = (core_api.retro_api_version)();
+        println!("API Version: {}", api_version);
+        if api_version != EXPECTED_LIB_RETRO_VERSION {
+            return Err(format!("The Core has been compiled with a LibRetro API that is unexpected, we expected version to be: {} but it was: {}", EXPECTED_LIB_RETRO_VERSION, api_version));
+        }
+        (core_api.retro_set_environment)(libretro_environment_callback);
+        (core_api.retro_init)();
+        (core_api.retro_set_video_refresh)(libretro_set_video_refresh_callback);
+        (core_api.retro_set_input_poll)(libretro_set_input_poll_callback);
+        (core_api.retro_set_input_state)(libretro_set_input_state_callback);
+        (core_api.retro_set_audio_sample)(libretro_set_audio_sample_callback);
+        (core_api.retro_set_audio_sample_batch)(libretro_set_audio_sample_batch_callback);
+        Ok(())
+    }
+}
+
+pub(crate) unsafe fn load_rom_file(
+    core_api: &CoreAPI,
+    rom_name: &str,
+    options: &RomLoadOptions,
+) -> Result<(), String> {
+    let rom_path = content_path(rom_name);
+    println!("Loading ROM file: {:?}", rom_path);
+    let content = LoadedContent::load(&rom_path, options)?;
+    let game_info = content.game_info();
+    CURRENT_EMULATOR_STATE.game_info = Some(game_info.clone());
+
+    println!("INFO: Calling retro_load_game in Core");
+    let was_load_successful = (core_api.retro_load_game)(&game_info);
+    // Kept until the state is reset after unloading as the core may keep pointers to the path, meta and data
+    CURRENT_EMULATOR_STATE.loaded_content = vec![content];
+    if !was_load_successful {
+        return Err("Rom Load was not successful".to_string());
+    }
+    println!("ROM was successfully loaded");
+    Ok(())
+}
+
+// Loads several ROMs at once for one of the subsystems the core advertised through ENVIRONMENT_SET_SUBSYSTEM_INFO,
+// `subsystem_name` is its ident or id
+pub(crate) unsafe fn load_subsystem_rom_files(
+    core_api: &CoreAPI,
+    subsystem_name: &str,
+    rom_paths: &[String],
+    options: &RomLoadOptions,
+) -> Result<(), String> {
+    let subsystems = (*std::ptr::addr_of!(CURRENT_EMULATOR_STATE.subsystems)).clone();
+    let subsystem = find_subsystem(&subsystems, subsystem_name).ok_or_else(|| {
+        let available: Vec<&str> = subsystems
+            .iter()
+            .map(|subsystem| subsystem.ident.as_str())
+            .collect();
+        format!(
+            "The Core has no subsystem {}, available subsystems: {:?}",
+            subsystem_name, available
+        )
+    })?;
+    let required_roms = subsystem.roms.iter().filter(|rom| rom.required).count();
+    if rom_paths.len() < required_roms || rom_paths.len() > subsystem.roms.len() {
+        let rom_descriptions: Vec<&str> = subsystem
+            .roms
+            .iter()
+            .map(|rom| rom.description.as_str())
+            .collect();
+        return Err(format!(
+            "Subsystem {} takes {} to {} ROMs {:?} but {} were given",
+            subsystem.ident,
+            required_roms,
+            subsystem.roms.len(),
+            rom_descriptions,
+            rom_paths.len()
+        ));
+    }
+
+    let mut loaded_content = Vec::new();
+    for rom_path in rom_paths {
+        let rom_path = content_path(rom_path);
+        println!("Loading ROM file: {:?}", rom_path);
+        loaded_content.push(LoadedContent::load(&rom_path, options)?);
+    }
+    let game_infos: Vec<GameInfo> = loaded_content.iter().map(LoadedContent::game_info).collect();
+    CURRENT_EMULATOR_STATE.game_info = game_infos.first().cloned();
+
+    println!(
+        "INFO: Calling retro_load_game_special in Core for subsystem {}",
+        subsystem.ident
+    );
+    let was_load_successful =
+        (core_api.retro_load_game_special)(subsystem.id, game_infos.as_ptr(), game_infos.len());
+    // Moving the CStrings, Vecs and mappings doesn't move their data so the pointers handed to the core stay valid
+    CURRENT_EMULATOR_STATE.loaded_content = loaded_content;
+    if !was_load_successful {
+        return Err("Subsystem Rom Load was not successful".to_string());
+    }
+    println!("ROMs were successfully loaded");
+    Ok(())
+}
@@ -1,1027 +1,1845 @@
-extern crate libc;
-extern crate libloading;
-use clap::{App, Arg};
-
-use libloading::Library;
-use libretro_sys::{CoreAPI, GameInfo, PixelFormat, SystemAvInfo, GameGeometry, SystemTiming, LogCallback, LogLevel};
-use minifb::{Key, KeyRepeat, Window, WindowOptions};
+use clap::{App, Arg, ArgMatches};
+
+use minifb::{Key, MouseButton, MouseMode, ScaleMode, Window, WindowOptions};
+use rustro_arch::audio::{output_device_names, speed_up_samples, AudioOutput};
+use rustro_arch::command_socket::{spawn_command_listener, Command};
+use rustro_arch::config::{
+    get_analog_deadzone, get_audio_latency_ms, get_audio_max_timing_skew, get_autoload_slot, get_color_correction, get_fast_forward_frames, get_fps_display, get_fps_sample_interval, get_load_fail_alert,
+    get_aspect_mode, get_max_save_slots, get_overscan_crop, get_quick_swap_slot, get_rewind_buffer_size, get_rewind_granularity, get_run_ahead_frames, get_savestate_compression, get_video_filter, get_video_rotation,
+    get_video_scale, get_video_scale_max, get_window_size_limits, expand_path, setup_config_for_core,
+};
+use rustro_arch::controller_info::describe_device;
+use rustro_arch::core_info::{
+    find_core_for_rom, find_cores, read_core_controller_types, read_core_info, read_core_subsystems, CoreInfo,
+};
+use rustro_arch::core_options::{game_options_path, load_game_options, parse_core_option_argument, save_game_options};
+use rustro_arch::frame_hash::frame_crc32;
+use rustro_arch::input::{
+    analog_to_dpad, bound_keys, describe_input, is_key_bound, key_to_config_name, lightgun_from_mouse, print_gamepads,
+    select_gamepad, setup_joypad_axis_bindings, setup_joypad_device_map, setup_key_device_map,
+    find_key_collisions, ExitConfirmation, FaceButtonSwap, KeyPressTracker, GAMEPAD_BUTTONS,
+};
+use rustro_arch::input_server::{spawn_input_server, InputServerEvent};
+use rustro_arch::menu::RomMenu;
+use rustro_arch::osd::{draw_text, draw_text_scaled, invert_colors, FpsDisplay, LoadFailAlert, OsdMessage, TEXT_HEIGHT};
+use rustro_arch::ram_watch::{self, WatchEntry};
+use rustro_arch::recent::{load_last_game, save_last_game, LastGame, LAST_GAME_FILE};
+use rustro_arch::recording::AvRecorder;
+use rustro_arch::replay::{self, InputPlayback, InputRecorder};
+use rustro_arch::rewind::RewindBuffer;
+use rustro_arch::rom_scan::{roms_to_json, scan_roms};
+use rustro_arch::rumble::GamepadRumble;
+use rustro_arch::save_state::{
+    auto_index_slot, highest_occupied_slot, next_save_slot, quick_swap_save_slot, StateVerification,
+};
+use rustro_arch::status::{take_stdout, Status};
+use rustro_arch::video::{
+    letterbox, next_video_scale, present_frame, rotated_size, PresentOptions, VideoFilter, WindowSizeLimits,
+};
+use rustro_arch::video_capture::VideoRecorder;
+use rustro_arch::{install_panic_hook, is_url, resolve_rom_path, Emulator};
 use std::collections::HashMap;
-use std::ffi::{c_void, CString, CStr};
-use std::fs::File;
-use std::io::Read;
-use std::io::{BufRead, BufReader};
+use std::fs;
+use std::io::{self, Write};
 use std::path::{Path, PathBuf};
 use std::time::{Duration, Instant};
-use std::{env, fs, ptr, mem}; // Add this line to import the Read trait
-use rodio::{Sink, OutputStream, OutputStreamHandle};
-use rodio::buffer::SamplesBuffer;
-use std::sync::mpsc::{channel, Sender};
-use std::thread;
-
-use gilrs::{Gilrs, Button, Event};
-
 
-const EXPECTED_LIB_RETRO_VERSION: u32 = 1;
+use gilrs::{Axis, Event, EventType, Gilrs};
 
-const audio_enable: bool = false;
-
-struct EmulatorState {
-    rom_name: String,
-    core_name: String,
-    frame_buffer: Option<Vec<u32>>,
-    audio_data: Option<Vec<i16>>,
-    pixel_format: PixelFormat,
-    bytes_per_pixel: u8, // its only either 2 or 4 bytes per pixel in libretro
-    screen_pitch: u32,
-    screen_width: u32,
-    screen_height: u32,
-    buttons_pressed: Option<Vec<i16>>,
-    current_save_slot: u8,
-    av_info: Option<SystemAvInfo>,
-    game_info: Option<GameInfo>,
-    game_info_ext: Option<GameInfoExt>,
-    system_directory: Option<CString>
+fn parse_command_line_arguments() -> ArgMatches<'static> {
+    App::new("RustroArch")
+        .arg(
+            Arg::with_name("rom_name")
+                .help("Sets the path to the ROM file to load")
+                .required_unless_one(&["list_cores", "list_subsystems", "list_controllers", "subsystem", "test_input", "continue", "menu", "scan_roms", "list_audio_devices"])
+                .index(1),
+        )
+        .arg(
+            Arg::with_name("library_name")
+                .help("Sets the path to the libRetro core to use")
+                .short("L")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("record_input")
+                .help("Records the input of every frame (and the starting save state) to a replay file")
+                .long("record-input")
+                .takes_value(true)
+                .conflicts_with("play_input"),
+        )
+        .arg(
+            Arg::with_name("play_input")
+                .help("Plays back a replay file recorded with --record-input instead of live input")
+                .long("play-input")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("replay_continue")
+                .help("Return control to the user when the replay ends instead of quitting")
+                .long("replay-continue")
+                .requires("play_input"),
+        )
+        .arg(
+            Arg::with_name("record_video")
+                .help("Records the shown frames to a raw XRGB8888 video file, see src/video_capture.rs for the ffmpeg command to encode it")
+                .long("record-video")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("record")
+                .help("Records the video and audio to {file}.raw and {file}.wav, the ffmpeg command to mux them is printed at the end")
+                .long("record")
+                .takes_value(true)
+                .conflicts_with("record_video"),
+        )
+        .arg(
+            Arg::with_name("command_socket")
+                .help("Listens on a TCP address (e.g. 127.0.0.1:55355) for commands: SAVE n, LOAD n, SCREENSHOT, PAUSE, RESET, QUIT")
+                .long("command-socket")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("input_server")
+                .help("Listens on a TCP address (e.g. 127.0.0.1:55400) for a client sending each frame's buttons (u32 frame + u16 mask, little-endian)")
+                .long("input-server")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("input_server_port")
+                .help("The controller port (1-4, default 1) the --input-server's buttons are pressed on, they're added to the local input on port 1")
+                .long("input-server-port")
+                .takes_value(true)
+                .possible_values(&["1", "2", "3", "4"])
+                .requires("input_server"),
+        )
+        .arg(
+            Arg::with_name("subsystem")
+                .help("Loads the --rom files with one of the core's subsystems (ident or id), e.g. sgb for the Super Game Boy")
+                .long("subsystem")
+                .takes_value(true)
+                .requires("rom")
+                .conflicts_with("rom_name"),
+        )
+        .arg(
+            Arg::with_name("rom")
+                .help("A ROM to load with --subsystem, give it once for each ROM the subsystem needs")
+                .long("rom")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1)
+                .requires("subsystem"),
+        )
+        .arg(
+            Arg::with_name("scale_filter")
+                .help("Filter applied to each frame before it's shown, overrides video_filter from the config")
+                .long("scale-filter")
+                .takes_value(true)
+                .possible_values(&VideoFilter::NAMES),
+        )
+        .arg(
+            Arg::with_name("core_option")
+                .help("Sets one of the core's options (key=value), overrides the config, give it once for each option")
+                .long("core-option")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1),
+        )
+        .arg(
+            Arg::with_name("load_state_on_boot")
+                .help("Loads this save state slot as soon as the game has loaded, overrides autoload_slot from the config")
+                .long("load-state-on-boot")
+                .takes_value(true)
+                .conflicts_with("play_input"),
+        )
+        .arg(
+            Arg::with_name("meta")
+                .help("A string passed to the core in GameInfo.meta, overrides game_info_meta from the config and {rom}.meta files")
+                .long("meta")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("no_audio")
+                .help("Doesn't open an audio device, overrides audio_enabled from the config. For machines without sound.")
+                .long("no-audio"),
+        )
+        .arg(
+            Arg::with_name("headless")
+                .help("Runs without a window, e.g. over SSH or in CI. Also used when the window can't be created.")
+                .long("headless"),
+        )
+        .arg(
+            Arg::with_name("run_seconds")
+                .help("Quits cleanly after running for this many seconds (wall clock), e.g. to check a core boots and keeps running in CI")
+                .long("run-seconds")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("test_input")
+                .help("Opens a window showing what each held key and gamepad button is bound to, without loading a core. Escape quits.")
+                .long("test-input"),
+        )
+        .arg(
+            Arg::with_name("hash_frames")
+                .help("Runs this many frames as fast as possible without a window (with --play-input's input if given), prints the CRC32 of the last frame and exits")
+                .long("hash-frames")
+                .takes_value(true)
+                .conflicts_with_all(&["record_input", "record_video", "record"]),
+        )
+        .arg(
+            Arg::with_name("hash_every_frame")
+                .help("Prints the CRC32 of every frame --hash-frames runs, not just the last")
+                .long("hash-every-frame")
+                .requires("hash_frames"),
+        )
+        .arg(
+            Arg::with_name("strict")
+                .help("Refuses to load a ROM whose extension isn't one the core lists, instead of just warning")
+                .long("strict"),
+        )
+        .arg(
+            Arg::with_name("menu")
+                .help("Shows the ROMs in roms_directory to choose one from, the core is the first in cores_directory that loads it unless -L is given")
+                .long("menu")
+                .conflicts_with_all(&["rom_name", "subsystem", "continue"]),
+        )
+        .arg(
+            Arg::with_name("continue")
+                .help("Launches the last game played again with the same core, -L picks a different core")
+                .long("continue")
+                .conflicts_with_all(&["rom_name", "subsystem"]),
+        )
+        .arg(
+            Arg::with_name("verify_savestate")
+                .help("Checks the core's save states: saves one, runs this many frames, loads it and runs them again (with --play-input's input if given), reports whether both runs match and exits")
+                .long("verify-savestate")
+                .takes_value(true)
+                .conflicts_with_all(&["record_input", "record_video", "record", "hash_frames"]),
+        )
+        .arg(
+            Arg::with_name("list_subsystems")
+                .help("Lists the subsystems (multi ROM configurations) the -L core supports and exits")
+                .long("list-subsystems"),
+        )
+        .arg(
+            Arg::with_name("list_controllers")
+                .help("Lists the devices the -L core says each port can take, the names input_libretro_device_p* accepts, and exits")
+                .long("list-controllers"),
+        )
+        .arg(
+            Arg::with_name("dump_audio")
+                .help("Writes the core's audio to this file as raw 16 bit stereo at the core's sample rate (logged, for ffplay -f s16le)")
+                .long("dump-audio")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("status_json")
+                .help("Prints a line of JSON a second on stdout (fps, frame, save slot, pixel format, resolution, paused and muted) for scripts, the logs go to stderr instead")
+                .long("status-json"),
+        )
+        .arg(
+            Arg::with_name("scan_roms")
+                .help("Lists the files in a directory the -L core loads, going by its extensions, and exits")
+                .long("scan-roms")
+                .takes_value(true)
+                .requires("library_name"),
+        )
+        .arg(
+            Arg::with_name("recursive")
+                .help("Makes --scan-roms look in subdirectories too")
+                .long("recursive")
+                .requires("scan_roms"),
+        )
+        .arg(
+            Arg::with_name("json")
+                .help("Prints --scan-roms' list as a JSON array of paths")
+                .long("json")
+                .requires("scan_roms"),
+        )
+        .arg(
+            Arg::with_name("list_audio_devices")
+                .help("Lists the audio output devices audio_device can pick from and exits")
+                .long("list-audio-devices"),
+        )
+        .arg(
+            Arg::with_name("list_cores")
+                .help("Lists the cores in a directory (default cores_directory from the config) and exits")
+                .long("list-cores")
+                .takes_value(true)
+                .min_values(0)
+                .max_values(1),
+        )
+        .get_matches()
 }
 
-static mut CURRENT_EMULATOR_STATE: EmulatorState = EmulatorState {
-    rom_name: String::new(),
-    core_name: String::new(),
-    frame_buffer: None,
-    audio_data: None,
-    pixel_format: PixelFormat::ARGB8888,
-    bytes_per_pixel: 4,
-    screen_pitch: 0,
-    screen_width: 0,
-    screen_height: 0,
-    buttons_pressed: None,
-    current_save_slot: 0,
-    av_info: None,
-    game_info: None,
-    game_info_ext: None,
-    system_directory: None
-};
-
-// retro_game_info_ext wasn't in libretro-sys package so declaring it here
-pub struct GameInfoExt {
-    pub full_path: *const libc::c_char,
-    pub archive_path: *const libc::c_char,
-    pub archive_file: *const libc::c_char,
-    pub dir: *const libc::c_char,
-    pub name: *const libc::c_char,
-    pub ext: *const libc::c_char,
-    pub meta: *const libc::c_char,
-    pub data: *const libc::c_void,
-    /* Size of game content memory buffer, in bytes */
-    pub size: libc::size_t,
-    pub file_in_archive: bool,
-    pub persistent_data: bool,
+fn print_arguments(matches: &ArgMatches) {
+    if let Some(rom_name) = matches.value_of("rom_name") {
+        println!("ROM name: {}", rom_name);
+    }
+    println!(
+        "Core Library name: {}",
+        matches.value_of("library_name").unwrap_or("default_library")
+    );
 }
 
-////////////////////////
-// Utility FUnctions
-////////////////////////
+// When input_hotkey_enable is bound, hotkeys only fire while that key is also held down
+fn are_hotkeys_enabled(config: &HashMap<String, String>, held_keys: &[Key]) -> bool {
+    let hotkey_enable = &config["input_hotkey_enable"];
+    if bound_keys(hotkey_enable).next().is_none() {
+        return true;
+    }
+    held_keys
+        .iter()
+        .any(|key| is_key_bound(hotkey_enable, &key_to_config_name(key)))
+}
 
-// Convert the input String to a CString, but be-careful with memory management when sending this to a core..
-fn convert_to_cstring(input: String) -> CString {
-    CString::new(input).expect("Failed to convert to CString")
+// Some setups (remote desktops, VMs without GPU drivers) refuse the default window, so each scale mode
+// is tried in turn before giving up
+fn create_window(width: usize, height: usize) -> Result<Window, String> {
+    let mut errors = Vec::new();
+    for scale_mode in [
+        ScaleMode::Stretch,
+        ScaleMode::AspectRatioStretch,
+        ScaleMode::Center,
+        ScaleMode::UpperLeft,
+    ] {
+        let options = WindowOptions {
+            scale_mode,
+            ..WindowOptions::default()
+        };
+        match Window::new("RustroArch", width, height, options) {
+            Ok(window) => return Ok(window),
+            Err(err) => errors.push(format!("{:?}: {}", scale_mode, err)),
+        }
+    }
+    Err(format!("Failed to create a window ({})", errors.join(", ")))
 }
 
-// print_c_string simply takes in a Cstring(libc::c_char pointer) and prints it to the console
-fn print_c_string(c_string_ptr: *const libc::c_char) {
-    unsafe {
-        if !c_string_ptr.is_null() {
-            let c_str = CStr::from_ptr(c_string_ptr);
-            if let Ok(rust_string) = c_str.to_str() {
-                println!("{}", rust_string);
+// --test-input, lists what every held key and gamepad input presses in the window (and the terminal when
+// it changes) so bindings can be checked without a core
+fn run_input_test(config: &HashMap<String, String>) {
+    let key_device_map = setup_key_device_map(config);
+    let joypad_device_map = setup_joypad_device_map(config);
+    let joypad_axis_bindings = setup_joypad_axis_bindings(config);
+    let analog_deadzone = get_analog_deadzone(config);
+    let mut window = create_window(640, 480).unwrap_or_else(|e| panic!("{}", e));
+    window.limit_update_rate(Some(Duration::from_micros(16600)));
+    let mut gilrs = Gilrs::new().unwrap();
+    print_gamepads(&gilrs);
+    let mut active_gamepad = select_gamepad(&gilrs, config);
+    println!("Press keys or gamepad buttons to see what they're bound to, Escape quits");
+
+    let (width, height) = (320, 240);
+    let mut last_lines = Vec::new();
+    while window.is_open() && !window.is_key_down(Key::Escape) {
+        while let Some(Event { event, .. }) = gilrs.next_event() {
+            if matches!(event, EventType::Connected | EventType::Disconnected) {
+                active_gamepad = select_gamepad(&gilrs, config);
             }
         }
+        let mut lines: Vec<String> = window
+            .get_keys()
+            .unwrap_or_default()
+            .iter()
+            .map(|key| {
+                let key_name = key_to_config_name(key);
+                describe_input(&format!("key {}", key_name), key_device_map.get(&key_name).copied())
+            })
+            .collect();
+        if let Some(gamepad) = active_gamepad.map(|id| gilrs.gamepad(id)) {
+            for button in GAMEPAD_BUTTONS.iter().filter(|button| gamepad.is_pressed(**button)) {
+                let input_name = format!("pad {:?}", button).to_lowercase();
+                lines.push(describe_input(&input_name, joypad_device_map.get(button).copied()));
+            }
+            for binding in &joypad_axis_bindings {
+                if binding.is_pressed(gamepad.value(binding.axis), analog_deadzone) {
+                    let direction = if binding.positive { "plus" } else { "minus" };
+                    let input_name = format!("pad {:?} {}", binding.axis, direction).to_lowercase();
+                    lines.push(describe_input(&input_name, Some(binding.button)));
+                }
+            }
+        }
+        if lines != last_lines {
+            lines.iter().for_each(|line| println!("{}", line));
+            let title = if lines.is_empty() { "nothing pressed".to_string() } else { lines.join(", ") };
+            window.set_title(&format!("RustroArch input test - {}", title));
+            last_lines = lines;
+        }
+
+        let mut buffer = vec![0; width * height];
+        draw_text(&mut buffer, width, height, 1, 1, "press keys or buttons - escape quits");
+        for (index, line) in last_lines.iter().enumerate() {
+            draw_text(&mut buffer, width, height, 1, (index + 2) * (TEXT_HEIGHT + 1), line);
+        }
+        if let Err(err) = window.update_with_buffer(&buffer, width, height) {
+            println!("Failed to present frame: {}", err);
+        }
     }
 }
 
-///////////////////////
-// Config Functions
-///////////////////////
-fn get_retroarch_config_path() -> PathBuf {
-    return match std::env::consts::OS {
-        "windows" => PathBuf::from(env::var("APPDATA").ok().unwrap()).join("retroarch"),
-        "macos" => PathBuf::from(env::var("HOME").ok().unwrap())
-            .join("Library/Application Support/RetroArch"),
-        _ => PathBuf::from(env::var("XDG_CONFIG_HOME").ok().unwrap()).join("retroarch"),
+// --menu, shows the ROMs in roms_directory until one is chosen that a core can load. Returns the core and
+// ROM paths, or None when the window is closed.
+fn run_rom_menu(config: &HashMap<String, String>, library_name: Option<&str>) -> Option<(String, String)> {
+    let roms_directory = match config["roms_directory"].as_str() {
+        "" => PathBuf::from("."),
+        roms_directory => expand_path(roms_directory),
     };
-}
+    let mut menu = RomMenu::scan(&roms_directory).unwrap_or_else(|e| panic!("{}", e));
+    let key_device_map = setup_key_device_map(config);
+    let joypad_device_map = setup_joypad_device_map(config);
+    let mut window = create_window(640, 480).unwrap_or_else(|e| panic!("{}", e));
+    window.limit_update_rate(Some(Duration::from_micros(16600)));
+    let mut gilrs = Gilrs::new().unwrap();
+    let mut key_tracker = KeyPressTracker::new();
+    println!("Choose a ROM from {}", roms_directory.display());
 
-fn parse_retroarch_config(config_file: &Path) -> Result<HashMap<String, String>, String> {
-    let file = File::open(config_file).map_err(|e| format!("Failed to open file: {}", e))?;
-    let reader = BufReader::new(file);
-    let mut config_map = HashMap::new();
-    for line in reader.lines() {
-        let line = line.map_err(|e| format!("Failed to read line: {}", e))?;
-        if let Some((key, value)) = line.split_once("=") {
-            config_map.insert(
-                key.trim().to_string(),
-                value.trim().replace("\"", "").to_string(),
-            );
+    let (width, height) = (320, 240);
+    let mut buffer = vec![0; width * height];
+    let mut status = String::new();
+    while window.is_open() && !window.is_key_down(Key::Escape) {
+        // Bound the same as in game, so the keyboard's dpad and start keys and the gamepad's work
+        let mut pressed: Vec<usize> = key_tracker
+            .newly_pressed(&window.get_keys().unwrap_or_default())
+            .iter()
+            .filter_map(|key| key_device_map.get(&key_to_config_name(key)).copied())
+            .collect();
+        while let Some(Event { event, .. }) = gilrs.next_event() {
+            if let EventType::ButtonPressed(button, _) = event {
+                pressed.extend(joypad_device_map.get(&button).copied());
+            }
+        }
+        for button in pressed {
+            match button as u32 {
+                libretro_sys::DEVICE_ID_JOYPAD_UP => menu.move_selection(false),
+                libretro_sys::DEVICE_ID_JOYPAD_DOWN => menu.move_selection(true),
+                libretro_sys::DEVICE_ID_JOYPAD_START => {
+                    let Some(rom_path) = menu.selected().map(|rom| rom.to_string_lossy().into_owned()) else {
+                        continue;
+                    };
+                    let core_path = library_name.map(PathBuf::from).or_else(|| {
+                        find_core_for_rom(&expand_path(&config["cores_directory"]), &rom_path)
+                    });
+                    match core_path {
+                        Some(core_path) => return Some((core_path.to_string_lossy().into_owned(), rom_path)),
+                        None => {
+                            println!("No core in {} loads {}", config["cores_directory"], rom_path);
+                            status = "no core in cores_directory loads this rom".to_string();
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+        menu.draw(&mut buffer, width, height, &status);
+        if let Err(err) = window.update_with_buffer(&buffer, width, height) {
+            println!("Failed to present frame: {}", err);
         }
     }
-    Ok(config_map)
+    None
 }
 
-fn convert_pixel_array_from_rgb565_to_xrgb8888(color_array: &[u8]) -> Box<[u32]> {
-    println!("convert_pixel_array_from_rgb565_to_xrgb8888");
-    let bytes_per_pixel = 2;
-    assert_eq!(
-        color_array.len() % bytes_per_pixel,
-        0,
-        "color_array length must be a multiple of 2 (16-bits per pixel)"
-    );
-
-    let num_pixels = color_array.len() / bytes_per_pixel;
-    let mut result = vec![0u32; num_pixels];
-
-    for i in 0..num_pixels {
-        // This Rust code is decoding a 16-bit color value, represented by two bytes of data, into its corresponding red, green, and blue components.
-        let first_byte = color_array[bytes_per_pixel * i];
-        let second_byte = color_array[(bytes_per_pixel * i) + 1];
-
-        // First extract the red component from the first byte. The first byte contains the most significant 8 bits of the 16-bit color value. The & operator performs a bitwise AND operation on first_byte and 0b1111_1000, which extracts the 5 most significant bits of the byte. The >> operator then shifts the extracted bits to the right by 3 positions, effectively dividing by 8, to get the value of the red component on a scale of 0-31.
-        let red = (first_byte & 0b1111_1000) >> 3;
-        // Next extract the green component from both bytes. The first part of the expression ((first_byte & 0b0000_0111) << 3) extracts the 3 least significant bits of first_byte and shifts them to the left by 3 positions, effectively multiplying by 8. The second part of the expression ((second_byte & 0b1110_0000) >> 5) extracts the 3 most significant bits of second_byte and shifts them to the right by 5 positions, effectively dividing by 32. The two parts are then added together to get the value of the green component on a scale of 0-63.
-        let green = ((first_byte & 0b0000_0111) << 3) + ((second_byte & 0b1110_0000) >> 5);
-        // Next extract the blue component from the second byte. The & operator performs a bitwise AND operation on second_byte and 0b0001_1111, which extracts the 5 least significant bits of the byte. This gives the value of the blue component on a scale of 0-31.
-        let blue = second_byte & 0b0001_1111;
-
-        // Use high bits for empty low bits as we have more bits available in XRGB8888
-        let red = (red << 3) | (red >> 2);
-        let green = (green << 2) | (green >> 3);
-        let blue = (blue << 3) | (blue >> 2);
-
-        // Finally save the pixel data in the result array as an XRGB8888 value
-        result[i] = ((red as u32) << 16) | ((green as u32) << 8) | (blue as u32);
+// --hash-frames, runs the frames and prints CRC32s of what the core drew so its output can be checked
+// against known good hashes. With a replay for the input every run is the same.
+fn print_frame_hashes(emulator: &mut Emulator, frames: u64, mut playback: Option<&mut InputPlayback>, every_frame: bool) {
+    let last_frame = emulator.frame_count() + frames;
+    while emulator.frame_count() < last_frame {
+        let next_frame = emulator.frame_count() + 1;
+        if let Err(err) = emulator.run_to_frame(next_frame, playback.as_deref_mut()) {
+            panic!("{}", err);
+        }
+        if emulator.should_quit() {
+            println!("Stopping at frame {} as the core asked to shut down", emulator.frame_count());
+            return;
+        }
+        if !every_frame && emulator.frame_count() < last_frame {
+            continue;
+        }
+        match emulator.visible_frame() {
+            Some(frame) => println!(
+                "Frame {} {}x{} crc32 {:08x}",
+                emulator.frame_count(),
+                emulator.screen_width(),
+                emulator.screen_height(),
+                frame_crc32(&frame)
+            ),
+            None => println!("Frame {} crc32 none, the core hasn't drawn a frame", emulator.frame_count()),
+        }
     }
-
-    result.into_boxed_slice()
 }
 
-unsafe extern "C" fn libretro_set_video_refresh_callback(
-    frame_buffer_data: *const libc::c_void,
-    width: libc::c_uint,
-    height: libc::c_uint,
-    pitch: libc::size_t,
-) {
-    println!("libretro_set_video_refresh_callback width: {} height: {} pitch: {}", width, height, pitch);
-    if (frame_buffer_data == ptr::null()) {
-        println!("frame_buffer_data was null");
-        return;
+// --verify-savestate, exits with 1 when the core's save states don't round-trip
+fn verify_save_state(emulator: &mut Emulator, frames: u64, playback: Option<&mut InputPlayback>) {
+    println!("Verifying save states over {} frames", frames);
+    match emulator.verify_save_state(frames, playback) {
+        Ok(StateVerification::Passed) => println!("Save state verification passed"),
+        Ok(StateVerification::FrameMismatch(frame)) => {
+            println!("Save state verification failed: frame {} was drawn differently after loading the state", frame);
+            std::process::exit(1);
+        }
+        Ok(StateVerification::StateMismatch) => {
+            println!("Save state verification failed: the frames matched but the core's state afterwards didn't");
+            std::process::exit(1);
+        }
+        Err(err) => {
+            println!("Save state verification failed: {}", err);
+            std::process::exit(1);
+        }
     }
-    let length_of_frame_buffer =
-        ((pitch as u32) * height) * CURRENT_EMULATOR_STATE.bytes_per_pixel as u32;
-        println!("length_of_frame_buffer: {}", length_of_frame_buffer);
-    let buffer_slice = std::slice::from_raw_parts(
-        frame_buffer_data as *const u8,
-        length_of_frame_buffer as usize,
-    );
-    println!("got buffer_slice");
-    let result = match CURRENT_EMULATOR_STATE.pixel_format {
-        PixelFormat::RGB565 => Vec::from(convert_pixel_array_from_rgb565_to_xrgb8888(buffer_slice)),
-        PixelFormat::ARGB8888 => {
-            println!("ARGB8888 len:{} w*h*p: {}",  buffer_slice.len(), width * height);
-            // std::slice::from_raw_parts(buffer_slice.as_ptr() as *const u32, buffer_slice.len()).to_vec() // original code doesn't work in nestopia
-            std::slice::from_raw_parts(buffer_slice.as_ptr() as *const u32, buffer_slice.len()/4).to_vec() // dividing by 4 here seems to fix nestopia for some reason
-        },
-        _ => panic!("Unknown Pixel Format {:?}", CURRENT_EMULATOR_STATE.pixel_format)
-    };
-    println!("Middle of libretro_set_video_refresh_callback");
-
-    // Wrap the Vec<u8> in an Option and assign it to the frame_buffer field
-    CURRENT_EMULATOR_STATE.frame_buffer = Some(result);
-    CURRENT_EMULATOR_STATE.screen_height = height;
-    CURRENT_EMULATOR_STATE.screen_width = width;
-    CURRENT_EMULATOR_STATE.screen_pitch = pitch as u32;
-    println!("End of libretro_set_video_refresh_callback")
 }
 
-unsafe extern "C" fn libretro_set_input_poll_callback() {
-    println!("libretro_set_input_poll_callback")
+// Queue the samples from the last frame, reopening the audio device first if the core's sample rate
+// has changed (ENVIRONMENT_SET_SYSTEM_AV_INFO or a different core being swapped in)
+fn queue_audio(
+    audio_output: &mut AudioOutput,
+    emulator: &Emulator,
+    samples: &[i16],
+    latency_ms: u32,
+) -> Result<(), String> {
+    let sample_rate = emulator.av_info().timing.sample_rate as u32;
+    if audio_output.sample_rate() != sample_rate {
+        *audio_output = AudioOutput::new(
+            sample_rate,
+            latency_ms,
+            audio_output.device_name(),
+            audio_output.max_timing_skew(),
+        )?;
+    }
+    audio_output.push_samples(samples);
+    Ok(())
 }
 
-unsafe extern "C" fn libretro_set_input_state_callback(
-    port: libc::c_uint,
-    device: libc::c_uint,
-    index: libc::c_uint,
-    id: libc::c_uint,
-) -> i16 {
-    // println!("libretro_set_input_state_callback port: {} device: {} index: {} id: {}", port, device, index, id);
-    let is_pressed = match &CURRENT_EMULATOR_STATE.buttons_pressed {
-        Some(buttons_pressed) => buttons_pressed[id as usize],
-        None => 0,
-    };
-
-    return is_pressed;
+// Runs `frames` frames for one shown frame. The audio from all of them is collected into `audio_samples`
+// (when given) as run_frame only keeps the last frame's audio.
+fn run_frames(
+    emulator: &mut Emulator,
+    frames: u32,
+    mut audio_samples: Option<&mut Vec<i16>>,
+) -> Result<(), String> {
+    for _ in 0..frames {
+        emulator.run_frame()?;
+        if let Some(audio_samples) = audio_samples.as_mut() {
+            audio_samples.extend_from_slice(emulator.audio_data());
+        }
+    }
+    Ok(())
 }
 
-unsafe extern "C" fn libretro_set_audio_sample_callback(left: i16, right: i16) {
-    println!("libretro_set_audio_sample_callback left channel: {} right: {}", left, right);
+// Steps back to the newest rewind snapshot and runs a frame from it so it's drawn. Once they've all been
+// used up the game stays where it is until the key is let go.
+fn rewind_frame(emulator: &mut Emulator, rewind: &mut RewindBuffer) -> Result<(), String> {
+    match rewind.pop() {
+        Ok(Some(state)) if emulator.unserialize(&state) => emulator.run_frame(),
+        Ok(Some(_)) => {
+            println!("The core couldn't load a rewind snapshot");
+            Ok(())
+        }
+        Ok(None) => Ok(()),
+        Err(err) => {
+            println!("{}", err);
+            Ok(())
+        }
+    }
 }
 
-const AUDIO_CHANNELS: usize = 2; // left and right
-unsafe extern "C" fn libretro_set_audio_sample_batch_callback(
-    audio_data: *const i16,
-    frames: libc::size_t,
-) -> libc::size_t {
-    let audio_slice = std::slice::from_raw_parts(audio_data, frames * AUDIO_CHANNELS);
-    CURRENT_EMULATOR_STATE.audio_data = Some(audio_slice.to_vec());
-    return frames;
+// Load the optional {rom_name}.watch file listing the RAM addresses to print every second
+fn load_ram_watches(rom_name: &str) -> Vec<WatchEntry> {
+    let watch_file = PathBuf::from(format!("{}.watch", rom_name));
+    if !watch_file.exists() {
+        return Vec::new();
+    }
+    match ram_watch::parse_watch_file(&watch_file) {
+        Ok(entries) => {
+            println!("Loaded {} RAM watches from {}", entries.len(), watch_file.display());
+            entries
+        }
+        Err(err) => {
+            println!("{}", err);
+            Vec::new()
+        }
+    }
 }
 
-unsafe extern "C" fn libretro_log_print_callback(level: LogLevel, fmt: *const libc::c_char) {
-    print!("{:?}: ", level);
-    print_c_string(fmt);
+fn print_ram_watches(emulator: &Emulator, ram_watches: &[WatchEntry]) {
+    for entry in ram_watches {
+        match emulator.read_ram_watch(entry) {
+            Some(value) => println!(
+                "RAM Watch {} [0x{:X}]: {} (0x{:0width$X})",
+                entry.label,
+                entry.address,
+                value,
+                value,
+                width = entry.size * 2
+            ),
+            None => println!("RAM Watch {} [0x{:X}]: unmapped", entry.label, entry.address),
+        }
+    }
 }
 
-// NOTE: In the implementation of this function make sure you only send CString's to return_data, otherwise the core will not know when the String ends!
-unsafe extern "C" fn libretro_environment_callback(command: u32, return_data: *mut c_void) -> bool {
-    println!("libretro_environment_callback command:{}", command);
-    return match command {
-        libretro_sys::ENVIRONMENT_GET_CAN_DUPE => {
-            *(return_data as *mut bool) = true; // Set the return_data to the value true
-            println!("Set ENVIRONMENT_GET_CAN_DUPE to true");
-            false
-        }
-        libretro_sys::ENVIRONMENT_SET_PIXEL_FORMAT => {
-            let pixel_format = *(return_data as *const u32);
-            let pixel_format_as_enum = PixelFormat::from_uint(pixel_format).unwrap();
-            CURRENT_EMULATOR_STATE.pixel_format = pixel_format_as_enum;
-            match pixel_format_as_enum {
-                PixelFormat::ARGB1555 => {
-                    println!(
-                        "Core will send us pixel data in the RETRO_PIXEL_FORMAT_0RGB1555 format"
-                    );
-                    CURRENT_EMULATOR_STATE.bytes_per_pixel = 2;
-                }
-                PixelFormat::RGB565 => {
-                    println!(
-                        "Core will send us pixel data in the RETRO_PIXEL_FORMAT_RGB565 format"
-                    );
-                    CURRENT_EMULATOR_STATE.bytes_per_pixel = 2;
-                }
-                PixelFormat::ARGB8888 => {
-                    println!(
-                        "Core will send us pixel data in the RETRO_PIXEL_FORMAT_XRGB8888 format"
-                    );
-                    CURRENT_EMULATOR_STATE.bytes_per_pixel = 4;
-                }
-                _ => {
-                    panic!("Core is trying to use an Unknown Pixel Format")
-                }
-            }
-            true
-        }
-        libretro_sys::ENVIRONMENT_SET_MEMORY_MAPS => {
-            println!("TODO: Handle ENVIRONMENT_SET_MEMORY_MAPS");
-            true
-        }
-        libretro_sys::ENVIRONMENT_SET_CONTROLLER_INFO => {
-            println!("TODO: Handle ENVIRONMENT_SET_CONTROLLER_INFO");
-            true
-        }
-        libretro_sys::ENVIRONMENT_GET_VARIABLE_UPDATE => {
-            println!("INFO: Ignoring ENVIRONMENT_GET_VARIABLE_UPDATE");
-            // Return true when we have changed variables that the core needs to know about, but we don't change anything yet
-            false
-        }
-        // All the GETs not currently supported
-        libretro_sys::ENVIRONMENT_GET_CAMERA_INTERFACE => {
-            println!("TODO: Handle ENVIRONMENT_GET_CAMERA_INTERFACE");
-            true
-        }
-        libretro_sys::ENVIRONMENT_GET_CORE_ASSETS_DIRECTORY => {
-            println!("TODO: Handle ENVIRONMENT_GET_CORE_ASSETS_DIRECTORY");
-            true
-        }
-        libretro_sys::ENVIRONMENT_GET_CURRENT_SOFTWARE_FRAMEBUFFER => {
-            println!("TODO: Handle ENVIRONMENT_GET_CURRENT_SOFTWARE_FRAMEBUFFER");
-            true
-        }
-        libretro_sys::ENVIRONMENT_GET_HW_RENDER_INTERFACE => {
-            println!("TODO: Handle ENVIRONMENT_GET_HW_RENDER_INTERFACE");
-            true
-        }
-        libretro_sys::ENVIRONMENT_GET_INPUT_DEVICE_CAPABILITIES => {
-            println!("TODO: Handle ENVIRONMENT_GET_INPUT_DEVICE_CAPABILITIES");
-            true
-        }
-        libretro_sys::ENVIRONMENT_GET_LANGUAGE => {
-            println!("TODO: Handle ENVIRONMENT_GET_LANGUAGE");
-            true
-        }
-        libretro_sys::ENVIRONMENT_GET_LIBRETRO_PATH => {
-            println!("TODO: Handle ENVIRONMENT_GET_LIBRETRO_PATH");
-            true
-        }
-        libretro_sys::ENVIRONMENT_GET_LOCATION_INTERFACE => {
-            println!("TODO: Handle ENVIRONMENT_GET_LOCATION_INTERFACE");
-            true
-        }
-        libretro_sys::ENVIRONMENT_GET_LOG_INTERFACE => {
-            println!("TODO: Handle ENVIRONMENT_GET_LOG_INTERFACE");
-            (*(return_data as *mut LogCallback)).log = libretro_log_print_callback;
-            true
-        }
-        libretro_sys::ENVIRONMENT_GET_OVERSCAN => {
-            println!("TODO: Handle ENVIRONMENT_GET_OVERSCAN");
-            true
-        }
-        libretro_sys::ENVIRONMENT_GET_PERF_INTERFACE => {
-            println!("TODO: Handle ENVIRONMENT_GET_PERF_INTERFACE");
-            true
-        }
-        libretro_sys::ENVIRONMENT_GET_RUMBLE_INTERFACE => {
-            println!("TODO: Handle ENVIRONMENT_GET_RUMBLE_INTERFACE");
-            true
-        }
-        libretro_sys::ENVIRONMENT_GET_SAVE_DIRECTORY => {
-            println!("TODO: Handle ENVIRONMENT_GET_SAVE_DIRECTORY");
-            *(return_data as *mut *const libc::c_char) = CURRENT_EMULATOR_STATE.system_directory.as_ref().unwrap().as_ptr() as *const i8;  // TODO use CString otherwise this will segfault
-            true
-        }
-        libretro_sys::ENVIRONMENT_GET_SENSOR_INTERFACE => {
-            println!("TODO: Handle ENVIRONMENT_GET_SENSOR_INTERFACE");
-            true
-        }
-        libretro_sys::ENVIRONMENT_GET_SYSTEM_DIRECTORY => {
-            println!("TODO: Handle ENVIRONMENT_GET_SYSTEM_DIRECTORY");
-            println!("Rom name: {:?}", CURRENT_EMULATOR_STATE.rom_name);
-            println!("Pointer: {:?}", CURRENT_EMULATOR_STATE.rom_name.as_ptr());
-           
-            *(return_data as *mut *const libc::c_char) = CURRENT_EMULATOR_STATE.system_directory.as_ref().unwrap().as_ptr() as *const i8;
-            println!("return_data: {:?}", return_data);
-            true
-        }
-        libretro_sys::ENVIRONMENT_GET_USERNAME => {
-            println!("TODO: Handle ENVIRONMENT_GET_USERNAME");
-            true
-        }
-        libretro_sys::ENVIRONMENT_GET_VARIABLE => {
-            println!("TODO: Handle ENVIRONMENT_GET_VARIABLE command: {}", command); // 15
-            false
-        }
-        // Rest of the SET_
-        libretro_sys::ENVIRONMENT_SET_DISK_CONTROL_INTERFACE=> {
-            println!("TODO: Handle ENVIRONMENT_SET_DISK_CONTROL_INTERFACE");
-            true
-        }
-        libretro_sys::ENVIRONMENT_SET_FRAME_TIME_CALLBACK=> {
-            println!("TODO: Handle ENVIRONMENT_SET_FRAME_TIME_CALLBACK");
-            true
-        }
-        libretro_sys::ENVIRONMENT_SET_GEOMETRY=> {
-            println!("TODO: Handle ENVIRONMENT_SET_GEOMETRY");
-            true
-        }
-        libretro_sys::ENVIRONMENT_SET_HW_RENDER=> {
-            println!("TODO: Handle ENVIRONMENT_SET_HW_RENDER");
-            true
-        }
-        libretro_sys::ENVIRONMENT_SET_INPUT_DESCRIPTORS=> {
-            println!("TODO: Handle ENVIRONMENT_SET_INPUT_DESCRIPTORS");
-            true
-        }
-        libretro_sys::ENVIRONMENT_SET_KEYBOARD_CALLBACK=> {
-            println!("TODO: Handle ENVIRONMENT_SET_KEYBOARD_CALLBACK");
-            true
-        }
-        libretro_sys::ENVIRONMENT_SET_MESSAGE=> {
-            println!("TODO: Handle ENVIRONMENT_SET_MESSAGE");
-            true
-        }
-        libretro_sys::ENVIRONMENT_SET_PERFORMANCE_LEVEL=> {
-            println!("TODO: Handle ENVIRONMENT_SET_PERFORMANCE_LEVEL");
-            true
-        }
-        libretro_sys::ENVIRONMENT_SET_PROC_ADDRESS_CALLBACK=> {
-            println!("TODO: Handle ENVIRONMENT_SET_PROC_ADDRESS_CALLBACK");
-            true
-        }
-        libretro_sys::ENVIRONMENT_SET_ROTATION=> {
-            println!("TODO: Handle ENVIRONMENT_SET_ROTATION");
-            true
-        }
-        libretro_sys::ENVIRONMENT_SET_SUBSYSTEM_INFO=> {
-            println!("TODO: Handle ENVIRONMENT_SET_SUBSYSTEM_INFO");
-            true
-        }
-        libretro_sys::ENVIRONMENT_SET_SUPPORT_NO_GAME=> {
-            println!("TODO: Handle ENVIRONMENT_SET_SUPPORT_NO_GAME");
-            true
-        }
-        libretro_sys::ENVIRONMENT_SET_SYSTEM_AV_INFO=> {
-            println!("TODO: Handle ENVIRONMENT_SET_SYSTEM_AV_INFO");
-            true
-        }
-        libretro_sys::ENVIRONMENT_SET_VARIABLES=> {
-            println!("TODO: Handle ENVIRONMENT_SET_VARIABLES");
-            true
-        }
-        libretro_sys::ENVIRONMENT_EXPERIMENTAL => {
-            println!("TODO: Handle ENVIRONMENT_EXPERIMENTAL");
-            true
-        }
-        libretro_sys::ENVIRONMENT_PRIVATE => {
-            println!("TODO: Handle ENVIRONMENT_PRIVATE");
-            true
-        }
-        libretro_sys::ENVIRONMENT_SHUTDOWN => {
-            println!("TODO: Handle ENVIRONMENT_SHUTDOWN");
-            true
-        }
-        55 => {
-            println!("TODO: Handle RETRO_ENVIRONMENT_SET_CORE_OPTIONS_DISPLAY");
-            false
-        }
-        66 => {
-            // TODO: need to return retro_game_info_ext retro_game_info_ext
-            println!("TODO: Handle ENVIRONMENT_GET_GAME_INFO_EXT");
-            let game_info = CURRENT_EMULATOR_STATE.game_info.clone().unwrap_unchecked();
-    
-            let data = (return_data as *mut GameInfoExt);
-            (*(return_data as *mut GameInfoExt)).full_path = CURRENT_EMULATOR_STATE.rom_name.as_ptr() as *const i8;
-            (*(return_data as *mut GameInfoExt)).archive_path = ptr::null();
-            (*(return_data as *mut GameInfoExt)).archive_file = ptr::null();
-            (*(return_data as *mut GameInfoExt)).ext = CURRENT_EMULATOR_STATE.rom_name.as_ptr() as *const i8;
-            (*(return_data as *mut GameInfoExt)).meta = ptr::null();
-            (*(return_data as *mut GameInfoExt)).dir = CURRENT_EMULATOR_STATE.rom_name.as_ptr() as *const i8; // TODO: Convert to Cstring
-            (*(return_data as *mut GameInfoExt)).name = CURRENT_EMULATOR_STATE.rom_name.as_ptr() as *const i8; // TODO: Convert to Cstring
-            (*(return_data as *mut GameInfoExt)).file_in_archive = false;
-            (*(return_data as *mut GameInfoExt)).persistent_data = true;
-            (*(return_data as *mut GameInfoExt)).size = CURRENT_EMULATOR_STATE.game_info.as_ref().unwrap().size;
-            (*(return_data as *mut GameInfoExt)).data = CURRENT_EMULATOR_STATE.game_info.as_ref().unwrap().data;
-            println!("Data size {}",  (*(return_data as *mut GameInfoExt)).size);
-
+// minifb has no text input so the swap core hotkey asks for the paths on the terminal
+fn prompt_for_path(prompt: &str, default: &str) -> String {
+    print!("{} [{}]: ", prompt, default);
+    io::stdout().flush().ok();
+    let mut line = String::new();
+    io::stdin().read_line(&mut line).ok();
+    match line.trim() {
+        "" => default.to_string(),
+        path => path.to_string(),
+    }
+}
 
-            true
+fn swap_core(emulator: Emulator, core_path: &str, rom_path: &str, game_config: HashMap<String, String>) -> Emulator {
+    let previous_core = emulator.core_name().to_string();
+    let previous_rom = emulator.rom_name().to_string();
+    let config = emulator.config().clone();
+    match emulator.swap_core(core_path, rom_path, game_config) {
+        Ok(emulator) => {
+            println!("Swapped to core: {} with ROM: {}", core_path, rom_path);
+            remember_last_game(core_path, rom_path);
+            emulator
         }
-        _ => {
-            println!(
-                "libretro_environment_callback Called with command: {}",
-                command
-            );
-            false
+        Err(err) => {
+            println!("Failed to swap core: {} reloading {}", err, previous_core);
+            Emulator::new(&previous_core, &previous_rom, config).unwrap_or_else(|e| panic!("{}", e))
         }
-    };
+    }
 }
 
-unsafe fn load_core(library_path: &String) -> (CoreAPI) {
-    unsafe {
-        let dylib = Box::leak(Box::new(
-            Library::new(library_path).expect("Failed to load Core"),
-        ));
-
-        let core_api = CoreAPI {
-            retro_set_environment: *(dylib.get(b"retro_set_environment").unwrap()),
-            retro_set_video_refresh: *(dylib.get(b"retro_set_video_refresh").unwrap()),
-            retro_set_audio_sample: *(dylib.get(b"retro_set_audio_sample").unwrap()),
-            retro_set_audio_sample_batch: *(dylib.get(b"retro_set_audio_sample_batch").unwrap()),
-            retro_set_input_poll: *(dylib.get(b"retro_set_input_poll").unwrap()),
-            retro_set_input_state: *(dylib.get(b"retro_set_input_state").unwrap()),
-
-            retro_init: *(dylib.get(b"retro_init").unwrap()),
-            retro_deinit: *(dylib.get(b"retro_deinit").unwrap()),
-
-            retro_api_version: *(dylib.get(b"retro_api_version").unwrap()),
-
-            retro_get_system_info: *(dylib.get(b"retro_get_system_info").unwrap()),
-            retro_get_system_av_info: *(dylib.get(b"retro_get_system_av_info").unwrap()),
-            retro_set_controller_port_device: *(dylib
-                .get(b"retro_set_controller_port_device")
-                .unwrap()),
-
-            retro_reset: *(dylib.get(b"retro_reset").unwrap()),
-            retro_run: *(dylib.get(b"retro_run").unwrap()),
-
-            retro_serialize_size: *(dylib.get(b"retro_serialize_size").unwrap()),
-            retro_serialize: *(dylib.get(b"retro_serialize").unwrap()),
-            retro_unserialize: *(dylib.get(b"retro_unserialize").unwrap()),
-
-            retro_cheat_reset: *(dylib.get(b"retro_cheat_reset").unwrap()),
-            retro_cheat_set: *(dylib.get(b"retro_cheat_set").unwrap()),
-
-            retro_load_game: *(dylib.get(b"retro_load_game").unwrap()),
-            retro_load_game_special: *(dylib.get(b"retro_load_game_special").unwrap()),
-            retro_unload_game: *(dylib.get(b"retro_unload_game").unwrap()),
-
-            retro_get_region: *(dylib.get(b"retro_get_region").unwrap()),
-            retro_get_memory_data: *(dylib.get(b"retro_get_memory_data").unwrap()),
-            retro_get_memory_size: *(dylib.get(b"retro_get_memory_size").unwrap()),
-        };
-
-        let api_version = (core_api.retro_api_version)();
-        println!("API Version: {}", api_version);
-        if (api_version != EXPECTED_LIB_RETRO_VERSION) {
-            panic!("The Core has been compiled with a LibRetro API that is unexpected, we expected version to be: {} but it was: {}", EXPECTED_LIB_RETRO_VERSION, api_version)
-        }
-        (core_api.retro_set_environment)(libretro_environment_callback);
-        (core_api.retro_init)();
-        (core_api.retro_set_video_refresh)(libretro_set_video_refresh_callback);
-        (core_api.retro_set_input_poll)(libretro_set_input_poll_callback);
-        (core_api.retro_set_input_state)(libretro_set_input_state_callback);
-        (core_api.retro_set_audio_sample)(libretro_set_audio_sample_callback);
-        (core_api.retro_set_audio_sample_batch)(libretro_set_audio_sample_batch_callback);
-        return core_api;
+// A ROM whose extension the core doesn't list is probably meant for another core, with --strict it isn't loaded
+fn check_rom_extension(core_info: &CoreInfo, rom_path: &str, strict: bool) {
+    if let Err(err) = core_info.check_rom_extension(rom_path) {
+        if strict {
+            println!("{}, not loading it as --strict was given", err);
+            std::process::exit(1);
+        }
+        println!("Warning: {}, trying it anyway", err);
     }
 }
 
-fn setup_config() -> Result<HashMap<String, String>, String> {
-    let retro_arch_config_path = get_retroarch_config_path();
-    let our_config = parse_retroarch_config(Path::new("./rustroarch.cfg"));
-    let retro_arch_config =
-        parse_retroarch_config(&retro_arch_config_path.join("config/retroarch.cfg"));
-    let mut merged_config: HashMap<String, String> = HashMap::from([
-        ("input_player1_a", "a"),
-        ("input_player1_b", "s"),
-        ("input_player1_x", "z"),
-        ("input_player1_y", "x"),
-        ("input_player1_l", "q"),
-        ("input_player1_r", "w"),
-        ("input_player1_down", "down"),
-        ("input_player1_up", "up"),
-        ("input_player1_left", "left"),
-        ("input_player1_right", "right"),
-        ("input_player1_select", "space"),
-        ("input_player1_start", "enter"),
-        ("input_reset", "h"),
-        ("input_save_state", "f2"),
-        ("input_load_state", "f4"),
-        ("input_screenshot", "f8"),
-        ("savestate_directory", "./states"),
-        ("input_state_slot_decrease", "f6"),
-        ("input_state_slot_increase", "f7"),
-        // ("audio_enable", "true"),
-    ])
-    .iter()
-    .map(|(k, v)| (k.to_string(), v.to_string()))
-    .collect();
-    match retro_arch_config {
-        Ok(config) => merged_config.extend(config),
-        _ => println!("We don't have RetroArch config"),
-    }
-    match our_config {
-        Ok(config) => merged_config.extend(config),
-        _ => println!("We don't have RustroArch config",),
+// Saved with absolute paths so --continue works from any directory
+fn remember_last_game(core_path: &str, rom_path: &str) {
+    let absolute = |path: &str| match is_url(path) {
+        true => path.to_string(),
+        false => fs::canonicalize(expand_path(path))
+            .map_or(path.to_string(), |path| path.to_string_lossy().into_owned()),
+    };
+    let last_game = LastGame {
+        core_path: absolute(core_path),
+        rom_path: absolute(rom_path),
+    };
+    if let Err(err) = save_last_game(Path::new(LAST_GAME_FILE), &last_game) {
+        println!("Failed to remember the last game for --continue: {}", err);
     }
-    // println!("retro_arch_config_path: {} merged_config: {:?}", retro_arch_config_path.join("config/retroarch.cfg").display(), merged_config);
-    Ok(merged_config.clone())
 }
 
-unsafe fn parse_command_line_arguments() {
-    let matches = App::new("RustroArch")
-        .arg(
-            Arg::with_name("rom_name")
-                .help("Sets the path to the ROM file to load")
-                .required(true)
-                .index(1),
-        )
-        .arg(
-            Arg::with_name("library_name")
-                .help("Sets the path to the libRetro core to use")
-                .short("L")
-                .takes_value(true),
-        )
-        .get_matches();
-
-    let rom_name = matches.value_of("rom_name").unwrap();
-    let library_name = matches
-        .value_of("library_name")
-        .unwrap_or("default_library");
-    println!("ROM name: {}", rom_name);
-    println!("Core Library name: {}", library_name);
-    CURRENT_EMULATOR_STATE.rom_name = rom_name.to_string();
-    CURRENT_EMULATOR_STATE.core_name = library_name.to_string();
+// Runs a command from the command socket, QUIT is left to the main loop as it needs to end the loop
+fn run_command(
+    emulator: &mut Emulator,
+    command: &Command,
+    max_save_slots: u16,
+    is_paused: &mut bool,
+) -> Result<String, String> {
+    match command {
+        Command::Save(slot) | Command::Load(slot) if *slot as u16 >= max_save_slots => Err(format!(
+            "Save slot {} is out of range, there are {} slots",
+            slot, max_save_slots
+        )),
+        Command::Save(slot) => {
+            emulator.save_state(*slot);
+            Ok(String::new())
+        }
+        Command::Load(slot) => emulator.load_state(*slot).map(|loaded_slot| {
+            if loaded_slot == *slot {
+                String::new()
+            } else {
+                format!("slot {} was empty, loaded slot {}", slot, loaded_slot)
+            }
+        }),
+        Command::Screenshot => emulator
+            .screenshot()
+            .map(|path| path.display().to_string()),
+        Command::Pause => {
+            *is_paused = !*is_paused;
+            Ok(if *is_paused { "paused" } else { "resumed" }.to_string())
+        }
+        Command::Reset => {
+            emulator.reset();
+            Ok(String::new())
+        }
+        Command::Quit => Ok(String::new()),
+    }
 }
 
-unsafe fn load_rom_file(core_api: &CoreAPI, rom_name: &String) -> bool {
-    println!("Loading ROM file: {:?}", rom_name);
-    let rom_name_cptr = CString::new(rom_name.clone())
-        .expect("Failed to create CString")
-        .as_ptr();
-    let contents = fs::read(rom_name).expect("Failed to read file");
-    let data: *const c_void = contents.as_ptr() as *const c_void;
-    let game_info = GameInfo {
-        path: rom_name_cptr,
-        data,
-        size: contents.len(),
-        meta: ptr::null(),
-    };
-    CURRENT_EMULATOR_STATE.game_info = Some(game_info.clone());
-
-    println!("INFO: Calling retro_load_game in Core");
-    let was_load_successful = (core_api.retro_load_game)(&game_info);
-    if (!was_load_successful) {
-        panic!("Rom Load was not successful");
+// The config with the game's .opt core options over it and the --core-option ones over those
+fn game_config(
+    config: &HashMap<String, String>,
+    rom_path: &str,
+    core_options: &[(String, String)],
+) -> HashMap<String, String> {
+    let mut game_config = config.clone();
+    if !is_url(rom_path) {
+        let path = game_options_path(rom_path);
+        match load_game_options(&path) {
+            Ok(game_options) if !game_options.is_empty() => {
+                println!("Loaded {} core options from {}", game_options.len(), path.display());
+                game_config.extend(game_options);
+            }
+            Ok(_) => {}
+            Err(err) => println!("{}, using the config's core options", err),
+        }
     }
-    println!("ROM was successfully loaded");
-    return was_load_successful;
+    game_config.extend(core_options.iter().cloned());
+    game_config
 }
 
-unsafe fn send_audio_to_thread(sender: &Sender<&Vec<i16>>) {
-    // Send the audio samples to the audio thread using the channel
-    match &CURRENT_EMULATOR_STATE.audio_data {
-        Some(data) => {
-            sender.send(data).unwrap();
-        },
-        None => {},
-    };
-    
+fn print_core_list(cores_directory: &str) {
+    let cores = find_cores(Path::new(cores_directory)).unwrap_or_else(|e| panic!("{}", e));
+    println!("{:<40} {:<30} Extensions", "File", "Library");
+    for core_path in cores {
+        let file_name = core_path.file_name().unwrap().to_string_lossy();
+        // A core that fails to load is reported and skipped rather than stopping the scan
+        match read_core_info(&core_path) {
+            Ok(core_info) => println!(
+                "{:<40} {:<30} {}",
+                file_name,
+                format!("{} {}", core_info.library_name, core_info.library_version),
+                core_info.valid_extensions.join("|")
+            ),
+            Err(err) => println!("{:<40} Skipped: {}", file_name, err),
+        }
+    }
 }
 
-unsafe fn play_audio( sink: &Sink, audio_samples: &Vec<i16>, sample_rate: u32) {
-    if !audio_enable {
+fn print_rom_scan(core_path: &str, directory: &str, recursive: bool, json_output: Option<fs::File>) {
+    let core_info = read_core_info(Path::new(core_path)).unwrap_or_else(|e| panic!("{}", e));
+    let roms = scan_roms(Path::new(directory), &core_info, recursive).unwrap_or_else(|e| panic!("{}", e));
+    if let Some(mut output) = json_output {
+        if let Err(err) = writeln!(output, "{}", roms_to_json(&roms)) {
+            println!("Failed to write the ROM list: {}", err);
+            std::process::exit(1);
+        }
         return;
     }
-    if sink.empty() {
-        let audio_slice = std::slice::from_raw_parts(audio_samples.as_ptr() as *const i16, audio_samples.len());
-        let source = SamplesBuffer::new(2, sample_rate, audio_slice);
-        sink.append(source);
-        sink.play();
-        sink.sleep_until_end();
+    for rom in &roms {
+        println!("{}", rom.display());
     }
 }
 
-fn get_save_state_path(
-    save_directory: &String,
-    game_file_name: &str,
-    save_state_index: u8,
-) -> Option<PathBuf> {
-    // Create a subdirectory named "saves" in the current working directory
-    let saves_dir = PathBuf::from(save_directory);
-    if !saves_dir.exists() {
-        match std::fs::create_dir(&saves_dir) {
-            Ok(_) => {}
-            Err(err) => panic!(
-                "Failed to create save directory: {:?} Error: {}",
-                &saves_dir, err
-            ),
-        }
+fn print_audio_device_list() {
+    let names = output_device_names().unwrap_or_else(|e| panic!("{}", e));
+    if names.is_empty() {
+        println!("No audio output devices found");
     }
+    for name in names {
+        println!("{}", name);
+    }
+}
 
-    // Generate the save state filename
-    let game_name = Path::new(game_file_name)
-        .file_stem()
-        .unwrap_or_default()
-        .to_string_lossy()
-        .replace(" ", "_");
-    let save_state_file_name = format!("{}_{}.state", game_name, save_state_index);
-
-    // Combine the saves directory and the save state filename to create the full path
-    let save_state_path = saves_dir.join(save_state_file_name);
-
-    Some(save_state_path)
+fn print_subsystem_list(core_path: &str) {
+    let subsystems = read_core_subsystems(Path::new(core_path)).unwrap_or_else(|e| panic!("{}", e));
+    if subsystems.is_empty() {
+        println!("{} has no subsystems", core_path);
+    }
+    for subsystem in subsystems {
+        println!("Subsystem {} (id {}): {}", subsystem.ident, subsystem.id, subsystem.description);
+        for (index, rom) in subsystem.roms.iter().enumerate() {
+            println!(
+                "  ROM {}: {}{} extensions: {}",
+                index + 1,
+                rom.description,
+                if rom.required { " (required)" } else { "" },
+                rom.valid_extensions.join("|")
+            );
+        }
+    }
 }
 
-unsafe fn save_state(core_api: &CoreAPI, save_directory: &String) {
-    let save_state_buffer_size = (core_api.retro_serialize_size)();
-    let mut state_buffer: Vec<u8> = vec![0; save_state_buffer_size];
-    // Call retro_serialize to create the save state
-    (core_api.retro_serialize)(
-        state_buffer.as_mut_ptr() as *mut c_void,
-        save_state_buffer_size,
-    );
-    let file_path = get_save_state_path(
-        save_directory,
-        &CURRENT_EMULATOR_STATE.rom_name,
-        CURRENT_EMULATOR_STATE.current_save_slot,
-    )
-    .unwrap();
-    std::fs::write(&file_path, &state_buffer).unwrap();
-    println!(
-        "Save state saved to: {} with size: {}",
-        &file_path.display(),
-        save_state_buffer_size
-    );
+fn print_controller_list(core_path: &str) {
+    let ports = read_core_controller_types(Path::new(core_path)).unwrap_or_else(|e| panic!("{}", e));
+    if ports.is_empty() {
+        println!("{} doesn't list its controllers before a game is loaded", core_path);
+    }
+    for (port, types) in ports.iter().enumerate() {
+        println!("Port {}:", port + 1);
+        for controller in types {
+            println!("  {}", describe_device(controller.id, types));
+        }
+    }
 }
 
-unsafe fn load_state(core_api: &CoreAPI, save_directory: &String) {
-    let file_path = get_save_state_path(
-        save_directory,
-        &CURRENT_EMULATOR_STATE.rom_name,
-        CURRENT_EMULATOR_STATE.current_save_slot,
-    )
-    .unwrap();
-    let mut state_buffer = Vec::new();
-    match File::open(&file_path) {
-        Ok(mut file) => {
-            // Read the save state file into a buffer
-            match file.read_to_end(&mut state_buffer) {
-                Ok(_) => {
-                    // Call retro_unserialize to apply the save state
-                    let result = (core_api.retro_unserialize)(
-                        state_buffer.as_mut_ptr() as *mut c_void,
-                        state_buffer.len() as usize,
-                    );
-                    if result {
-                        println!("Save state loaded from: {}", &file_path.display());
-                    } else {
-                        println!("Failed to load save state: error code {}", result);
-                    }
-                }
-                Err(err) => println!("Error reading save state file: {}", err),
-            }
+// Frames without another ENVIRONMENT_SET_GEOMETRY before a geometry change is reported
+const GEOMETRY_SETTLE_FRAMES: u32 = 10;
+// How long messages like the filter being switched stay on screen
+const OSD_MESSAGE_DURATION: Duration = Duration::from_secs(2);
+// How soon after the first press of Escape the second has to come to quit with confirm_exit
+const EXIT_CONFIRM_WINDOW: Duration = Duration::from_secs(1);
+// Seconds running slower than the core's fps before a core's performance level is blamed for it
+const SLOW_SECONDS_BEFORE_WARNING: u32 = 3;
+// How many frames savestate_load_fail_alert = flash inverts the screen for
+const LOAD_FAIL_FLASH_FRAMES: u32 = 6;
+
+// Makes a failed save state load visible during play rather than only in the terminal
+fn report_load_failure(
+    err: &str,
+    show_osd: bool,
+    alert: LoadFailAlert,
+    osd_message: &mut OsdMessage,
+    flash_frames: &mut u32,
+) {
+    println!("{}", err);
+    if show_osd {
+        osd_message.show(err, OSD_MESSAGE_DURATION);
+    }
+    match alert {
+        LoadFailAlert::Beep => {
+            print!("\x07");
+            io::stdout().flush().ok();
         }
-        Err(_) => println!("Save state file not found"),
+        LoadFailAlert::Flash => *flash_frames = LOAD_FAIL_FLASH_FRAMES,
+        LoadFailAlert::None => {}
     }
 }
 
-fn setup_key_device_map(config: &HashMap<String, String>) -> HashMap<&String, usize> {
-    return HashMap::from([
-        (
-            &config["input_player1_a"],
-            libretro_sys::DEVICE_ID_JOYPAD_A as usize,
-        ),
-        (
-            &config["input_player1_b"],
-            libretro_sys::DEVICE_ID_JOYPAD_B as usize,
-        ),
-        (
-            &config["input_player1_x"],
-            libretro_sys::DEVICE_ID_JOYPAD_X as usize,
-        ),
-        (
-            &config["input_player1_y"],
-            libretro_sys::DEVICE_ID_JOYPAD_Y as usize,
-        ),
-        (
-            &config["input_player1_l"],
-            libretro_sys::DEVICE_ID_JOYPAD_L as usize,
-        ),
-        (
-            &config["input_player1_r"],
-            libretro_sys::DEVICE_ID_JOYPAD_R as usize,
-        ),
-        (
-            &config["input_player1_down"],
-            libretro_sys::DEVICE_ID_JOYPAD_DOWN as usize,
-        ),
-        (
-            &config["input_player1_up"],
-            libretro_sys::DEVICE_ID_JOYPAD_UP as usize,
-        ),
-        (
-            &config["input_player1_right"],
-            libretro_sys::DEVICE_ID_JOYPAD_RIGHT as usize,
-        ),
-        (
-            &config["input_player1_left"],
-            libretro_sys::DEVICE_ID_JOYPAD_LEFT as usize,
-        ),
-        (
-            &config["input_player1_start"],
-            libretro_sys::DEVICE_ID_JOYPAD_START as usize,
-        ),
-        (
-            &config["input_player1_select"],
-            libretro_sys::DEVICE_ID_JOYPAD_SELECT as usize,
-        ),
-    ]);
+fn frame_duration(emulator: &Emulator) -> Duration {
+    Duration::from_micros(1000000 / emulator.av_info().timing.fps as u64)
 }
-fn setup_joypad_device_map() -> HashMap<Button, usize> {
-    return HashMap::from([
-        (
-            Button::South,
-            libretro_sys::DEVICE_ID_JOYPAD_A as usize,
-        ),
-        (
-            Button::East,
-            libretro_sys::DEVICE_ID_JOYPAD_B as usize,
-        ),
-        (
-            Button::West,
-            libretro_sys::DEVICE_ID_JOYPAD_X as usize,
-        ),
-        (
-            Button::North,
-            libretro_sys::DEVICE_ID_JOYPAD_Y as usize,
-        ),
-        (
-            Button::LeftTrigger,
-            libretro_sys::DEVICE_ID_JOYPAD_L as usize,
-        ),
-        (
-            Button::LeftTrigger2,
-            libretro_sys::DEVICE_ID_JOYPAD_L2 as usize,
-        ),
-        (
-            Button::RightTrigger,
-            libretro_sys::DEVICE_ID_JOYPAD_R as usize,
-        ),
-        (
-            Button::RightTrigger2,
-            libretro_sys::DEVICE_ID_JOYPAD_R2 as usize,
-        ),
-        (
-            Button::DPadDown,            
-            libretro_sys::DEVICE_ID_JOYPAD_DOWN as usize,
-        ),
-        (
-            Button::DPadUp,
-            libretro_sys::DEVICE_ID_JOYPAD_UP as usize,
-        ),
-        (
-            Button::DPadRight,
-            libretro_sys::DEVICE_ID_JOYPAD_RIGHT as usize,
-        ),
-        (
-            Button::DPadLeft,
-            libretro_sys::DEVICE_ID_JOYPAD_LEFT as usize,
-        ),
-        (
-            Button::Start,
-            libretro_sys::DEVICE_ID_JOYPAD_START as usize,
-        ),
-        (
-            Button::Select,
-            libretro_sys::DEVICE_ID_JOYPAD_SELECT as usize,
-        ),
-    ]);
+
+// The core's base size times video_scale (640x480 without one), turned to match the rotation and kept
+// within window_min_size and window_max_size
+fn window_size(emulator: &Emulator, video_scale: Option<u32>, rotation: u32, limits: &WindowSizeLimits) -> (usize, usize) {
+    let geometry = &emulator.av_info().geometry;
+    let (width, height) = match video_scale {
+        Some(scale) => ((geometry.base_width * scale) as usize, (geometry.base_height * scale) as usize),
+        None => (640, 480),
+    };
+    let (width, height) = rotated_size(width, height, rotation);
+    limits.clamp(width, height)
+}
+
+// With sync_to_audio the audio device paces the loop instead of the window's update rate limit
+fn window_update_rate(emulator: &Emulator, sync_to_audio: bool) -> Option<Duration> {
+    (!sync_to_audio).then(|| frame_duration(emulator))
 }
 
 fn main() {
-    unsafe { parse_command_line_arguments() };
-    let config = setup_config().unwrap();
+    install_panic_hook();
+    let matches = parse_command_line_arguments();
+    // Taken before anything is logged so stdout only ever has the status lines on it
+    let mut status_output = matches.is_present("status_json").then(|| {
+        take_stdout().unwrap_or_else(|e| {
+            println!("{}", e);
+            std::process::exit(1);
+        })
+    });
+    // The same for --scan-roms --json so launchers can parse the whole of stdout
+    let json_output = matches.is_present("json").then(|| {
+        take_stdout().unwrap_or_else(|e| {
+            println!("{}", e);
+            std::process::exit(1);
+        })
+    });
+    print_arguments(&matches);
+    let last_game = matches.is_present("continue").then(|| match load_last_game(Path::new(LAST_GAME_FILE)) {
+        Ok(Some(last_game)) => {
+            println!("Continuing {} with core {}", last_game.rom_path, last_game.core_path);
+            last_game
+        }
+        Ok(None) => {
+            println!("No game has been played yet, once one has been launched with -L <core> <rom> --continue will launch it again");
+            std::process::exit(1);
+        }
+        Err(err) => {
+            println!("Can't continue: {}", err);
+            std::process::exit(1);
+        }
+    });
+    // The menu comes before the core's profile is looked up as it can choose the core too
+    let menu_choice = matches.is_present("menu").then(|| {
+        let config = setup_config_for_core(None).unwrap();
+        run_rom_menu(&config, matches.value_of("library_name")).unwrap_or_else(|| {
+            println!("No ROM was chosen");
+            std::process::exit(0);
+        })
+    });
+    let core_path = matches
+        .value_of("library_name")
+        .or(menu_choice.as_ref().map(|(core_path, _)| core_path.as_str()))
+        .or(last_game.as_ref().map(|last_game| last_game.core_path.as_str()))
+        .unwrap_or("default_library");
+    // The core's profile is merged into the config so its library name is needed first. If it can't be
+    // read the core won't load either, which reports the error.
+    let core_info = read_core_info(Path::new(core_path)).ok();
+    let core_library_name = core_info.as_ref().map(|core_info| core_info.library_name.clone());
+    let mut config = setup_config_for_core(core_library_name.as_deref()).unwrap();
+    // Core options live in the config under their key so the command line ones just replace them
+    let core_options: Vec<(String, String)> = matches
+        .values_of("core_option")
+        .into_iter()
+        .flatten()
+        .filter_map(|argument| {
+            parse_core_option_argument(argument)
+                .map_err(|e| println!("{}", e))
+                .ok()
+        })
+        .collect();
+    config.extend(core_options.iter().cloned());
+    if matches.is_present("no_audio") {
+        config.insert("audio_enabled".to_string(), "false".to_string());
+    }
+    if let Some(meta) = matches.value_of("meta") {
+        config.insert("game_info_meta".to_string(), meta.to_string());
+    }
 
-    let key_device_map = setup_key_device_map(&config);
-    let joypad_device_map = setup_joypad_device_map();
+    if matches.is_present("list_controllers") {
+        print_controller_list(matches.value_of("library_name").unwrap_or("default_library"));
+        return;
+    }
+    if matches.is_present("list_subsystems") {
+        print_subsystem_list(matches.value_of("library_name").unwrap_or("default_library"));
+        return;
+    }
+    if let Some(directory) = matches.value_of("scan_roms") {
+        let core_path = matches.value_of("library_name").unwrap();
+        print_rom_scan(core_path, directory, matches.is_present("recursive"), json_output);
+        return;
+    }
+    if matches.is_present("list_audio_devices") {
+        print_audio_device_list();
+        return;
+    }
+    if matches.is_present("list_cores") {
+        print_core_list(matches.value_of("list_cores").unwrap_or(&config["cores_directory"]));
+        return;
+    }
+    if matches.is_present("test_input") {
+        run_input_test(&config);
+        return;
+    }
 
-    println!("Setting up minifb window");
-    let mut window =
-        Window::new("RustroArch", 640, 480, WindowOptions::default()).unwrap_or_else(|e| {
-            panic!("{}", e);
-        });
+    let key_device_map = setup_key_device_map(&config);
+    // Used while hotkeys are enabled, without the keys bound to both if hotkeys win
+    let hotkey_priority = config["hotkey_priority"] == "true";
+    let mut hotkey_key_device_map = key_device_map.clone();
+    for collision in find_key_collisions(&config) {
+        println!(
+            "Key {} is bound to both {} and {}, {} wins (hotkey_priority = {})",
+            collision.key,
+            collision.game_input,
+            collision.hotkey,
+            if hotkey_priority { &collision.hotkey } else { &collision.game_input },
+            hotkey_priority
+        );
+        if hotkey_priority {
+            hotkey_key_device_map.remove(&collision.key);
+        }
+    }
+    let max_save_slots = get_max_save_slots(&config);
+    let save_slot_wrap = config["savestate_slot_wrap"] == "true";
+    let savestate_auto_index = config["savestate_auto_index"] == "true";
+    let quick_swap_slot = get_quick_swap_slot(&config, max_save_slots);
+    let mut current_save_slot: u8 = 0;
+    let joypad_device_map = setup_joypad_device_map(&config);
+    let joypad_axis_bindings = setup_joypad_axis_bindings(&config);
+    let analog_deadzone = get_analog_deadzone(&config);
+    let mut face_button_swap = FaceButtonSwap::from_config(&config);
+    let mut video_filter = match matches.value_of("scale_filter") {
+        Some(name) => VideoFilter::from_name(name).unwrap(),
+        None => get_video_filter(&config),
+    };
+    let mut aspect_mode = get_aspect_mode(&config);
+    let overscan_crop = get_overscan_crop(&config);
+    let color_correction = get_color_correction(&config);
+    let video_rotation = get_video_rotation(&config);
+    let video_scale_to_window = config["video_scale_to_window"] == "true";
+    let video_scale_integer = config["video_scale_integer"] == "true";
+    let mut video_scale = get_video_scale(&config);
+    let video_scale_max = get_video_scale_max(&config);
+    let window_size_limits = get_window_size_limits(&config);
 
     let mut fps_timer = Instant::now();
     let mut fps_counter = 0;
-    let core_api;
+    let mut fps_text = String::new();
+    let mut osd_message = OsdMessage::default();
+    let mut stats_timer = Instant::now(); // RAM watches and audio underruns are reported every second
+    let mut stats_frames = 0; // --status-json's fps, over the same second whatever fps_sample_interval is
+    let mut slow_seconds = 0; // in a row below the core's fps, for the performance level warning
+    let fps_display = get_fps_display(&config);
+    // Shown with the FPS, or on its own in the overlay when video_fps_display is off
+    let show_frame_count = config["video_show_frame_count"] == "true";
+    let fps_sample_interval = get_fps_sample_interval(&config);
+
+    let resolve_rom = |rom: &str| {
+        resolve_rom_path(rom, &config["roms_directory"])
+            .unwrap_or_else(|e| panic!("{}", e))
+            .to_string_lossy()
+            .into_owned()
+    };
+    let mut emulator = match matches.value_of("subsystem") {
+        Some(subsystem) => {
+            let rom_paths: Vec<String> = matches.values_of("rom").unwrap().map(resolve_rom).collect();
+            Emulator::new_with_subsystem(core_path, subsystem, &rom_paths, config.clone())
+        }
+        None => {
+            let rom_name = matches
+                .value_of("rom_name")
+                .or(menu_choice.as_ref().map(|(_, rom_path)| rom_path.as_str()))
+                .or(last_game.as_ref().map(|last_game| last_game.rom_path.as_str()))
+                .unwrap();
+            let rom_path = resolve_rom(rom_name);
+            if let Some(core_info) = &core_info {
+                check_rom_extension(core_info, &rom_path, matches.is_present("strict"));
+            }
+            Emulator::new(core_path, &rom_path, game_config(&config, &rom_path, &core_options))
+        }
+    }
+    .unwrap_or_else(|e| panic!("{}", e));
+    if matches.value_of("subsystem").is_none() {
+        remember_last_game(core_path, emulator.rom_name());
+    }
+    for (key, _) in &core_options {
+        if !emulator.core_variables().iter().any(|variable| &variable.key == key) {
+            println!("Unknown core option: {}, the core doesn't have it", key);
+        }
+    }
+    // Options set with --core-option stick for the game, they're read back from its .opt next time
+    if !core_options.is_empty() && matches.value_of("subsystem").is_none() && !is_url(emulator.rom_name()) {
+        let path = game_options_path(emulator.rom_name());
+        match save_game_options(&path, emulator.core_variables()) {
+            Ok(()) => println!("Core options for this game saved to: {}", path.display()),
+            Err(err) => println!("{}", err),
+        }
+    }
 
-    println!("Setting up Audio Thread");
-    // Create a channel for passing audio samples from the main thread to the audio thread
-    let (sender, receiver) = channel();
-    
-    // Spawn a new thread to play back audio
-    if (audio_enable) {
-        let audio_thread = thread::spawn(move || {
-            println!("Audio Thread Started");
-            let sample_rate = unsafe { match &CURRENT_EMULATOR_STATE.av_info {
-                Some(av_info) => av_info.timing.sample_rate,
-                None => 0.0
+    // Without a window nothing is shown and there's no keyboard or mouse input, gamepads, replays and the
+    // command socket still work. It's made once the game has loaded so it can be turned to match a core that
+    // rotates its screen.
+    let mut window = if matches.is_present("headless")
+        || matches.is_present("hash_frames")
+        || matches.is_present("verify_savestate")
+    {
+        println!("Running headless");
+        None
+    } else {
+        println!("Setting up minifb window");
+        let (window_width, window_height) =
+            window_size(&emulator, video_scale, video_rotation.unwrap_or(emulator.rotation()), &window_size_limits);
+        match create_window(window_width, window_height) {
+            Ok(window) => Some(window),
+            Err(err) => {
+                println!("{}", err);
+                println!("There may be no display available, use --headless to run without a window");
+                std::process::exit(1);
             }
-            };
-            let (_stream, stream_handle) = OutputStream::try_default().unwrap();
-            let sink = Sink::try_new(&stream_handle).unwrap();
-            loop {
-                // Receive the next set of audio samples from the channel
-                let audio_samples = receiver.recv().unwrap();
-                unsafe { play_audio(&sink, audio_samples, sample_rate as u32); }
+        }
+    };
+    // Loaded before anything else looks at the core's state so a replay recording starts from it
+    let boot_slot = match matches.value_of("load_state_on_boot") {
+        Some(slot) => match slot.parse::<u8>() {
+            Ok(slot) => Some(slot),
+            Err(_) => {
+                println!("Invalid --load-state-on-boot slot: {} booting normally", slot);
+                None
             }
-        });
+        },
+        None if matches.is_present("play_input") => None, // the replay brings its own state
+        None => get_autoload_slot(&config),
+    };
+    if let Some(slot) = boot_slot {
+        if slot as u16 >= max_save_slots {
+            println!(
+                "Can't load slot {} on boot, there are only {} slots, booting normally",
+                slot, max_save_slots
+            );
+        } else if emulator.serialize().is_empty() {
+            println!("Can't load slot {} on boot, this core doesn't support save states", slot);
+        } else if !emulator.has_save_state(slot) {
+            println!("There's no save state in slot {} to load on boot, booting normally", slot);
+        } else {
+            match emulator.load_state(slot) {
+                Ok(loaded_slot) => current_save_slot = loaded_slot,
+                Err(err) => println!("{}, booting normally", err),
+            }
+        }
+    }
+    if savestate_auto_index {
+        current_save_slot = auto_index_slot(max_save_slots, |slot| emulator.has_save_state(slot));
+        println!("Saving states from slot {} (savestate_auto_index)", current_save_slot);
     }
 
-    println!("Gamepad Setup");
-    let mut gilrs = Gilrs::new().unwrap();
-    let mut active_gamepad = None;
-
-    let mut av_info = SystemAvInfo {
-        geometry: GameGeometry {
-            base_width: 0,
-            base_height: 0,
-            max_width: 0,
-            max_height: 0,
-            aspect_ratio: 0.0,
-        },
-        timing: SystemTiming {
-            fps: 0.0,
-            sample_rate: 0.0,
-        },
+    let audio_latency_ms = get_audio_latency_ms(&config);
+    // The core's audio is still collected (recordings use it) but nothing plays it without an output
+    let mut audio_output = if config["audio_enabled"] != "true" {
+        println!("Audio is disabled (audio_enabled or --no-audio)");
+        None
+    } else {
+        println!("Setting up Audio");
+        let sample_rate = emulator.av_info().timing.sample_rate as u32;
+        AudioOutput::new(
+            sample_rate,
+            audio_latency_ms,
+            &config["audio_device"],
+            get_audio_max_timing_skew(&config),
+        )
+            .map_err(|e| println!("{}, continuing without audio", e))
+            .ok()
     };
-    unsafe {
-        println!("Setting up Core");
-        core_api = load_core(&CURRENT_EMULATOR_STATE.core_name);
-        (core_api.retro_init)();
-        (core_api.retro_get_system_av_info)(&mut av_info);
-        println!("AV Info: {:?}", &av_info);
-        CURRENT_EMULATOR_STATE.av_info = Some(av_info.clone());
-        // Environment variables
-        CURRENT_EMULATOR_STATE.system_directory = Some(CString::new("System").unwrap());
 
-        println!("About to load ROM: {:?}", CURRENT_EMULATOR_STATE.rom_name);
-        load_rom_file(&core_api, &CURRENT_EMULATOR_STATE.rom_name);
+    println!("Gamepad Setup");
+    let mut gilrs = Gilrs::new().unwrap();
+    print_gamepads(&gilrs);
+    let mut active_gamepad = select_gamepad(&gilrs, &config);
+    let mut gamepad_rumble = GamepadRumble::new();
+
+    let mut ram_watches = load_ram_watches(emulator.rom_name());
+
+    let mut input_recorder = matches.value_of("record_input").map(|replay_file| {
+        InputRecorder::create(Path::new(replay_file), &emulator.serialize())
+            .unwrap_or_else(|e| panic!("{}", e))
+    });
+    let mut input_playback = matches.value_of("play_input").map(|replay_file| {
+        let playback =
+            InputPlayback::open(Path::new(replay_file)).unwrap_or_else(|e| panic!("{}", e));
+        if !emulator.unserialize(&playback.initial_state) {
+            println!("Core failed to load the replay's starting save state, playback may desync");
+        }
+        println!("Playing back {} frames of input from {}", playback.total_frames(), replay_file);
+        playback
+    });
+    if let Some(frames) = matches.value_of("hash_frames") {
+        let frames = frames
+            .parse::<u64>()
+            .unwrap_or_else(|_| panic!("Invalid --hash-frames: {}", frames));
+        let every_frame = matches.is_present("hash_every_frame");
+        print_frame_hashes(&mut emulator, frames, input_playback.as_mut(), every_frame);
+        return;
+    }
+    if let Some(frames) = matches.value_of("verify_savestate") {
+        let frames = frames
+            .parse::<u64>()
+            .unwrap_or_else(|_| panic!("Invalid --verify-savestate: {}", frames));
+        verify_save_state(&mut emulator, frames, input_playback.as_mut());
+        return;
+    }
+    let replay_continue = matches.is_present("replay_continue");
+    let mut video_recorder = matches.value_of("record_video").map(|video_file| {
+        VideoRecorder::create(Path::new(video_file), emulator.av_info().timing.fps)
+            .unwrap_or_else(|e| panic!("{}", e))
+    });
+    let mut av_recorder = matches.value_of("record").map(|file| {
+        let timing = &emulator.av_info().timing;
+        AvRecorder::create(Path::new(file), timing.fps, timing.sample_rate as u32, emulator.frame_count())
+            .unwrap_or_else(|e| panic!("{}", e))
+    });
+    if let Some(file) = matches.value_of("dump_audio") {
+        emulator.dump_audio(Path::new(file)).unwrap_or_else(|e| panic!("{}", e));
     }
 
-    let fps = av_info.timing.fps as u64;
-    window.limit_update_rate(Some(std::time::Duration::from_micros(1000000/fps)));
-    while window.is_open() && !window.is_key_down(Key::Escape) {
-        // Call the libRetro core every frame
-        unsafe {
-            (core_api.retro_run)();
+    let commands = matches.value_of("command_socket").map(|address| {
+        spawn_command_listener(address).unwrap_or_else(|e| panic!("{}", e))
+    });
+    let input_server = matches.value_of("input_server").map(|address| {
+        spawn_input_server(address).unwrap_or_else(|e| panic!("{}", e))
+    });
+    let input_server_port = matches
+        .value_of("input_server_port")
+        .map_or(0, |port| port.parse::<usize>().unwrap() - 1);
+    let mut remote_mask = 0;
+    let mut is_paused = false;
+    // Kept apart from is_paused so a manual pause stays paused when the window gets focus back
+    let pause_on_focus_loss = config["pause_on_focus_loss"] == "true";
+    let mut is_focus_paused = false;
+    let mut is_fast_forwarding = false;
+    let mut geometry_settle_frames: Option<u32> = None;
+    let mut run_ahead_frames = get_run_ahead_frames(&config);
+    if run_ahead_frames > 0 && emulator.serialize().is_empty() {
+        println!("Run ahead needs save states which this core doesn't support, turning it off");
+        run_ahead_frames = 0;
+    }
+    let mut rewind = (config["rewind_enable"] == "true").then(|| {
+        RewindBuffer::new(
+            get_rewind_granularity(&config),
+            get_rewind_buffer_size(&config),
+            get_savestate_compression(&config),
+        )
+    });
+    if rewind.is_some() && emulator.serialize().is_empty() {
+        println!("Rewind needs save states which this core doesn't support, turning it off");
+        rewind = None;
+    }
+    let fast_forward_frames = get_fast_forward_frames(&config);
+    let fast_forward_mute = config["fast_forward_mute"] == "true";
+    let load_fail_osd = config["savestate_load_fail_osd"] == "true";
+    let load_fail_alert = get_load_fail_alert(&config);
+    let mut flash_frames = 0;
+    // The loop is paced by a timer by default, a frame every 1/fps seconds. The audio device plays at its
+    // own clock though, which never quite matches, so over time the audio buffer drains (crackles) or fills
+    // up (drops audio). sync_to_audio paces each frame by how much audio the device has played instead so
+    // the two can't drift apart, at the cost of the frame rate following the audio clock: frames are
+    // occasionally shown a little early or late, which can look like judder, and minifb can't wait for
+    // vsync so there may be tearing either way. Without audio (or while it's muted) the timer is used.
+    let mut sync_to_audio = config["sync_to_audio"] == "true" && audio_output.is_some();
+    let mut fast_forward_audio = Vec::new();
+    let mut hotkey_tracker = KeyPressTracker::new();
+
+    if let Some(window) = window.as_mut() {
+        window.limit_update_rate(window_update_rate(&emulator, sync_to_audio));
+    }
+    let run_seconds = matches.value_of("run_seconds").map(|seconds| {
+        seconds
+            .parse::<f64>()
+            .ok()
+            .and_then(|seconds| Duration::try_from_secs_f64(seconds).ok())
+            .unwrap_or_else(|| panic!("Invalid --run-seconds: {}", seconds))
+    });
+    let confirm_exit = config["confirm_exit"] == "true";
+    let mut exit_confirmation = ExitConfirmation::new(EXIT_CONFIRM_WINDOW);
+    let run_start = Instant::now();
+    let mut timer_frame_start = Instant::now();
+    while window
+        .as_ref()
+        .is_none_or(|window| window.is_open() && (confirm_exit || !window.is_key_down(Key::Escape)))
+    {
+        if run_seconds.is_some_and(|run_seconds| run_start.elapsed() >= run_seconds) {
+            println!("Ran for {:.2}s ({} frames), quitting", run_start.elapsed().as_secs_f64(), emulator.frame_count());
+            break;
+        }
+        let is_window_active = window.as_mut().is_none_or(|window| window.is_active());
+        if pause_on_focus_loss && is_window_active == is_focus_paused {
+            is_focus_paused = !is_focus_paused;
+            println!(
+                "Window {} focus, {}",
+                if is_focus_paused { "lost" } else { "regained" },
+                if is_focus_paused { "pausing" } else { "resuming" }
+            );
         }
-
         // Calculate fps
         fps_counter += 1;
+        stats_frames += 1;
         let elapsed = fps_timer.elapsed();
-        if elapsed >= Duration::from_secs(1) {
+        if elapsed >= fps_sample_interval {
             let fps = fps_counter as f64 / elapsed.as_secs_f64();
-            window.set_title(&format!("RustroArch (FPS: {:.2})", fps));
+            match fps_display {
+                FpsDisplay::Title if show_frame_count => {
+                    let text = format!("FPS: {:.2}, Frame: {}", fps, emulator.frame_count());
+                    match window.as_mut() {
+                        Some(window) => window.set_title(&format!("RustroArch ({})", text)),
+                        None => println!("{}", text),
+                    }
+                }
+                FpsDisplay::Title => match window.as_mut() {
+                    Some(window) => window.set_title(&format!("RustroArch (FPS: {:.2})", fps)),
+                    None => println!("FPS: {:.2}", fps),
+                },
+                FpsDisplay::Overlay => fps_text = format!("FPS {:.2}", fps),
+                FpsDisplay::Off => {}
+            }
             fps_counter = 0;
             fps_timer = Instant::now();
         }
+        if stats_timer.elapsed() >= Duration::from_secs(1) {
+            if !ram_watches.is_empty() {
+                print_ram_watches(&emulator, &ram_watches);
+            }
+            let underruns = audio_output.as_ref().map_or(0, |output| output.take_underruns());
+            if underruns > 0 {
+                println!(
+                    "Audio buffer ran dry {} times in the last second, try increasing audio_latency_ms",
+                    underruns
+                );
+            }
+            let fps = stats_frames as f64 / stats_timer.elapsed().as_secs_f64();
+            // A core that said it's demanding and keeps falling behind explains the slowdown, once
+            let core_fps = emulator.av_info().timing.fps;
+            let running_freely = !is_paused && !is_focus_paused && !is_fast_forwarding;
+            slow_seconds = match running_freely && fps < core_fps * 0.9 {
+                true => slow_seconds + 1,
+                false => 0,
+            };
+            if let Some(level) = emulator.performance_level().filter(|_| slow_seconds == SLOW_SECONDS_BEFORE_WARNING) {
+                println!(
+                    "Running at {:.1} of {:.1} fps, the core needs performance level {} which this machine may not keep up with",
+                    fps, core_fps, level
+                );
+            }
+            if let Some(output) = status_output.as_mut() {
+                let status = Status {
+                    fps,
+                    frame: emulator.frame_count(),
+                    save_slot: current_save_slot,
+                    pixel_format: emulator.pixel_format(),
+                    width: emulator.screen_width(),
+                    height: emulator.screen_height(),
+                    paused: is_paused || is_focus_paused,
+                    muted: audio_output.is_none() || (is_fast_forwarding && fast_forward_mute),
+                };
+                // Whatever's reading them going away isn't a reason to stop the game
+                if writeln!(output, "{}", status.to_json()).and_then(|_| output.flush()).is_err() {
+                    status_output = None;
+                }
+            }
+            stats_frames = 0;
+            stats_timer = Instant::now();
+        }
 
         let mut this_frames_pressed_buttons = vec![0; 16];
 
-        let mini_fb_keys = window.get_keys_pressed(KeyRepeat::Yes).unwrap();
+        // Game buttons are pressed for as long as their key is held, hotkeys only fire on the frame
+        // their key goes down so holding one doesn't repeat it
+        let held_keys = window
+            .as_ref()
+            .and_then(|window| window.get_keys())
+            .unwrap_or_default();
+        let newly_pressed_keys = hotkey_tracker.newly_pressed(&held_keys);
+        if confirm_exit && newly_pressed_keys.contains(&Key::Escape) {
+            if exit_confirmation.press(Instant::now()) {
+                break;
+            }
+            println!("Press Escape again to quit");
+            osd_message.show("Press again to quit", EXIT_CONFIRM_WINDOW);
+        }
+        let hotkeys_enabled = are_hotkeys_enabled(&config, &held_keys);
+        let active_key_device_map = if hotkeys_enabled { &hotkey_key_device_map } else { &key_device_map };
+        // Replays store one input per shown frame so fast forward would desync them. A key that's also a game
+        // button only fast forwards when hotkeys win.
+        is_fast_forwarding = hotkeys_enabled
+            && input_recorder.is_none()
+            && input_playback.is_none()
+            && held_keys
+                .iter()
+                .map(key_to_config_name)
+                .any(|key| is_key_bound(&config["input_hold_fast_forward"], &key) && !active_key_device_map.contains_key(&key));
+        // Like fast forward, held and left out of replays
+        let is_rewinding = rewind.is_some()
+            && hotkeys_enabled
+            && input_recorder.is_none()
+            && input_playback.is_none()
+            && held_keys
+                .iter()
+                .map(key_to_config_name)
+                .any(|key| is_key_bound(&config["input_rewind"], &key) && !active_key_device_map.contains_key(&key));
 
         // Gamepad input Handling
         // Examine new events
-        while let Some(Event { id, event, time }) = gilrs.next_event() {
+        while let Some(Event { event, .. }) = gilrs.next_event() {
             // println!("{:?} New event from {}: {:?}", time, id, event);
-            active_gamepad = Some(id);
+            if matches!(event, EventType::Connected | EventType::Disconnected) {
+                active_gamepad = select_gamepad(&gilrs, &config);
+            }
         }
 
         // You can also use cached gamepad state
         if let Some(gamepad) = active_gamepad.map(|id| gilrs.gamepad(id)) {
-            for button in [Button::South, Button::North, Button::East, Button::West, Button::Start, Button::Select, Button::DPadDown, Button::DPadUp, Button::DPadLeft, Button::DPadRight, Button::LeftTrigger, Button::LeftTrigger2, Button::RightTrigger, Button::RightTrigger2] {
-                if gamepad.is_pressed(button) {
+            for (button, libretro_button) in &joypad_device_map {
+                if gamepad.is_pressed(*button) {
                     println!("Button Pressed: {:?}", button);
-                    let libretro_button = joypad_device_map.get(&button).unwrap();
                     this_frames_pressed_buttons[*libretro_button] = 1;
                 }
             }
+            for binding in &joypad_axis_bindings {
+                if binding.is_pressed(gamepad.value(binding.axis), analog_deadzone) {
+                    this_frames_pressed_buttons[binding.button] = 1;
+                }
+            }
+            // Let the left stick drive the dpad for cores that only take digital input
+            analog_to_dpad(
+                gamepad.value(Axis::LeftStickX),
+                gamepad.value(Axis::LeftStickY),
+                analog_deadzone,
+                &mut this_frames_pressed_buttons,
+            );
         }
 
-        unsafe {
-            // Input Handling for the keys pressed in minifb cargo
-            for key in mini_fb_keys {
-                let key_as_string = format!("{:?}", key).to_ascii_lowercase();
+        // Input Handling for the keys pressed in minifb cargo
+        for key in &held_keys {
+            if let Some(libretro_button_id) = active_key_device_map.get(&key_to_config_name(key)) {
+                this_frames_pressed_buttons[*libretro_button_id] = 1;
+            }
+        }
+        for key in newly_pressed_keys {
+            let key_as_string = key_to_config_name(&key);
 
-                if let Some(libretro_button_id) = key_device_map.get(&key_as_string) {
-                    this_frames_pressed_buttons[*libretro_button_id] = 1;
-                    continue;
+            if active_key_device_map.contains_key(&key_as_string) {
+                continue;
+            }
+            if !hotkeys_enabled {
+                continue;
+            }
+            if is_key_bound(&config["input_hold_fast_forward"], &key_as_string)
+                || is_key_bound(&config["input_rewind"], &key_as_string)
+            {
+                continue; // handled above while it's held
+            }
+            if is_key_bound(&config["input_reset"], &key_as_string) {
+                emulator.reset();
+                println!("Core was reset");
+                continue;
+            }
+            if is_key_bound(&config["input_swap_core"], &key_as_string) {
+                let core_path = prompt_for_path("Core to load", emulator.core_name());
+                let rom_path = prompt_for_path("ROM to load", emulator.rom_name());
+                let rom_path = match resolve_rom_path(&rom_path, &config["roms_directory"]) {
+                    Ok(rom_path) => rom_path.to_string_lossy().into_owned(),
+                    Err(err) => {
+                        println!("{}", err);
+                        continue;
+                    }
+                };
+                let game_config = game_config(&config, &rom_path, &core_options);
+                emulator = swap_core(emulator, &core_path, &rom_path, game_config);
+                ram_watches = load_ram_watches(emulator.rom_name());
+                if let Some(window) = window.as_mut() {
+                    window.limit_update_rate(window_update_rate(&emulator, sync_to_audio));
                 }
-                if &key_as_string == &config["input_save_state"] {
-                    save_state(&core_api, &config["savestate_directory"]);
-                    continue;
+                if input_playback.take().is_some() {
+                    println!("Replay stopped as the core was swapped");
                 }
-                if &key_as_string == &config["input_load_state"] {
-                    load_state(&core_api, &config["savestate_directory"]);
-                    continue;
+                continue;
+            }
+            if is_key_bound(&config["input_save_state"], &key_as_string) {
+                emulator.save_state(current_save_slot);
+                if savestate_auto_index {
+                    current_save_slot = next_save_slot(current_save_slot, max_save_slots, true, true);
+                    println!("Current save slot: Slot {}/{}", current_save_slot, max_save_slots);
                 }
-                if &key_as_string == &config["input_state_slot_increase"] {
-                    if CURRENT_EMULATOR_STATE.current_save_slot != 255 {
-                        CURRENT_EMULATOR_STATE.current_save_slot += 1;
-                        println!(
-                            "Current save slot increased to: {}",
-                            CURRENT_EMULATOR_STATE.current_save_slot
-                        )
+                continue;
+            }
+            if is_key_bound(&config["input_load_state"], &key_as_string) {
+                // With savestate_auto_index the current slot is the next one to save to, not the last save
+                let slot = match savestate_auto_index {
+                    true => highest_occupied_slot(max_save_slots, |slot| emulator.has_save_state(slot))
+                        .unwrap_or(current_save_slot),
+                    false => current_save_slot,
+                };
+                match emulator.load_state(slot) {
+                    Ok(loaded_slot) if loaded_slot != slot => {
+                        let message = format!("Slot {} empty - loaded slot {}", slot, loaded_slot);
+                        if load_fail_osd {
+                            osd_message.show(&message, OSD_MESSAGE_DURATION);
+                        }
+                        current_save_slot = loaded_slot;
+                    }
+                    Ok(_) => {}
+                    Err(err) => {
+                        report_load_failure(&err, load_fail_osd, load_fail_alert, &mut osd_message, &mut flash_frames)
                     }
-                    continue;
                 }
-                if &key_as_string == &config["input_state_slot_decrease"] {
-                    if CURRENT_EMULATOR_STATE.current_save_slot != 0 {
-                        CURRENT_EMULATOR_STATE.current_save_slot -= 1;
-                        println!(
-                            "Current save slot decreased to: {}",
-                            CURRENT_EMULATOR_STATE.current_save_slot
-                        )
+                continue;
+            }
+            if is_key_bound(&config["input_screenshot"], &key_as_string) {
+                match emulator.screenshot() {
+                    Ok(path) => println!("Screenshot saved to: {}", path.display()),
+                    Err(err) => println!("{}", err),
+                }
+                continue;
+            }
+            if is_key_bound(&config["input_screenshot_clipboard"], &key_as_string) {
+                match emulator.screenshot_to_clipboard() {
+                    Ok(destination) => println!("Screenshot copied to: {}", destination),
+                    Err(err) => println!("{}", err),
+                }
+                continue;
+            }
+            if is_key_bound(&config["input_toggle_filter"], &key_as_string) {
+                // Only the enum changes, the filtered frame is rebuilt every frame anyway
+                video_filter = video_filter.toggled();
+                println!("Video filter: {}", video_filter.name());
+                osd_message.show(&format!("Filter: {}", video_filter.name()), OSD_MESSAGE_DURATION);
+                continue;
+            }
+            let scale_increase = is_key_bound(&config["input_scale_increase"], &key_as_string);
+            if scale_increase || is_key_bound(&config["input_scale_decrease"], &key_as_string) {
+                // minifb windows can't be resized so a new one is made at the new size
+                if let Some(window) = window.as_mut() {
+                    let rotation = video_rotation.unwrap_or(emulator.rotation());
+                    let current_scale = video_scale.unwrap_or_else(|| {
+                        // Carry on from whichever whole scale is closest to the 640x480 window
+                        let (base_width, _) = window_size(&emulator, Some(1), rotation, &WindowSizeLimits::default());
+                        (window.get_size().0 / base_width.max(1)).max(1) as u32
+                    });
+                    let scale = next_video_scale(current_scale, video_scale_max, scale_increase);
+                    let (width, height) = window_size(&emulator, Some(scale), rotation, &window_size_limits);
+                    match create_window(width, height) {
+                        Ok(mut new_window) => {
+                            new_window.limit_update_rate(window_update_rate(&emulator, sync_to_audio));
+                            *window = new_window;
+                            video_scale = Some(scale);
+                            println!("Video scale: {}x ({}x{})", scale, width, height);
+                            osd_message.show(&format!("Scale: {}x", scale), OSD_MESSAGE_DURATION);
+                        }
+                        Err(err) => println!("{}", err),
                     }
-                    continue;
                 }
-                println!("Unhandled Key Pressed: {} ", key_as_string);
+                continue;
+            }
+            if is_key_bound(&config["input_toggle_aspect"], &key_as_string) {
+                // Read on every present, the letterbox is worked out again from the new mode
+                aspect_mode = aspect_mode.next();
+                println!("Aspect mode: {}", aspect_mode.name());
+                osd_message.show(&format!("Aspect: {}", aspect_mode.name()), OSD_MESSAGE_DURATION);
+                continue;
             }
+            if is_key_bound(&config["input_toggle_swap_ab"], &key_as_string) {
+                face_button_swap.ab = !face_button_swap.ab;
+                let state = if face_button_swap.ab { "swapped" } else { "normal" };
+                println!("A/B buttons: {}", state);
+                osd_message.show(&format!("A/B: {}", state), OSD_MESSAGE_DURATION);
+                continue;
+            }
+            if is_key_bound(&config["input_debug_dump"], &key_as_string) {
+                println!("===== Debug dump =====\n{}\n======================", emulator.debug_info());
+                continue;
+            }
+            if is_key_bound(&config["input_quick_swap_slot"], &key_as_string) {
+                current_save_slot = quick_swap_save_slot(current_save_slot, quick_swap_slot);
+                println!("Current save slot swapped to: Slot {}/{}", current_save_slot, max_save_slots);
+                osd_message.show(&format!("Slot {}", current_save_slot), OSD_MESSAGE_DURATION);
+                continue;
+            }
+            if is_key_bound(&config["input_state_slot_increase"], &key_as_string) {
+                current_save_slot =
+                    next_save_slot(current_save_slot, max_save_slots, save_slot_wrap, true);
+                println!(
+                    "Current save slot increased to: Slot {}/{}",
+                    current_save_slot, max_save_slots
+                );
+                continue;
+            }
+            if is_key_bound(&config["input_state_slot_decrease"], &key_as_string) {
+                current_save_slot =
+                    next_save_slot(current_save_slot, max_save_slots, save_slot_wrap, false);
+                println!(
+                    "Current save slot decreased to: Slot {}/{}",
+                    current_save_slot, max_save_slots
+                );
+                continue;
+            }
+            println!("Unhandled Key Pressed: {} ", key_as_string);
+        }
 
-            CURRENT_EMULATOR_STATE.buttons_pressed = Some(this_frames_pressed_buttons);
-            send_audio_to_thread(&sender);
+        // Only the local keyboard and gamepad, remote and replayed input is already in the game's layout
+        face_button_swap.apply(&mut this_frames_pressed_buttons);
 
-            match &CURRENT_EMULATOR_STATE.frame_buffer {
-                Some(buffer) => {
-                    let width = (CURRENT_EMULATOR_STATE.screen_pitch
-                        / CURRENT_EMULATOR_STATE.bytes_per_pixel as u32)
-                        as usize;
-                    let height = CURRENT_EMULATOR_STATE.screen_height as usize;
-                    let slice_of_pixel_buffer: &[u32] =
-                        std::slice::from_raw_parts(buffer.as_ptr() as *const u32, buffer.len()); // convert to &[u32] slice reference
-                    if slice_of_pixel_buffer.len() < width * height * 4 {
-                        // The frame buffer isn't big enough so lets add additional pixels just so we can display it
-                        let mut vec: Vec<u32> = slice_of_pixel_buffer.to_vec();
-                        // println!("Frame Buffer wasn't big enough");
-                        vec.resize((width * height * 4) as usize, 0x0000FFFF); // Add any missing pixels with colour blue
-                        window.update_with_buffer(&vec, width, height).unwrap();
-                    } else {
-                        window
-                            .update_with_buffer(&slice_of_pixel_buffer, width, height)
-                            .unwrap();
-                    }
+        // Commands from the command socket are run here as the core can only be used from this thread
+        let mut quit_requested = false;
+        for request in commands.iter().flat_map(|commands| commands.try_iter()) {
+            println!("Command received: {:?}", request.command);
+            let result = run_command(&mut emulator, &request.command, max_save_slots, &mut is_paused);
+            if let (Command::Load(_), Err(err)) = (&request.command, &result) {
+                report_load_failure(err, load_fail_osd, load_fail_alert, &mut osd_message, &mut flash_frames);
+            }
+            quit_requested |= request.command == Command::Quit;
+            request.response.send(result).ok();
+        }
+        if quit_requested {
+            break;
+        }
+
+        for event in input_server.iter().flat_map(|input_server| input_server.try_iter()) {
+            remote_mask = match event {
+                InputServerEvent::Input(input) => input.mask,
+                InputServerEvent::Disconnected => 0,
+            };
+        }
+        if input_server.is_some() {
+            let remote_buttons = replay::mask_to_buttons(remote_mask);
+            if input_server_port == 0 {
+                for (button, remote_button) in this_frames_pressed_buttons.iter_mut().zip(remote_buttons) {
+                    *button |= remote_button;
+                }
+            } else {
+                emulator.set_input(input_server_port, &remote_buttons);
+            }
+        }
+
+        // While paused no frames are run so the replay shouldn't advance either
+        if let Some(playback) = input_playback.as_mut().filter(|_| !is_paused && !is_focus_paused) {
+            match playback.next_frame() {
+                Some(mask) => this_frames_pressed_buttons = replay::mask_to_buttons(mask),
+                None if replay_continue => {
+                    println!("Replay finished, returning control to the user");
+                    input_playback = None;
                 }
                 None => {
-                    println!("We don't have a buffer to display");
+                    println!("Replay finished");
+                    break;
+                }
+            }
+        }
+        if let Some(recorder) = input_recorder.as_mut().filter(|_| !is_paused && !is_focus_paused) {
+            if let Err(err) = recorder.record_frame(replay::buttons_to_mask(&this_frames_pressed_buttons)) {
+                println!("{}, recording stopped", err);
+                input_recorder = None;
+            }
+        }
+
+        emulator.set_input(0, &this_frames_pressed_buttons);
+        emulator.set_keyboard(&held_keys);
+        if let Some(window) = window.as_ref() {
+            emulator.set_lightgun(lightgun_from_mouse(
+                window.get_mouse_pos(MouseMode::Discard),
+                window.get_size(),
+                window.get_mouse_down(MouseButton::Left),
+                window.get_mouse_down(MouseButton::Right),
+            ));
+        }
+
+        // Call the libRetro core every frame. This is after the input for this frame has been gathered so
+        // the core sees it straight away and the frame it draws is shown right after.
+        let fast_forwarded = is_fast_forwarding && !is_paused && !is_focus_paused;
+        emulator.set_fixed_frame_time(is_fast_forwarding || is_paused || is_focus_paused);
+        if !is_paused && !is_focus_paused {
+            fast_forward_audio.clear();
+            let run_result = if let Some(rewind) = rewind.as_mut().filter(|_| is_rewinding) {
+                rewind_frame(&mut emulator, rewind)
+            } else if fast_forwarded {
+                // Recordings keep all the audio even when fast forward is muted
+                let keep_audio = !fast_forward_mute || av_recorder.is_some();
+                let audio_samples = keep_audio.then_some(&mut fast_forward_audio);
+                run_frames(&mut emulator, fast_forward_frames, audio_samples)
+            } else if run_ahead_frames > 0 {
+                emulator.run_frame_ahead(run_ahead_frames)
+            } else {
+                emulator.run_frame()
+            };
+            if let Err(err) = run_result {
+                println!("{}", err);
+                break;
+            }
+            // Recorded once a shown frame (the buffer keeps every rewind_granularity-th), so the frames fast
+            // forward skips over are rewound past in one step
+            if let Some(rewind_buffer) = rewind.as_mut().filter(|_| !is_rewinding) {
+                if let Err(err) = rewind_buffer.record(|| emulator.serialize()) {
+                    println!("{}, turning rewind off", err);
+                    rewind = None;
+                }
+            }
+        }
+        if emulator.should_quit() {
+            println!("Shutting down as the core asked");
+            break;
+        }
+        if emulator.take_rumble_changed() {
+            if let Some(gamepad_id) = active_gamepad {
+                gamepad_rumble.set(&mut gilrs, gamepad_id, emulator.rumble_state(0));
+            }
+        }
+        if emulator.take_av_info_changed() {
+            // The window stretches whatever size frame it's given so only the pacing needs updating,
+            // the audio device is reopened at the new sample rate when the next audio is queued
+            let geometry = &emulator.av_info().geometry;
+            println!(
+                "AV Info changed to {}x{} at {:.2} fps, {} Hz",
+                geometry.base_width,
+                geometry.base_height,
+                emulator.av_info().timing.fps,
+                emulator.av_info().timing.sample_rate
+            );
+            if let Some(window) = window.as_mut() {
+                window.limit_update_rate(window_update_rate(&emulator, sync_to_audio));
+            }
+        }
+        // Some cores set the geometry every frame while switching video modes. The window already
+        // stretches each frame to fit so there's nothing to resize, the new geometry is only reported
+        // once it has settled.
+        if emulator.take_geometry_changed() {
+            geometry_settle_frames = Some(GEOMETRY_SETTLE_FRAMES);
+        }
+        if let Some(frames) = geometry_settle_frames {
+            if frames == 0 {
+                let geometry = &emulator.av_info().geometry;
+                println!(
+                    "Geometry changed to {}x{} aspect ratio {:.3}",
+                    geometry.base_width, geometry.base_height, geometry.aspect_ratio
+                );
+            }
+            geometry_settle_frames = frames.checked_sub(1);
+        }
+
+        // Fast forward runs several frames of audio for every frame of real time. Queuing all of it would
+        // overflow the ring buffer, dropping audio and leaving it at its maximum latency once fast forward
+        // ends. So it's either muted (fast_forward_mute, the default) or sped up to fit in one frame's
+        // time, which keeps it in sync but raises the pitch like a tape played too fast.
+        let mut audio_paced = false;
+        let mut audio_result = Ok(());
+        if let Some(audio_output) = audio_output.as_mut().filter(|_| !is_paused && !is_focus_paused) {
+            if !fast_forwarded {
+                audio_result = queue_audio(audio_output, &emulator, emulator.audio_data(), audio_latency_ms);
+                audio_paced = sync_to_audio;
+            } else if !fast_forward_mute {
+                let samples = speed_up_samples(&fast_forward_audio, fast_forward_frames as usize);
+                audio_result = queue_audio(audio_output, &emulator, &samples, audio_latency_ms);
+                audio_paced = sync_to_audio;
+            }
+            if audio_paced && audio_result.is_ok() {
+                audio_output.wait_for_playback(frame_duration(&emulator) * 2);
+            }
+        }
+        if let Err(err) = audio_result {
+            // The device couldn't be reopened (e.g. it was unplugged), carry on silently paced by the timer
+            println!("{}, continuing without audio", err);
+            audio_output = None;
+            audio_paced = false;
+            sync_to_audio = false;
+            if let Some(window) = window.as_mut() {
+                window.limit_update_rate(window_update_rate(&emulator, sync_to_audio));
+            }
+        }
+
+        let width = emulator.screen_width() as usize;
+        let height = emulator.screen_height() as usize;
+        // The frame count changes every frame so the overlay is redrawn every frame, the title only when
+        // the FPS is
+        let overlay_text = match (fps_display, show_frame_count) {
+            (FpsDisplay::Overlay, true) => Some(format!("{} FRAME {}", fps_text, emulator.frame_count())),
+            (FpsDisplay::Overlay, false) => Some(fps_text.clone()),
+            (FpsDisplay::Off, true) => Some(format!("FRAME {}", emulator.frame_count())),
+            _ => None,
+        };
+        let rotation = video_rotation.unwrap_or(emulator.rotation());
+        let mut present_options = PresentOptions {
+            overscan: overscan_crop,
+            rotation,
+            color_correction: color_correction.clone(),
+            filter: video_filter,
+            aspect_ratio: None,
+            integer_scale: video_scale_integer,
+        };
+        let (frame_width, frame_height) = present_options.frame_size(width, height);
+        let flash = flash_frames > 0;
+        flash_frames = flash_frames.saturating_sub(1);
+        let draw_overlay = |buffer: &mut [u32], width: usize, height: usize, scale: usize| {
+            if let Some(text) = &overlay_text {
+                draw_text_scaled(buffer, width, height, scale, scale, scale, text);
+            }
+            if let Some(text) = osd_message.current() {
+                let y = height.saturating_sub((TEXT_HEIGHT + 1) * scale);
+                draw_text_scaled(buffer, width, height, scale, y, scale, text);
+            }
+            if flash {
+                invert_colors(buffer);
+            }
+        };
+        // Recordings always get the frame at its own size, so does the window unless video_scale_to_window
+        let scaled_window = window.is_some() && video_scale_to_window;
+        let wants_frame = (window.is_some() && !scaled_window) || video_recorder.is_some() || av_recorder.is_some();
+        let frame_buffer = emulator.frame_buffer();
+        let stride = emulator.frame_stride();
+        let presented_frame = match wants_frame {
+            true => present_frame(frame_buffer, width, height, stride, 0, 0, &present_options),
+            false => None,
+        }
+        .map(|mut buffer| {
+            draw_overlay(&mut buffer, frame_width, frame_height, 1);
+            (buffer, frame_width, frame_height)
+        });
+        if let (Some(recorder), Some((buffer, width, height))) = (video_recorder.as_mut(), &presented_frame) {
+            if let Err(err) = recorder.record_frame(buffer, *width, *height) {
+                println!("{}, video recording stopped", err);
+                video_recorder = None;
+            }
+        }
+        if let Some(recorder) = av_recorder.as_mut() {
+            let frame = presented_frame
+                .as_ref()
+                .map(|(buffer, width, height)| (buffer.as_slice(), *width, *height));
+            let samples = if fast_forwarded { &fast_forward_audio } else { emulator.audio_data() };
+            if let Err(err) = recorder.record(emulator.frame_count(), frame, samples) {
+                println!("{}, recording stopped", err);
+                av_recorder = None;
+            }
+        }
+
+        // The window paces the loop when it's shown unless the audio does (sync_to_audio). Headless, or
+        // synced to audio while there's none, it sleeps out the rest of the frame.
+        if window.is_none() || (sync_to_audio && !audio_paced) {
+            let frame_time = timer_frame_start.elapsed();
+            std::thread::sleep(frame_duration(&emulator).saturating_sub(frame_time));
+        }
+        timer_frame_start = Instant::now();
+        let Some(window) = window.as_mut() else {
+            continue;
+        };
+        // Only what's shown is letterboxed, recordings and screenshots keep the frame as it is
+        let core_aspect_ratio = emulator.av_info().geometry.aspect_ratio;
+        let aspect_ratio = aspect_mode.aspect_ratio(frame_width, frame_height, core_aspect_ratio, rotation % 2 == 1);
+        let (window_width, window_height) = window.get_size();
+        let shown_frame = match scaled_window {
+            true => {
+                present_options.aspect_ratio = aspect_ratio;
+                present_frame(frame_buffer, width, height, stride, window_width, window_height, &present_options).map(
+                    |mut buffer| {
+                        let scale = (window_height / frame_height.max(1)).max(1);
+                        draw_overlay(&mut buffer, window_width, window_height, scale);
+                        (buffer, window_width, window_height)
+                    },
+                )
+            }
+            false => presented_frame.map(|(buffer, width, height)| match aspect_ratio {
+                Some(aspect_ratio) => letterbox(&buffer, width, height, aspect_ratio, window_width, window_height),
+                None => (buffer, width, height),
+            }),
+        };
+        match shown_frame {
+            Some((buffer, width, height)) => {
+                if let Err(err) = window.update_with_buffer(&buffer, width, height) {
+                    println!("Failed to present frame: {}", err);
                 }
             }
+            None => {
+                // Early frames from some cores have an inconsistent pitch/height, keep showing the
+                // previous frame but still process the window events so input keeps working
+                println!(
+                    "Skipping frame, buffer of {} pixels is too small for {}x{}",
+                    emulator.frame_buffer().len(),
+                    width,
+                    height
+                );
+                window.update();
+            }
         }
     }
     // Cleanup at the end
+    if let Some(recorder) = input_recorder {
+        match recorder.finish() {
+            Ok(_) => println!("Input recording saved to: {}", matches.value_of("record_input").unwrap()),
+            Err(err) => println!("{}", err),
+        }
+    }
+    if let Some(recorder) = av_recorder {
+        let mux_command = recorder.ffmpeg_command("recording.mp4");
+        match recorder.finish() {
+            Ok(summary) => {
+                println!(
+                    "Recorded {} frames ({:.2}s of video) and {:.2}s of audio",
+                    summary.video_frames,
+                    summary.video_seconds(),
+                    summary.audio_seconds()
+                );
+                if !summary.is_in_sync() {
+                    println!("The video and audio lengths differ by more than 2 frames, they may drift apart");
+                }
+                if let Some(mux_command) = mux_command {
+                    println!("Mux them with: {}", mux_command);
+                }
+            }
+            Err(err) => println!("{}", err),
+        }
+    }
+    match emulator.finish_audio_dump() {
+        Some(Ok(frames)) => println!(
+            "{} frames of audio dumped to: {}",
+            frames,
+            matches.value_of("dump_audio").unwrap()
+        ),
+        Some(Err(err)) => println!("{}", err),
+        None => {}
+    }
+    if let Some(recorder) = video_recorder {
+        match recorder.finish() {
+            Ok(frames) => println!(
+                "{} frames of video saved to: {}",
+                frames,
+                matches.value_of("record_video").unwrap()
+            ),
+            Err(err) => println!("{}", err),
+        }
+    }
 }
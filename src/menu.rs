@@ -0,0 +1,109 @@
+use crate::osd::{draw_text, TEXT_HEIGHT};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const LINE_HEIGHT: usize = TEXT_HEIGHT + 1;
+
+// --menu, the files in roms_directory to pick one from. The selection wraps around at either end.
+pub struct RomMenu {
+    roms: Vec<PathBuf>,
+    selected: usize,
+}
+
+impl RomMenu {
+    pub fn new(roms: Vec<PathBuf>) -> RomMenu {
+        RomMenu { roms, selected: 0 }
+    }
+
+    // Every file in the directory sorted by name, hidden files (and .meta/.watch side files) are left out
+    pub fn scan(roms_directory: &Path) -> Result<RomMenu, String> {
+        let entries = fs::read_dir(roms_directory).map_err(|e| {
+            format!(
+                "Failed to read roms_directory {}: {}",
+                roms_directory.display(),
+                e
+            )
+        })?;
+        let mut roms: Vec<PathBuf> = entries
+            .flatten()
+            .map(|entry| entry.path())
+            .filter(|path| {
+                let file_name = path
+                    .file_name()
+                    .map_or(String::new(), |name| name.to_string_lossy().into_owned());
+                path.is_file()
+                    && !file_name.starts_with('.')
+                    && !file_name.ends_with(".meta")
+                    && !file_name.ends_with(".watch")
+            })
+            .collect();
+        roms.sort();
+        Ok(RomMenu::new(roms))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.roms.is_empty()
+    }
+
+    pub fn move_selection(&mut self, down: bool) {
+        if self.roms.is_empty() {
+            return;
+        }
+        self.selected = match down {
+            true => (self.selected + 1) % self.roms.len(),
+            false => (self.selected + self.roms.len() - 1) % self.roms.len(),
+        };
+    }
+
+    pub fn selected(&self) -> Option<&Path> {
+        self.roms.get(self.selected).map(PathBuf::as_path)
+    }
+
+    // The file names that fit in `rows` lines, scrolled so the selected one (marked with >) is shown
+    pub fn visible_lines(&self, rows: usize) -> Vec<String> {
+        let rows = rows.max(1);
+        let first = self.selected.saturating_sub(rows - 1);
+        self.roms
+            .iter()
+            .enumerate()
+            .skip(first)
+            .take(rows)
+            .map(|(index, rom)| {
+                let marker = if index == self.selected { ">" } else { " " };
+                let name = rom.file_name().unwrap_or_default().to_string_lossy();
+                format!("{} {}", marker, name)
+            })
+            .collect()
+    }
+
+    // A title line, the list and a status line at the bottom (e.g. why the chosen ROM couldn't be loaded)
+    pub fn draw(&self, buffer: &mut [u32], width: usize, height: usize, status: &str) {
+        buffer.fill(0);
+        draw_text(
+            buffer,
+            width,
+            height,
+            1,
+            1,
+            "up/down choose - start loads - escape quits",
+        );
+        let rows = (height / LINE_HEIGHT).saturating_sub(3);
+        let lines = match self.roms.is_empty() {
+            true => vec!["no roms found".to_string()],
+            false => self.visible_lines(rows),
+        };
+        for (index, line) in lines.iter().enumerate() {
+            draw_text(buffer, width, height, 1, (index + 2) * LINE_HEIGHT, line);
+        }
+        if !status.is_empty() {
+            draw_text(
+                buffer,
+                width,
+                height,
+                1,
+                height.saturating_sub(LINE_HEIGHT),
+                status,
+            );
+        }
+    }
+}
@@ -0,0 +1,171 @@
+// On screen text drawn straight into the frame with a tiny 3x5 pixel font
+use std::time::{Duration, Instant};
+
+const GLYPH_WIDTH: usize = 3;
+const GLYPH_HEIGHT: usize = 5;
+pub const TEXT_HEIGHT: usize = GLYPH_HEIGHT + 2; // including the box around it
+const TEXT_COLOR: u32 = 0x00FFFFFF;
+const BACKGROUND_COLOR: u32 = 0x00000000;
+
+// Each row is 3 bits, the most significant bit is the leftmost pixel. Unknown characters are drawn blank.
+fn glyph(character: char) -> [u8; GLYPH_HEIGHT] {
+    match character.to_ascii_uppercase() {
+        '0' => [0b111, 0b101, 0b101, 0b101, 0b111],
+        '1' => [0b010, 0b110, 0b010, 0b010, 0b111],
+        '2' => [0b111, 0b001, 0b111, 0b100, 0b111],
+        '3' => [0b111, 0b001, 0b111, 0b001, 0b111],
+        '4' => [0b101, 0b101, 0b111, 0b001, 0b001],
+        '5' => [0b111, 0b100, 0b111, 0b001, 0b111],
+        '6' => [0b111, 0b100, 0b111, 0b101, 0b111],
+        '7' => [0b111, 0b001, 0b010, 0b010, 0b010],
+        '8' => [0b111, 0b101, 0b111, 0b101, 0b111],
+        '9' => [0b111, 0b101, 0b111, 0b001, 0b111],
+        '.' => [0b000, 0b000, 0b000, 0b000, 0b010],
+        ':' => [0b000, 0b010, 0b000, 0b010, 0b000],
+        '-' => [0b000, 0b000, 0b111, 0b000, 0b000],
+        'A' => [0b010, 0b101, 0b111, 0b101, 0b101],
+        'B' => [0b110, 0b101, 0b110, 0b101, 0b110],
+        'C' => [0b011, 0b100, 0b100, 0b100, 0b011],
+        'D' => [0b110, 0b101, 0b101, 0b101, 0b110],
+        'E' => [0b111, 0b100, 0b110, 0b100, 0b111],
+        'F' => [0b111, 0b100, 0b110, 0b100, 0b100],
+        'G' => [0b011, 0b100, 0b101, 0b101, 0b011],
+        'H' => [0b101, 0b101, 0b111, 0b101, 0b101],
+        'I' => [0b111, 0b010, 0b010, 0b010, 0b111],
+        'J' => [0b001, 0b001, 0b001, 0b101, 0b010],
+        'K' => [0b101, 0b101, 0b110, 0b101, 0b101],
+        'L' => [0b100, 0b100, 0b100, 0b100, 0b111],
+        'M' => [0b101, 0b111, 0b111, 0b101, 0b101],
+        'N' => [0b110, 0b101, 0b101, 0b101, 0b101],
+        'O' => [0b010, 0b101, 0b101, 0b101, 0b010],
+        'P' => [0b110, 0b101, 0b110, 0b100, 0b100],
+        'Q' => [0b010, 0b101, 0b101, 0b110, 0b011],
+        'R' => [0b110, 0b101, 0b110, 0b101, 0b101],
+        'S' => [0b111, 0b100, 0b111, 0b001, 0b111],
+        'T' => [0b111, 0b010, 0b010, 0b010, 0b010],
+        'U' => [0b101, 0b101, 0b101, 0b101, 0b111],
+        'V' => [0b101, 0b101, 0b101, 0b101, 0b010],
+        'W' => [0b101, 0b101, 0b111, 0b111, 0b101],
+        'X' => [0b101, 0b101, 0b010, 0b101, 0b101],
+        'Y' => [0b101, 0b101, 0b010, 0b010, 0b010],
+        'Z' => [0b111, 0b001, 0b010, 0b100, 0b111],
+        _ => [0; GLYPH_HEIGHT],
+    }
+}
+
+// Draws white text on a black box with its top left corner at x, y. Anything past the edge of the
+// frame is clipped.
+pub fn draw_text(pixels: &mut [u32], width: usize, height: usize, x: usize, y: usize, text: &str) {
+    draw_text_scaled(pixels, width, height, x, y, 1, text);
+}
+
+// draw_text with every pixel of the font drawn as a scale x scale block, for frames scaled up to the window
+pub fn draw_text_scaled(
+    pixels: &mut [u32],
+    width: usize,
+    height: usize,
+    x: usize,
+    y: usize,
+    scale: usize,
+    text: &str,
+) {
+    let scale = scale.max(1);
+    let box_width = text.chars().count() * (GLYPH_WIDTH + 1) + 1;
+    let box_height = GLYPH_HEIGHT + 2;
+    // Relative to x, y in unscaled font pixels
+    let mut set_pixel = |text_x: usize, text_y: usize, color: u32| {
+        for block_y in 0..scale {
+            for block_x in 0..scale {
+                let pixel_x = x + text_x * scale + block_x;
+                let pixel_y = y + text_y * scale + block_y;
+                if pixel_x < width && pixel_y < height && pixel_y * width + pixel_x < pixels.len() {
+                    pixels[pixel_y * width + pixel_x] = color;
+                }
+            }
+        }
+    };
+    for box_y in 0..box_height {
+        for box_x in 0..box_width {
+            set_pixel(box_x, box_y, BACKGROUND_COLOR);
+        }
+    }
+    for (index, character) in text.chars().enumerate() {
+        let glyph_x = 1 + index * (GLYPH_WIDTH + 1);
+        for (row, bits) in glyph(character).iter().enumerate() {
+            for column in 0..GLYPH_WIDTH {
+                if bits & (0b100 >> column) != 0 {
+                    set_pixel(glyph_x + column, 1 + row, TEXT_COLOR);
+                }
+            }
+        }
+    }
+}
+
+// Where the FPS is shown (video_fps_display)
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FpsDisplay {
+    Title,
+    Overlay,
+    Off,
+}
+
+impl FpsDisplay {
+    pub fn from_name(name: &str) -> Option<FpsDisplay> {
+        match name.to_ascii_lowercase().as_str() {
+            "title" => Some(FpsDisplay::Title),
+            "overlay" => Some(FpsDisplay::Overlay),
+            "off" => Some(FpsDisplay::Off),
+            _ => None,
+        }
+    }
+}
+
+// What else happens when a save state fails to load besides the message (savestate_load_fail_alert), so
+// it isn't missed in fullscreen. Beep rings the terminal bell.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LoadFailAlert {
+    None,
+    Beep,
+    Flash,
+}
+
+impl LoadFailAlert {
+    pub fn from_name(name: &str) -> Option<LoadFailAlert> {
+        match name.to_ascii_lowercase().as_str() {
+            "none" => Some(LoadFailAlert::None),
+            "beep" => Some(LoadFailAlert::Beep),
+            "flash" => Some(LoadFailAlert::Flash),
+            _ => None,
+        }
+    }
+}
+
+// Inverts every pixel of the frame, shown for a few frames as a flash
+pub fn invert_colors(pixels: &mut [u32]) {
+    for pixel in pixels {
+        *pixel ^= 0x00FFFFFF;
+    }
+}
+
+// A message shown over the frame for a while after something changes, e.g. a hotkey switching the filter
+#[derive(Default)]
+pub struct OsdMessage {
+    text: String,
+    shown_until: Option<Instant>,
+}
+
+impl OsdMessage {
+    pub fn show(&mut self, text: &str, duration: Duration) {
+        self.text.clear();
+        self.text.push_str(text);
+        self.shown_until = Some(Instant::now() + duration);
+    }
+
+    // The message while it's still being shown
+    pub fn current(&self) -> Option<&str> {
+        match self.shown_until {
+            Some(shown_until) if Instant::now() < shown_until => Some(&self.text),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,139 @@
+use libretro_sys::MemoryDescriptor;
+use std::fs;
+use std::path::Path;
+
+// A single line of a {rom_name}.watch file, e.g. `0xC0A0 2 le Player Score`
+#[derive(Clone, Debug, PartialEq)]
+pub struct WatchEntry {
+    pub address: usize,
+    pub size: usize, // 1, 2 or 4 bytes
+    pub big_endian: bool,
+    pub label: String,
+}
+
+fn parse_address(address: &str) -> Result<usize, String> {
+    let result = match address.strip_prefix("0x").or_else(|| address.strip_prefix("0X")) {
+        Some(hex) => usize::from_str_radix(hex, 16),
+        None => address.parse::<usize>(),
+    };
+    result.map_err(|e| format!("Invalid address '{}': {}", address, e))
+}
+
+// Format: <address> <size> [le|be] <label>, blank lines and lines starting with # are ignored
+pub fn parse_watch_line(line: &str) -> Result<Option<WatchEntry>, String> {
+    let line = line.trim();
+    if line.is_empty() || line.starts_with('#') {
+        return Ok(None);
+    }
+    let mut parts = line.split_whitespace().peekable();
+    let address = parse_address(parts.next().unwrap())?;
+    let size = match parts.next() {
+        Some("1") => 1,
+        Some("2") => 2,
+        Some("4") => 4,
+        Some(other) => return Err(format!("Invalid size '{}', expected 1, 2 or 4", other)),
+        None => return Err(format!("Missing size for address 0x{:X}", address)),
+    };
+    let big_endian = match parts.peek() {
+        Some(&"be") => {
+            parts.next();
+            true
+        }
+        Some(&"le") => {
+            parts.next();
+            false
+        }
+        _ => false,
+    };
+    let label = parts.collect::<Vec<&str>>().join(" ");
+    let label = if label.is_empty() {
+        format!("0x{:X}", address)
+    } else {
+        label
+    };
+    Ok(Some(WatchEntry {
+        address,
+        size,
+        big_endian,
+        label,
+    }))
+}
+
+pub fn parse_watch_file(path: &Path) -> Result<Vec<WatchEntry>, String> {
+    let contents =
+        fs::read_to_string(path).map_err(|e| format!("Failed to read watch file: {}", e))?;
+    let mut entries = Vec::new();
+    for (line_number, line) in contents.lines().enumerate() {
+        match parse_watch_line(line) {
+            Ok(Some(entry)) => entries.push(entry),
+            Ok(None) => {}
+            Err(err) => println!("Skipping line {} of {}: {}", line_number + 1, path.display(), err),
+        }
+    }
+    Ok(entries)
+}
+
+// Removes the bits set in `disconnect` from the address, the same way RetroArch does for memory maps
+fn reduce_address(mut address: usize, mut disconnect: usize) -> usize {
+    while disconnect != 0 {
+        let tmp = (disconnect - 1) & !disconnect;
+        address = (address & tmp) | ((address >> 1) & !tmp);
+        disconnect = (disconnect & (disconnect - 1)) >> 1;
+    }
+    address
+}
+
+// Find the host pointer for an emulated address using the descriptors from ENVIRONMENT_SET_MEMORY_MAPS
+pub fn resolve_address(
+    descriptors: &[MemoryDescriptor],
+    address: usize,
+    size: usize,
+) -> Option<*const u8> {
+    for descriptor in descriptors {
+        if descriptor.ptr.is_null() {
+            continue;
+        }
+        let matches = if descriptor.select != 0 {
+            ((descriptor.start ^ address) & descriptor.select) == 0
+        } else {
+            address >= descriptor.start && address < descriptor.start + descriptor.len
+        };
+        if !matches || address < descriptor.start {
+            continue;
+        }
+        let offset = reduce_address(address - descriptor.start, descriptor.disconnect);
+        if descriptor.len != 0 && offset + size > descriptor.len {
+            continue;
+        }
+        return Some(unsafe {
+            (descriptor.ptr as *const u8).add(descriptor.offset + offset)
+        });
+    }
+    None
+}
+
+pub fn decode_value(bytes: &[u8], big_endian: bool) -> u32 {
+    let accumulate = |value: u32, byte: &u8| (value << 8) | *byte as u32;
+    if big_endian {
+        bytes.iter().fold(0, accumulate)
+    } else {
+        bytes.iter().rev().fold(0, accumulate)
+    }
+}
+
+// Reads the current value of a watch, falling back to the flat system RAM when the core has no memory map
+/// # Safety
+/// The descriptors must come from the currently loaded core so their pointers are still valid.
+pub unsafe fn read_watch_value(
+    entry: &WatchEntry,
+    descriptors: &[MemoryDescriptor],
+    system_ram: &[u8],
+) -> Option<u32> {
+    let bytes = if descriptors.is_empty() {
+        system_ram.get(entry.address..entry.address + entry.size)?
+    } else {
+        let ptr = resolve_address(descriptors, entry.address, entry.size)?;
+        std::slice::from_raw_parts(ptr, entry.size)
+    };
+    Some(decode_value(bytes, entry.big_endian))
+}
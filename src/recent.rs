@@ -0,0 +1,39 @@
+use crate::config::parse_retroarch_config;
+use crate::save_state::write_file_atomically;
+use std::path::Path;
+
+// Next to ./rustroarch.cfg and in the same key = "value" format, rewritten every time a game is launched
+pub const LAST_GAME_FILE: &str = "./rustroarch_last_game.cfg";
+
+// The core and ROM --continue launches again
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LastGame {
+    pub core_path: String,
+    pub rom_path: String,
+}
+
+pub fn save_last_game(path: &Path, last_game: &LastGame) -> Result<(), String> {
+    let contents = format!(
+        "core_path = \"{}\"\nrom_path = \"{}\"\n",
+        last_game.core_path, last_game.rom_path
+    );
+    write_file_atomically(path, contents.as_bytes())
+}
+
+// None when no game has been launched yet
+pub fn load_last_game(path: &Path) -> Result<Option<LastGame>, String> {
+    if !path.exists() {
+        return Ok(None);
+    }
+    let values = parse_retroarch_config(path)
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    match (values.get("core_path"), values.get("rom_path")) {
+        (Some(core_path), Some(rom_path)) if !core_path.is_empty() && !rom_path.is_empty() => {
+            Ok(Some(LastGame {
+                core_path: core_path.clone(),
+                rom_path: rom_path.clone(),
+            }))
+        }
+        _ => Err(format!("{} doesn't name a core and a ROM", path.display())),
+    }
+}
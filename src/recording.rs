@@ -0,0 +1,255 @@
+use crate::video_capture::{VideoRecorder, VIDEO_HEADER_SIZE};
+use std::fs::File;
+use std::io::{BufWriter, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+const WAV_HEADER_SIZE: u32 = 44;
+const AUDIO_CHANNELS: u16 = 2;
+
+// 16 bit stereo PCM WAV file, the sizes in the header are filled in by finish once they're known
+pub struct WavWriter {
+    writer: BufWriter<File>,
+    sample_rate: u32,
+    samples_written: u64,
+}
+
+impl WavWriter {
+    pub fn create(path: &Path, sample_rate: u32) -> Result<WavWriter, String> {
+        let file = File::create(path).map_err(|e| format!("Failed to create audio file: {}", e))?;
+        let mut wav_writer = WavWriter {
+            writer: BufWriter::new(file),
+            sample_rate,
+            samples_written: 0,
+        };
+        wav_writer.write_header()?;
+        Ok(wav_writer)
+    }
+
+    fn write_header(&mut self) -> Result<(), String> {
+        let data_size =
+            (self.samples_written * 2).min(u32::MAX as u64 - WAV_HEADER_SIZE as u64) as u32;
+        let block_align = AUDIO_CHANNELS * 2;
+        let mut header = Vec::with_capacity(WAV_HEADER_SIZE as usize);
+        header.extend_from_slice(b"RIFF");
+        header.extend_from_slice(&(WAV_HEADER_SIZE - 8 + data_size).to_le_bytes());
+        header.extend_from_slice(b"WAVEfmt ");
+        header.extend_from_slice(&16u32.to_le_bytes());
+        header.extend_from_slice(&1u16.to_le_bytes()); // PCM
+        header.extend_from_slice(&AUDIO_CHANNELS.to_le_bytes());
+        header.extend_from_slice(&self.sample_rate.to_le_bytes());
+        header.extend_from_slice(&(self.sample_rate * block_align as u32).to_le_bytes());
+        header.extend_from_slice(&block_align.to_le_bytes());
+        header.extend_from_slice(&16u16.to_le_bytes());
+        header.extend_from_slice(b"data");
+        header.extend_from_slice(&data_size.to_le_bytes());
+        self.writer
+            .write_all(&header)
+            .map_err(|e| format!("Failed to write audio header: {}", e))
+    }
+
+    // Interleaved left and right samples
+    pub fn write_samples(&mut self, samples: &[i16]) -> Result<(), String> {
+        let bytes: Vec<u8> = samples
+            .iter()
+            .flat_map(|sample| sample.to_le_bytes())
+            .collect();
+        self.writer
+            .write_all(&bytes)
+            .map_err(|e| format!("Failed to write audio: {}", e))?;
+        self.samples_written += samples.len() as u64;
+        Ok(())
+    }
+
+    // Number of stereo frames written
+    pub fn frames_written(&self) -> u64 {
+        self.samples_written / AUDIO_CHANNELS as u64
+    }
+
+    pub fn finish(mut self) -> Result<u64, String> {
+        self.writer
+            .seek(SeekFrom::Start(0))
+            .map_err(|e| format!("Failed to update audio header: {}", e))?;
+        self.write_header()?;
+        self.writer
+            .flush()
+            .map_err(|e| format!("Failed to flush audio file: {}", e))?;
+        Ok(self.frames_written())
+    }
+}
+
+// --dump-audio, the core's samples exactly as they arrived as headerless s16le stereo. There's nothing in
+// the file saying the sample rate so it's logged, and again if the core changes it part way through.
+pub struct RawAudioDump {
+    writer: BufWriter<File>,
+    path: PathBuf,
+    sample_rate: Option<u32>,
+    samples_written: u64,
+}
+
+impl RawAudioDump {
+    pub fn create(path: &Path) -> Result<RawAudioDump, String> {
+        let file = File::create(path).map_err(|e| format!("Failed to create audio dump: {}", e))?;
+        Ok(RawAudioDump {
+            writer: BufWriter::new(file),
+            path: path.to_path_buf(),
+            sample_rate: None,
+            samples_written: 0,
+        })
+    }
+
+    // e.g. ffplay -f s16le -ar 32768 -ch_layout stereo audio.raw
+    pub fn ffplay_command(&self, sample_rate: u32) -> String {
+        format!(
+            "ffplay -f s16le -ar {} -ch_layout stereo {}",
+            sample_rate,
+            self.path.display()
+        )
+    }
+
+    // Interleaved left and right samples
+    pub fn write_samples(&mut self, samples: &[i16], sample_rate: u32) -> Result<(), String> {
+        if self.sample_rate != Some(sample_rate) {
+            match self.sample_rate {
+                None => println!(
+                    "Dumping audio at {} Hz, play it with: {}",
+                    sample_rate,
+                    self.ffplay_command(sample_rate)
+                ),
+                Some(_) => println!(
+                    "Sample rate changed to {} Hz after {} frames of audio, the rest of the dump plays at that rate",
+                    sample_rate,
+                    self.frames_written()
+                ),
+            }
+            self.sample_rate = Some(sample_rate);
+        }
+        let bytes: Vec<u8> = samples
+            .iter()
+            .flat_map(|sample| sample.to_le_bytes())
+            .collect();
+        self.writer
+            .write_all(&bytes)
+            .map_err(|e| format!("Failed to write audio dump: {}", e))?;
+        self.samples_written += samples.len() as u64;
+        Ok(())
+    }
+
+    // Number of stereo frames written
+    pub fn frames_written(&self) -> u64 {
+        self.samples_written / AUDIO_CHANNELS as u64
+    }
+
+    pub fn finish(mut self) -> Result<u64, String> {
+        self.writer
+            .flush()
+            .map_err(|e| format!("Failed to flush audio dump: {}", e))?;
+        Ok(self.frames_written())
+    }
+}
+
+// How long each half of a finished recording is, they should match to within a frame or two
+#[derive(Clone, Debug, PartialEq)]
+pub struct RecordingSummary {
+    pub video_frames: u64,
+    pub fps: f64,
+    pub audio_frames: u64,
+    pub sample_rate: u32,
+}
+
+impl RecordingSummary {
+    pub fn video_seconds(&self) -> f64 {
+        self.video_frames as f64 / self.fps
+    }
+
+    pub fn audio_seconds(&self) -> f64 {
+        self.audio_frames as f64 / self.sample_rate as f64
+    }
+
+    pub fn is_in_sync(&self) -> bool {
+        (self.video_seconds() - self.audio_seconds()).abs() <= 2.0 / self.fps
+    }
+}
+
+// Records the video to {name}.raw (see video_capture.rs) and the audio to {name}.wav so ffmpeg can mux them.
+// Every frame is tagged with the core's frame counter: the video gets one frame for each frame the core
+// ran, repeating the shown frame when several were run at once (fast forward) or one couldn't be shown,
+// and nothing while paused. The audio is every sample the core produced, so both line up from the start.
+pub struct AvRecorder {
+    video: VideoRecorder,
+    audio: WavWriter,
+    video_path: PathBuf,
+    audio_path: PathBuf,
+    last_frame_count: u64,
+    video_frames_owed: u64, // frames run since the last one that could be shown
+}
+
+impl AvRecorder {
+    pub fn create(
+        path: &Path,
+        fps: f64,
+        sample_rate: u32,
+        frame_count: u64,
+    ) -> Result<AvRecorder, String> {
+        let video_path = path.with_extension("raw");
+        let audio_path = path.with_extension("wav");
+        Ok(AvRecorder {
+            video: VideoRecorder::create(&video_path, fps)?,
+            audio: WavWriter::create(&audio_path, sample_rate)?,
+            video_path,
+            audio_path,
+            last_frame_count: frame_count,
+            video_frames_owed: 0,
+        })
+    }
+
+    // `frame` is the frame shown (pixels, width, height) if there is one and `samples` the audio from every
+    // frame run since the last call
+    pub fn record(
+        &mut self,
+        frame_count: u64,
+        frame: Option<(&[u32], usize, usize)>,
+        samples: &[i16],
+    ) -> Result<(), String> {
+        // The counter goes back to 0 when the core is reset
+        let frames_run = frame_count.checked_sub(self.last_frame_count).unwrap_or(frame_count);
+        self.last_frame_count = frame_count;
+        if frames_run == 0 {
+            return Ok(());
+        }
+        self.audio.write_samples(samples)?;
+        self.video_frames_owed += frames_run;
+        if let Some((pixels, width, height)) = frame {
+            for _ in 0..self.video_frames_owed {
+                self.video.record_frame(pixels, width, height)?;
+            }
+            self.video_frames_owed = 0;
+        }
+        Ok(())
+    }
+
+    // The ffmpeg command that muxes the two files, None if no frames were recorded
+    pub fn ffmpeg_command(&self, output: &str) -> Option<String> {
+        let (width, height) = self.video.size()?;
+        Some(format!(
+            "ffmpeg -f rawvideo -pixel_format bgr0 -video_size {}x{} -framerate {} -skip_initial_bytes {} -i {} -i {} -c:v libx264 -pix_fmt yuv420p -c:a aac {}",
+            width,
+            height,
+            self.video.fps(),
+            VIDEO_HEADER_SIZE,
+            self.video_path.display(),
+            self.audio_path.display(),
+            output
+        ))
+    }
+
+    pub fn finish(self) -> Result<RecordingSummary, String> {
+        let fps = self.video.fps();
+        let sample_rate = self.audio.sample_rate;
+        Ok(RecordingSummary {
+            video_frames: self.video.finish()?,
+            fps,
+            audio_frames: self.audio.finish()?,
+            sample_rate,
+        })
+    }
+}
@@ -0,0 +1,54 @@
+// The video standard the loaded content was made for. The core reports it with retro_get_region
+// (RETRO_REGION_NTSC is 0 and RETRO_REGION_PAL 1, libretro_sys doesn't have them) unless force_region is set.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Region {
+    Ntsc,
+    Pal,
+}
+
+impl Region {
+    pub fn from_name(name: &str) -> Option<Region> {
+        match name.to_ascii_lowercase().as_str() {
+            "ntsc" => Some(Region::Ntsc),
+            "pal" => Some(Region::Pal),
+            _ => None,
+        }
+    }
+
+    pub fn from_retro_region(region: u32) -> Option<Region> {
+        match region {
+            0 => Some(Region::Ntsc),
+            1 => Some(Region::Pal),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Region::Ntsc => "NTSC",
+            Region::Pal => "PAL",
+        }
+    }
+
+    // What a TV of this region refreshes at, used when the core's own fps can't be
+    pub fn fps(self) -> f64 {
+        match self {
+            Region::Ntsc => 60.0,
+            Region::Pal => 50.0,
+        }
+    }
+}
+
+// Some cores report 0 (or nonsense) fps, which can't be used to pace frames
+pub fn is_usable_fps(fps: f64) -> bool {
+    fps.is_finite() && (1.0..=1000.0).contains(&fps)
+}
+
+// The fps frames are paced at, the core's when it's usable otherwise the region's
+pub fn pacing_fps(core_fps: f64, region: Region) -> f64 {
+    if is_usable_fps(core_fps) {
+        core_fps
+    } else {
+        region.fps()
+    }
+}
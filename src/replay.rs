@@ -0,0 +1,137 @@
+use std::fs::File;
+use std::io::{BufWriter, Read, Write};
+use std::path::Path;
+
+// Replay file layout (all integers little-endian):
+//   "RRIN" magic, u8 version, u32 save state length, save state bytes,
+//   then a list of runs: u32 number of frames, u16 joypad button mask held for those frames.
+// Only changes in input are written so idle stretches cost 6 bytes no matter how long they are.
+// Version 1 replays were recorded when the input gathered in a frame was only seen by the core on the next
+// frame, so they're played back with an extra frame of no input at the start.
+const REPLAY_MAGIC: &[u8; 4] = b"RRIN";
+const REPLAY_VERSION: u8 = 2;
+
+pub fn buttons_to_mask(buttons: &[i16]) -> u16 {
+    buttons
+        .iter()
+        .take(16)
+        .enumerate()
+        .fold(0, |mask, (id, pressed)| if *pressed != 0 { mask | (1 << id) } else { mask })
+}
+
+pub fn mask_to_buttons(mask: u16) -> Vec<i16> {
+    (0..16).map(|id| ((mask >> id) & 1) as i16).collect()
+}
+
+pub struct InputRecorder {
+    writer: BufWriter<File>,
+    current_mask: u16,
+    run_length: u32,
+}
+
+impl InputRecorder {
+    pub fn create(path: &Path, initial_state: &[u8]) -> Result<InputRecorder, String> {
+        let file = File::create(path).map_err(|e| format!("Failed to create replay file: {}", e))?;
+        let mut writer = BufWriter::new(file);
+        let header_result = writer
+            .write_all(REPLAY_MAGIC)
+            .and_then(|_| writer.write_all(&[REPLAY_VERSION]))
+            .and_then(|_| writer.write_all(&(initial_state.len() as u32).to_le_bytes()))
+            .and_then(|_| writer.write_all(initial_state));
+        header_result.map_err(|e| format!("Failed to write replay header: {}", e))?;
+        Ok(InputRecorder {
+            writer,
+            current_mask: 0,
+            run_length: 0,
+        })
+    }
+
+    fn write_run(&mut self) -> Result<(), String> {
+        if self.run_length == 0 {
+            return Ok(());
+        }
+        self.writer
+            .write_all(&self.run_length.to_le_bytes())
+            .and_then(|_| self.writer.write_all(&self.current_mask.to_le_bytes()))
+            .map_err(|e| format!("Failed to write replay input: {}", e))
+    }
+
+    pub fn record_frame(&mut self, mask: u16) -> Result<(), String> {
+        if mask != self.current_mask || self.run_length == u32::MAX {
+            self.write_run()?;
+            self.current_mask = mask;
+            self.run_length = 0;
+        }
+        self.run_length += 1;
+        Ok(())
+    }
+
+    pub fn finish(mut self) -> Result<(), String> {
+        self.write_run()?;
+        self.writer
+            .flush()
+            .map_err(|e| format!("Failed to flush replay file: {}", e))
+    }
+}
+
+pub struct InputPlayback {
+    pub initial_state: Vec<u8>,
+    runs: Vec<(u32, u16)>,
+    run_index: usize,
+    frames_into_run: u32,
+}
+
+impl InputPlayback {
+    pub fn open(path: &Path) -> Result<InputPlayback, String> {
+        let mut contents = Vec::new();
+        File::open(path)
+            .and_then(|mut file| file.read_to_end(&mut contents))
+            .map_err(|e| format!("Failed to read replay file: {}", e))?;
+        InputPlayback::parse(&contents)
+    }
+
+    pub fn parse(contents: &[u8]) -> Result<InputPlayback, String> {
+        if contents.len() < 9 || &contents[0..4] != REPLAY_MAGIC {
+            return Err("Not a RustroArch replay file".to_string());
+        }
+        let version = contents[4];
+        if version != 1 && version != REPLAY_VERSION {
+            return Err(format!("Unsupported replay version: {}", version));
+        }
+        let state_length = u32::from_le_bytes(contents[5..9].try_into().unwrap()) as usize;
+        let runs_start = 9 + state_length;
+        if contents.len() < runs_start {
+            return Err("Replay file is truncated".to_string());
+        }
+        let mut runs: Vec<(u32, u16)> = if version == 1 { vec![(1, 0)] } else { Vec::new() };
+        runs.extend(contents[runs_start..].chunks_exact(6).map(|run| {
+            (
+                u32::from_le_bytes(run[0..4].try_into().unwrap()),
+                u16::from_le_bytes(run[4..6].try_into().unwrap()),
+            )
+        }));
+        Ok(InputPlayback {
+            initial_state: contents[9..runs_start].to_vec(),
+            runs,
+            run_index: 0,
+            frames_into_run: 0,
+        })
+    }
+
+    pub fn total_frames(&self) -> u64 {
+        self.runs.iter().map(|(frames, _)| *frames as u64).sum()
+    }
+
+    // Returns the button mask for the next frame or None once the recording has ended
+    pub fn next_frame(&mut self) -> Option<u16> {
+        while let Some((frames, mask)) = self.runs.get(self.run_index) {
+            if self.frames_into_run < *frames {
+                self.frames_into_run += 1;
+                return Some(*mask);
+            }
+            self.run_index += 1;
+            self.frames_into_run = 0;
+        }
+        None
+    }
+}
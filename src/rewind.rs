@@ -0,0 +1,76 @@
+use crate::save_state::{decode_state, encode_state, StateCompression};
+use std::collections::VecDeque;
+
+// Snapshots of the core's state to step back through, newest last. Only every `granularity`th frame is
+// kept, each is compressed the same way as save states, and the oldest are dropped once together they
+// take more than max_bytes however few frames that covers.
+pub struct RewindBuffer {
+    snapshots: VecDeque<Vec<u8>>,
+    granularity: u32,
+    max_bytes: usize,
+    compression: StateCompression,
+    used_bytes: usize,
+    frames_until_snapshot: u32,
+}
+
+impl RewindBuffer {
+    pub fn new(granularity: u32, max_bytes: usize, compression: StateCompression) -> RewindBuffer {
+        RewindBuffer {
+            snapshots: VecDeque::new(),
+            granularity: granularity.max(1),
+            max_bytes,
+            compression,
+            used_bytes: 0,
+            frames_until_snapshot: 0,
+        }
+    }
+
+    // Called once a frame, the core is only serialized on the frames a snapshot is taken
+    pub fn record(&mut self, serialize: impl FnOnce() -> Vec<u8>) -> Result<(), String> {
+        if self.frames_until_snapshot > 0 {
+            self.frames_until_snapshot -= 1;
+            return Ok(());
+        }
+        self.frames_until_snapshot = self.granularity - 1;
+        let snapshot = encode_state(&serialize(), self.compression)?;
+        self.used_bytes += snapshot.len();
+        self.snapshots.push_back(snapshot);
+        while self.used_bytes > self.max_bytes {
+            match self.snapshots.pop_front() {
+                Some(oldest) => self.used_bytes -= oldest.len(),
+                None => break,
+            }
+        }
+        if self.snapshots.is_empty() {
+            return Err(format!(
+                "A rewind snapshot doesn't fit in {} bytes, nothing can be rewound",
+                self.max_bytes
+            ));
+        }
+        Ok(())
+    }
+
+    // The newest snapshot, taken out of the buffer. None once there's nothing left to rewind.
+    pub fn pop(&mut self) -> Result<Option<Vec<u8>>, String> {
+        let Some(snapshot) = self.snapshots.pop_back() else {
+            return Ok(None);
+        };
+        self.used_bytes -= snapshot.len();
+        // Recording starts again from the state that was rewound to
+        self.frames_until_snapshot = 0;
+        decode_state(&snapshot).map(Some)
+    }
+
+    pub fn len(&self) -> usize {
+        self.snapshots.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.snapshots.is_empty()
+    }
+
+    // What the snapshots take up as stored, compressed or not
+    pub fn used_bytes(&self) -> usize {
+        self.used_bytes
+    }
+}
@@ -0,0 +1,314 @@
+use crate::config::expand_path;
+use libretro_sys::GameInfo;
+use memmap2::Mmap;
+use std::collections::HashMap;
+use std::ffi::{c_void, CString};
+use std::fs::{self, File};
+use std::io::{BufRead, BufReader, Read};
+use std::path::{Path, PathBuf};
+use std::ptr;
+
+// The ROM data handed to the core in GameInfo.data, it has to stay alive until the game is unloaded
+pub(crate) enum RomData {
+    Read(Vec<u8>),
+    Mapped(Mmap),
+    NotLoaded, // cores that load from the path (need_fullpath) read the file themselves
+}
+
+impl RomData {
+    pub(crate) fn as_slice(&self) -> &[u8] {
+        match self {
+            RomData::Read(contents) => contents,
+            RomData::Mapped(mapping) => mapping,
+            RomData::NotLoaded => &[],
+        }
+    }
+}
+
+// A ROM handed to the core, the path, meta and data its GameInfo points into have to stay alive until the
+// game is unloaded
+pub(crate) struct LoadedContent {
+    pub path: CString,
+    pub meta: Option<CString>,
+    pub data: RomData,
+    pub crc32: u32,
+}
+
+impl LoadedContent {
+    // The ROM's CRC32 is logged so the dump can be checked against No-Intro and Redump
+    pub(crate) fn load(rom_path: &str, options: &RomLoadOptions) -> Result<LoadedContent, String> {
+        let content = match is_url(rom_path) {
+            true => LoadedContent::download(rom_path, options)?,
+            false => {
+                // max_rom_size_mb doesn't apply to need_fullpath cores as the file is never held in memory
+                let (data, crc32) = match options.need_fullpath {
+                    true => (RomData::NotLoaded, file_crc32(rom_path)?),
+                    false => {
+                        let data = read_rom(rom_path, options)?;
+                        let crc32 = content_crc32(data.as_slice());
+                        (data, crc32)
+                    }
+                };
+                LoadedContent {
+                    path: CString::new(rom_path).map_err(|e| format!("Invalid ROM path {}: {}", rom_path, e))?,
+                    meta: game_info_meta(rom_path, options.meta.as_deref())?,
+                    crc32,
+                    data,
+                }
+            }
+        };
+        println!("Content CRC32: {:08X}", content.crc32);
+        Ok(content)
+    }
+
+    // A ROM from a URL is kept in memory with the URL's file name as its path, so the core can still go by
+    // the extension. Cores that only load from a path (need_fullpath) get it saved to a temporary file.
+    fn download(url: &str, options: &RomLoadOptions) -> Result<LoadedContent, String> {
+        let contents = download_rom(url, options.max_size_mb)?;
+        let mut path = url_file_name(url);
+        if options.need_fullpath {
+            let directory = std::env::temp_dir().join("rustro_arch_downloads");
+            let file_path = directory.join(&path);
+            fs::create_dir_all(&directory)
+                .and_then(|_| fs::write(&file_path, &contents))
+                .map_err(|e| format!("Failed to save the download to {}: {}", file_path.display(), e))?;
+            println!("The core loads ROMs from a path, saved the download to {}", file_path.display());
+            path = file_path.to_string_lossy().into_owned();
+        }
+        Ok(LoadedContent {
+            path: CString::new(path.as_str()).map_err(|e| format!("Invalid ROM path {}: {}", path, e))?,
+            meta: game_info_meta(&path, options.meta.as_deref())?,
+            crc32: content_crc32(&contents),
+            data: RomData::Read(contents),
+        })
+    }
+
+    pub(crate) fn game_info(&self) -> GameInfo {
+        let data = match self.data {
+            RomData::NotLoaded => ptr::null(),
+            _ => self.data.as_slice().as_ptr() as *const c_void,
+        };
+        GameInfo {
+            path: self.path.as_ptr(),
+            data,
+            size: self.data.as_slice().len(),
+            meta: self.meta.as_ref().map_or(ptr::null(), |meta| meta.as_ptr()),
+        }
+    }
+}
+
+// The CRC32 of the whole ROM as No-Intro and Redump list it, worked out over the data handed to the core.
+// A mapped file is paged in as it's hashed rather than copied.
+pub(crate) fn content_crc32(data: &[u8]) -> u32 {
+    crc32fast::hash(data)
+}
+
+// The CRC32 of a ROM the core loads from the path (need_fullpath) itself. The file is streamed through a
+// small buffer so a disc image is never held in memory.
+fn file_crc32(rom_path: &str) -> Result<u32, String> {
+    let file = File::open(rom_path)
+        .map_err(|e| format!("Failed to open ROM file {}: {}", rom_path, e))?;
+    let mut reader = BufReader::with_capacity(64 * 1024, file);
+    let mut hasher = crc32fast::Hasher::new();
+    loop {
+        let block = reader
+            .fill_buf()
+            .map_err(|e| format!("Failed to read ROM file {}: {}", rom_path, e))?;
+        if block.is_empty() {
+            return Ok(hasher.finalize());
+        }
+        hasher.update(block);
+        let read = block.len();
+        reader.consume(read);
+    }
+}
+
+// GameInfo.meta for a ROM, `meta` (game_info_meta or --meta) if it's set, otherwise the contents of
+// {rom}.meta next to the ROM if there is one. Few cores read it so it's usually null.
+pub fn game_info_meta(rom_path: &str, meta: Option<&str>) -> Result<Option<CString>, String> {
+    let meta = match meta {
+        Some(meta) => meta.to_string(),
+        None => {
+            let meta_path = format!("{}.meta", rom_path);
+            match fs::read_to_string(&meta_path) {
+                Ok(contents) => {
+                    println!("Using the GameInfo meta in {}", meta_path);
+                    contents.trim_end().to_string()
+                }
+                Err(_) => return Ok(None),
+            }
+        }
+    };
+    CString::new(meta)
+        .map(Some)
+        .map_err(|e| format!("Invalid GameInfo meta for {}: {}", rom_path, e))
+}
+
+// rom_use_mmap, max_rom_size_mb and game_info_meta from the config
+pub(crate) struct RomLoadOptions {
+    pub use_mmap: bool,
+    pub max_size_mb: u64,     // 0 means no limit
+    pub meta: Option<String>, // None to look for a {rom}.meta file
+    pub need_fullpath: bool,  // from the core's system info, local ROMs aren't loaded and downloads are saved to a file
+}
+
+impl RomLoadOptions {
+    pub(crate) fn from_config(config: &HashMap<String, String>) -> RomLoadOptions {
+        RomLoadOptions {
+            use_mmap: config["rom_use_mmap"] == "true",
+            max_size_mb: crate::config::get_max_rom_size_mb(config),
+            meta: config
+                .get("game_info_meta")
+                .filter(|meta| !meta.is_empty())
+                .cloned(),
+            need_fullpath: false,
+        }
+    }
+}
+
+// Memory mapping lets the OS page in a large disc image as the core reads it instead of copying it all up
+// front. The size limit only applies to reading as a mapping doesn't use up memory.
+pub(crate) fn read_rom(rom_path: &str, options: &RomLoadOptions) -> Result<RomData, String> {
+    if options.use_mmap {
+        let file = File::open(rom_path)
+            .map_err(|e| format!("Failed to open ROM file {}: {}", rom_path, e))?;
+        // The mapping is read only, if the file is changed while the game is running the core sees the change
+        let mapping = unsafe { Mmap::map(&file) }
+            .map_err(|e| format!("Failed to memory map ROM file {}: {}", rom_path, e))?;
+        return Ok(RomData::Mapped(mapping));
+    }
+    let size = fs::metadata(rom_path)
+        .map_err(|e| format!("Failed to read ROM file {}: {}", rom_path, e))?
+        .len();
+    if options.max_size_mb > 0 && size > options.max_size_mb * 1024 * 1024 {
+        return Err(format!(
+            "ROM file {} is {} MB which is over max_rom_size_mb ({} MB), set rom_use_mmap = true or use a core that loads from the path (need_fullpath)",
+            rom_path,
+            size / (1024 * 1024),
+            options.max_size_mb
+        ));
+    }
+    fs::read(rom_path)
+        .map(RomData::Read)
+        .map_err(|e| format!("Failed to read ROM file {}: {}", rom_path, e))
+}
+
+// http:// and https:// ROMs are downloaded rather than read from disk
+pub fn is_url(rom: &str) -> bool {
+    let rom = rom.to_ascii_lowercase();
+    rom.starts_with("http://") || rom.starts_with("https://")
+}
+
+// The last part of a URL's path without the query, e.g. game.gb for https://example.com/roms/game.gb?v=2
+pub fn url_file_name(url: &str) -> String {
+    let url = url.split(['?', '#']).next().unwrap_or(url);
+    url.splitn(4, '/')
+        .nth(3)
+        .and_then(|path| path.rsplit('/').next())
+        .filter(|name| !name.is_empty())
+        .unwrap_or("download")
+        .to_string()
+}
+
+// URLs are passed through as they are, anything else is a path to expand
+pub(crate) fn content_path(rom: &str) -> String {
+    match is_url(rom) {
+        true => rom.to_string(),
+        false => expand_path(rom).to_string_lossy().into_owned(),
+    }
+}
+
+// Downloads a ROM into memory, max_rom_size_mb (0 for no limit) applies as it would reading one from disk
+pub fn download_rom(url: &str, max_size_mb: u64) -> Result<Vec<u8>, String> {
+    println!("Downloading ROM: {}", url);
+    let response = ureq::get(url)
+        .call()
+        .map_err(|e| format!("Failed to download ROM {}: {}", url, e))?;
+    let limit = match max_size_mb {
+        0 => u64::MAX,
+        max_size_mb => max_size_mb * 1024 * 1024,
+    };
+    let mut contents = Vec::new();
+    response
+        .into_reader()
+        .take(limit.saturating_add(1))
+        .read_to_end(&mut contents)
+        .map_err(|e| format!("Failed to download ROM {}: {}", url, e))?;
+    if contents.len() as u64 > limit {
+        return Err(format!(
+            "ROM {} is over max_rom_size_mb ({} MB)",
+            url, max_size_mb
+        ));
+    }
+    println!("Downloaded {} bytes", contents.len());
+    Ok(contents)
+}
+
+// Lowercase letters and digits only, so "super mario land" matches "Super_Mario_Land (World).gb"
+fn fuzzy_name(name: &str) -> String {
+    name.chars()
+        .filter(|character| character.is_alphanumeric())
+        .flat_map(|character| character.to_lowercase())
+        .collect()
+}
+
+// Lets a ROM be given by just its file name (or a URL). Paths that exist as given (including absolute ones) are used
+// as they are, otherwise the name is looked up in roms_directory: first exactly, then ignoring case (with
+// or without the extension), then as the best fuzzy match, the shortest file name containing it.
+pub fn resolve_rom_path(rom: &str, roms_directory: &str) -> Result<PathBuf, String> {
+    if is_url(rom) {
+        return Ok(PathBuf::from(rom));
+    }
+    let rom_path = expand_path(rom);
+    if rom_path.is_absolute() || rom_path.exists() || roms_directory.is_empty() {
+        return Ok(rom_path);
+    }
+    let in_directory = Path::new(roms_directory).join(&rom_path);
+    if in_directory.exists() {
+        return Ok(in_directory);
+    }
+    let mut files: Vec<PathBuf> = fs::read_dir(roms_directory)
+        .map_err(|e| format!("Failed to read roms_directory {}: {}", roms_directory, e))?
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.is_file())
+        .collect();
+    files.sort();
+    let file_name = |path: &PathBuf| {
+        path.file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+    };
+    let file_stem = |path: &PathBuf| {
+        path.file_stem()
+            .map(|stem| stem.to_string_lossy().into_owned())
+    };
+    let ignoring_case = files.iter().find(|path| {
+        file_name(path).is_some_and(|name| name.eq_ignore_ascii_case(rom))
+            || file_stem(path).is_some_and(|stem| stem.eq_ignore_ascii_case(rom))
+    });
+    if let Some(path) = ignoring_case {
+        return Ok(path.clone());
+    }
+    let query = fuzzy_name(rom);
+    let closest = files
+        .iter()
+        .filter(|path| {
+            !query.is_empty()
+                && file_name(path).is_some_and(|name| fuzzy_name(&name).contains(&query))
+        })
+        .min_by_key(|path| file_name(path).map_or(usize::MAX, |name| name.len()));
+    match closest {
+        Some(path) => {
+            println!(
+                "Found no ROM called {}, using the closest match: {}",
+                rom,
+                path.display()
+            );
+            Ok(path.clone())
+        }
+        None => Err(format!(
+            "ROM {} wasn't found, it isn't in roms_directory {} either",
+            rom, roms_directory
+        )),
+    }
+}
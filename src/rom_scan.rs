@@ -0,0 +1,66 @@
+use crate::core_info::CoreInfo;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// --scan-roms, the files in the directory the core loads going by its valid_extensions (so everything for
+// a core that lists none), sorted by path. Hidden files and directories are left out.
+pub fn scan_roms(
+    directory: &Path,
+    core_info: &CoreInfo,
+    recursive: bool,
+) -> Result<Vec<PathBuf>, String> {
+    let mut roms = Vec::new();
+    let mut directories = vec![directory.to_path_buf()];
+    while let Some(directory) = directories.pop() {
+        let entries = fs::read_dir(&directory)
+            .map_err(|e| format!("Failed to read {}: {}", directory.display(), e))?;
+        for path in entries.flatten().map(|entry| entry.path()) {
+            if path
+                .file_name()
+                .is_some_and(|name| name.to_string_lossy().starts_with('.'))
+            {
+                continue;
+            }
+            if path.is_dir() {
+                if recursive {
+                    directories.push(path);
+                }
+            } else if core_info
+                .check_rom_extension(&path.to_string_lossy())
+                .is_ok()
+            {
+                roms.push(path);
+            }
+        }
+    }
+    roms.sort();
+    Ok(roms)
+}
+
+// A JSON array of the paths, for launchers reading --scan-roms --json
+pub fn roms_to_json(roms: &[PathBuf]) -> String {
+    let paths: Vec<String> = roms
+        .iter()
+        .map(|rom| json_string(&rom.to_string_lossy()))
+        .collect();
+    format!("[{}]", paths.join(","))
+}
+
+fn json_string(value: &str) -> String {
+    let mut json = String::from("\"");
+    for character in value.chars() {
+        match character {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            '\r' => json.push_str("\\r"),
+            '\t' => json.push_str("\\t"),
+            character if (character as u32) < 0x20 => {
+                json.push_str(&format!("\\u{:04x}", character as u32))
+            }
+            character => json.push(character),
+        }
+    }
+    json.push('"');
+    json
+}
@@ -0,0 +1,50 @@
+use gilrs::ff::{BaseEffect, BaseEffectType, Effect, EffectBuilder};
+use gilrs::{GamepadId, Gilrs};
+
+// The strong and weak motor strengths (0-0xFFFF) a core asked for through the rumble interface
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct RumbleState {
+    pub strong: u16,
+    pub weak: u16,
+}
+
+// Plays a core's rumble on a gamepad. The effect repeats until the core changes the strengths, dropping
+// the gilrs Effect stops it.
+#[derive(Default)]
+pub struct GamepadRumble {
+    effect: Option<Effect>,
+}
+
+impl GamepadRumble {
+    pub fn new() -> GamepadRumble {
+        GamepadRumble::default()
+    }
+
+    // Gamepads without force feedback are ignored
+    pub fn set(&mut self, gilrs: &mut Gilrs, gamepad_id: GamepadId, rumble: RumbleState) {
+        self.effect = None;
+        if rumble == RumbleState::default() || !gilrs.gamepad(gamepad_id).is_ff_supported() {
+            return;
+        }
+        let effect = EffectBuilder::new()
+            .add_effect(BaseEffect {
+                kind: BaseEffectType::Strong {
+                    magnitude: rumble.strong,
+                },
+                ..Default::default()
+            })
+            .add_effect(BaseEffect {
+                kind: BaseEffectType::Weak {
+                    magnitude: rumble.weak,
+                },
+                ..Default::default()
+            })
+            .gamepads(&[gamepad_id])
+            .finish(gilrs)
+            .and_then(|effect| effect.play().map(|_| effect));
+        match effect {
+            Ok(effect) => self.effect = Some(effect),
+            Err(err) => println!("Failed to rumble gamepad: {}", err),
+        }
+    }
+}
@@ -0,0 +1,218 @@
+use crate::core::LibretroCore;
+use std::fs::{self, File};
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+
+pub fn get_save_state_path(
+    save_directory: &String,
+    game_file_name: &str,
+    save_state_index: u8,
+) -> Option<PathBuf> {
+    // Create a subdirectory named "saves" in the current working directory
+    let saves_dir = PathBuf::from(save_directory);
+    if !saves_dir.exists() {
+        match std::fs::create_dir(&saves_dir) {
+            Ok(_) => {}
+            Err(err) => panic!(
+                "Failed to create save directory: {:?} Error: {}",
+                &saves_dir, err
+            ),
+        }
+    }
+
+    // Generate the save state filename
+    let game_name = Path::new(game_file_name)
+        .file_stem()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .replace(" ", "_");
+    let save_state_file_name = format!("{}_{}.state", game_name, save_state_index);
+
+    // Combine the saves directory and the save state filename to create the full path
+    let save_state_path = saves_dir.join(save_state_file_name);
+
+    Some(save_state_path)
+}
+
+// Writes to a temporary file next to the target and renames it over the target once it's all on disk, so
+// a crash or power cut part way through leaves the previous file as it was instead of half written
+pub fn write_file_atomically(path: &Path, contents: &[u8]) -> Result<(), String> {
+    let file_name = path
+        .file_name()
+        .ok_or(format!("Can't write to {}, it isn't a file", path.display()))?;
+    let temp_path = path.with_file_name(format!(".{}.tmp", file_name.to_string_lossy()));
+    let result = File::create(&temp_path)
+        .and_then(|mut file| {
+            file.write_all(contents)?;
+            file.sync_all()
+        })
+        .and_then(|_| fs::rename(&temp_path, path));
+    if let Err(err) = result {
+        let _ = fs::remove_file(&temp_path);
+        return Err(format!("Failed to write {}: {}", path.display(), err));
+    }
+    Ok(())
+}
+
+pub fn serialize_state(core: &impl LibretroCore) -> Vec<u8> {
+    let save_state_buffer_size = core.serialize_size();
+    let mut state_buffer: Vec<u8> = vec![0; save_state_buffer_size];
+    // Call retro_serialize to create the save state
+    core.serialize(&mut state_buffer);
+    state_buffer
+}
+
+// What Emulator::verify_save_state found
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StateVerification {
+    Passed,
+    FrameMismatch(u64), // the first frame drawn differently after loading the state
+    StateMismatch,      // every frame matched but the states saved at the end differ
+}
+
+// How save states are written to disk (savestate_compression), they're always read back either way
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StateCompression {
+    None,
+    Zstd,
+}
+
+// Compressed states start with this so they can be told apart from the raw states saved before
+// compression existed, which are just whatever the core serialized
+const ZSTD_STATE_MAGIC: &[u8; 4] = b"RSZS";
+
+pub fn encode_state(state: &[u8], compression: StateCompression) -> Result<Vec<u8>, String> {
+    match compression {
+        StateCompression::None => Ok(state.to_vec()),
+        StateCompression::Zstd => {
+            let compressed = zstd::encode_all(state, zstd::DEFAULT_COMPRESSION_LEVEL)
+                .map_err(|e| format!("Failed to compress save state: {}", e))?;
+            Ok([&ZSTD_STATE_MAGIC[..], &compressed].concat())
+        }
+    }
+}
+
+pub fn decode_state(contents: &[u8]) -> Result<Vec<u8>, String> {
+    match contents.strip_prefix(ZSTD_STATE_MAGIC) {
+        Some(compressed) => zstd::decode_all(compressed)
+            .map_err(|e| format!("Failed to decompress save state: {}", e)),
+        None => Ok(contents.to_vec()),
+    }
+}
+
+// Returns where the state was written, None when it couldn't be (and why is printed)
+pub fn save_state(
+    core: &impl LibretroCore,
+    save_directory: &String,
+    game_file_name: &str,
+    save_slot: u8,
+    compression: StateCompression,
+) -> Option<PathBuf> {
+    let state_buffer = serialize_state(core);
+    let save_state_buffer_size = state_buffer.len();
+    let file_contents = match encode_state(&state_buffer, compression) {
+        Ok(file_contents) => file_contents,
+        Err(err) => {
+            println!("{}", err);
+            return None;
+        }
+    };
+    let file_path = get_save_state_path(save_directory, game_file_name, save_slot).unwrap();
+    if let Err(err) = write_file_atomically(&file_path, &file_contents) {
+        println!("{}", err);
+        return None;
+    }
+    if compression == StateCompression::None {
+        println!(
+            "Save state saved to: {} with size: {}",
+            &file_path.display(),
+            save_state_buffer_size
+        );
+    } else {
+        println!(
+            "Save state saved to: {} with size: {} compressed from {}",
+            &file_path.display(),
+            file_contents.len(),
+            save_state_buffer_size
+        );
+    }
+    Some(file_path)
+}
+
+// The core is left as it was if the slot is empty or the state can't be loaded
+pub fn load_state(
+    core: &mut impl LibretroCore,
+    save_directory: &String,
+    game_file_name: &str,
+    save_slot: u8,
+) -> Result<(), String> {
+    let file_path = get_save_state_path(save_directory, game_file_name, save_slot).unwrap();
+    let mut file_contents = Vec::new();
+    let mut file = File::open(&file_path).map_err(|_| format!("Save state slot {} is empty", save_slot))?;
+    // Read the save state file into a buffer
+    file.read_to_end(&mut file_contents)
+        .map_err(|e| format!("Error reading save state file: {}", e))?;
+    let state_buffer = decode_state(&file_contents)?;
+    // Call retro_unserialize to apply the save state
+    if !core.unserialize(&state_buffer) {
+        return Err(format!("The core failed to load the save state in slot {}", save_slot));
+    }
+    println!("Save state loaded from: {}", &file_path.display());
+    Ok(())
+}
+
+// The occupied slot closest to an empty one for savestate_load_fallback, on a tie the lower slot
+pub fn nearest_occupied_slot(slot: u8, max_slots: u16, is_occupied: impl Fn(u8) -> bool) -> Option<u8> {
+    (1..max_slots).find_map(|distance| {
+        let lower = (slot as u16).checked_sub(distance);
+        let higher = Some(slot as u16 + distance).filter(|higher| *higher < max_slots);
+        [lower, higher]
+            .into_iter()
+            .flatten()
+            .map(|candidate| candidate as u8)
+            .find(|candidate| is_occupied(*candidate))
+    })
+}
+
+// The highest slot with a save state in it, which savestate_auto_index loads from
+pub fn highest_occupied_slot(max_slots: u16, is_occupied: impl Fn(u8) -> bool) -> Option<u8> {
+    (0..max_slots).rev().map(|slot| slot as u8).find(|slot| is_occupied(*slot))
+}
+
+// The slot savestate_auto_index saves to next when a game starts, the one after the highest occupied
+// slot so nothing is overwritten until every slot has been used
+pub fn auto_index_slot(max_slots: u16, is_occupied: impl Fn(u8) -> bool) -> u8 {
+    highest_occupied_slot(max_slots, is_occupied)
+        .map_or(0, |slot| next_save_slot(slot, max_slots, true, true))
+}
+
+// input_quick_swap_slot: slot 0 goes to the quick swap slot and any other slot goes back to 0, so after
+// moving away with the increase/decrease keys one tap returns to 0
+pub fn quick_swap_save_slot(current_slot: u8, quick_swap_slot: u8) -> u8 {
+    if current_slot == 0 {
+        quick_swap_slot
+    } else {
+        0
+    }
+}
+
+// Returns the save slot after an increase/decrease, either wrapping around or stopping at the ends
+pub fn next_save_slot(current_slot: u8, max_slots: u16, wrap: bool, increase: bool) -> u8 {
+    let last_slot = (max_slots - 1) as u8;
+    let current_slot = current_slot.min(last_slot);
+    if increase {
+        if current_slot < last_slot {
+            current_slot + 1
+        } else if wrap {
+            0
+        } else {
+            last_slot
+        }
+    } else if current_slot > 0 {
+        current_slot - 1
+    } else if wrap {
+        last_slot
+    } else {
+        0
+    }
+}
@@ -0,0 +1,64 @@
+use arboard::{Clipboard, ImageData};
+use std::borrow::Cow;
+use std::fs::{self, File};
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// Screenshots are named after the ROM plus a timestamp so repeated screenshots never overwrite each other
+pub fn get_screenshot_path(screenshot_directory: &str, game_file_name: &str) -> Option<PathBuf> {
+    let game_file_stem = Path::new(game_file_name).file_stem()?.to_str()?;
+    fs::create_dir_all(screenshot_directory).ok()?;
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_millis())
+        .unwrap_or(0);
+    Some(Path::new(screenshot_directory).join(format!("{}_{}.png", game_file_stem, timestamp)))
+}
+
+// Converts XRGB8888 pixels into tightly packed RGB bytes
+pub fn frame_to_rgb(pixels: &[u32]) -> Vec<u8> {
+    pixels
+        .iter()
+        .flat_map(|pixel| [(pixel >> 16) as u8, (pixel >> 8) as u8, *pixel as u8])
+        .collect()
+}
+
+// Converts XRGB8888 pixels into RGBA bytes with the alpha fully opaque, the padding byte is ignored
+pub fn frame_to_rgba(pixels: &[u32]) -> Vec<u8> {
+    pixels
+        .iter()
+        .flat_map(|pixel| [(pixel >> 16) as u8, (pixel >> 8) as u8, *pixel as u8, 0xFF])
+        .collect()
+}
+
+// `pixels` is the visible width x height part of the frame, see video::crop_frame
+pub fn save_screenshot(
+    path: &Path,
+    pixels: &[u32],
+    width: usize,
+    height: usize,
+) -> Result<(), String> {
+    let file = File::create(path).map_err(|e| format!("Failed to create screenshot: {}", e))?;
+    let mut encoder = png::Encoder::new(BufWriter::new(file), width as u32, height as u32);
+    encoder.set_color(png::ColorType::Rgb);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder
+        .write_header()
+        .and_then(|mut writer| writer.write_image_data(&frame_to_rgb(pixels)))
+        .map_err(|e| format!("Failed to write screenshot: {}", e))
+}
+
+// Some platforms (e.g. Wayland without a clipboard manager or a headless session) can't take images,
+// in which case the caller should fall back to save_screenshot
+pub fn copy_screenshot_to_clipboard(pixels: &[u32], width: usize, height: usize) -> Result<(), String> {
+    let mut clipboard =
+        Clipboard::new().map_err(|e| format!("Failed to open the clipboard: {}", e))?;
+    clipboard
+        .set_image(ImageData {
+            width,
+            height,
+            bytes: Cow::Owned(frame_to_rgba(pixels)),
+        })
+        .map_err(|e| format!("Failed to copy screenshot to the clipboard: {}", e))
+}
@@ -0,0 +1,102 @@
+use crate::controller_info::ControllerType;
+use crate::core_options::CoreVariable;
+use crate::environment::GameInfoExt;
+use crate::input::LightgunState;
+use crate::rom::LoadedContent;
+use crate::rumble::RumbleState;
+use crate::subsystem::Subsystem;
+use crate::video::ByteOrder;
+use libretro_sys::{FrameTimeCallback, GameInfo, MemoryDescriptor, PixelFormat, SystemAvInfo};
+use std::collections::BTreeMap;
+use std::ffi::CString;
+
+// libretro callbacks don't receive a user data pointer, so everything they read or write lives in
+// this global. This is also why only one Emulator can be running at a time.
+pub(crate) struct EmulatorState {
+    pub rom_name: String,
+    pub core_name: String,
+    pub frame_buffer: Option<Vec<u32>>,
+    pub audio_data: Option<Vec<i16>>,
+    // 0RGB1555 until the core calls ENVIRONMENT_SET_PIXEL_FORMAT, the format libretro says to assume
+    pub pixel_format: PixelFormat,
+    pub pixel_format_negotiated: bool, // set by ENVIRONMENT_SET_PIXEL_FORMAT, or once the default has been logged
+    pub bytes_per_pixel: u8, // its only either 2 or 4 bytes per pixel in libretro
+    pub screen_pitch: u32,
+    pub rgb565_byte_order: ByteOrder, // video_rgb565_byte_order and video_rgb565_swap_bytes from the config
+    pub swap_red_blue: bool,     // video_swap_rb from the config
+    pub threaded_convert: bool,  // video_threaded_convert from the config
+    pub screen_width: u32,
+    pub screen_height: u32,
+    pub buttons_pressed: Vec<Vec<i16>>, // indexed by port then by DEVICE_ID_JOYPAD_*
+    pub lightgun: Option<LightgunState>, // Some when port 0 is set to a lightgun
+    pub keyboard: Option<Vec<u32>>, // Some when a port is set to a keyboard, the RETROK_* ids held down
+    pub rumble: Vec<RumbleState>,        // indexed by port, set through the rumble interface
+    pub rumble_changed: bool,
+    pub av_info: Option<SystemAvInfo>,
+    pub av_info_changed: bool, // set when the core calls ENVIRONMENT_SET_SYSTEM_AV_INFO
+    pub geometry_changed: bool, // set when the core calls ENVIRONMENT_SET_GEOMETRY
+    pub frame_count: u64,      // frames run since the game was loaded or reset
+    pub core_crashed: bool,    // set when one of our callbacks panicked while the core was calling it
+    pub should_quit: bool,     // set when the core calls ENVIRONMENT_SHUTDOWN
+    pub game_info: Option<GameInfo>,
+    pub game_info_ext: Option<GameInfoExt>,
+    pub system_directory: Option<CString>,
+    pub language: u32, // RETRO_LANGUAGE_* answered to ENVIRONMENT_GET_LANGUAGE
+    pub rotation: u32, // quarter turns counter-clockwise, set through ENVIRONMENT_SET_ROTATION
+    pub performance_level: Option<u32>, // how demanding the core says it is, ENVIRONMENT_SET_PERFORMANCE_LEVEL
+    pub frame_time_callback: Option<FrameTimeCallback>, // set through ENVIRONMENT_SET_FRAME_TIME_CALLBACK
+    pub memory_descriptors: Vec<MemoryDescriptor>,
+    pub subsystems: Vec<Subsystem>,
+    pub controller_types: Vec<Vec<ControllerType>>, // indexed by port, set through ENVIRONMENT_SET_CONTROLLER_INFO
+    pub core_variables: Vec<CoreVariable>, // declared with ENVIRONMENT_SET_VARIABLES
+    pub core_variable_values: BTreeMap<String, CString>, // what ENVIRONMENT_GET_VARIABLE points the core at
+    pub core_option_values: BTreeMap<String, String>, // the config, core options are set by their key
+    pub loaded_content: Vec<LoadedContent>, // paths, meta and data the core may point into while a game is loaded
+}
+
+impl EmulatorState {
+    pub(crate) const fn new() -> EmulatorState {
+        EmulatorState {
+            rom_name: String::new(),
+            core_name: String::new(),
+            frame_buffer: None,
+            audio_data: None,
+            pixel_format: PixelFormat::ARGB1555,
+            pixel_format_negotiated: false,
+            bytes_per_pixel: 2,
+            screen_pitch: 0,
+            rgb565_byte_order: ByteOrder::native(),
+            swap_red_blue: false,
+            threaded_convert: false,
+            screen_width: 0,
+            screen_height: 0,
+            buttons_pressed: Vec::new(),
+            lightgun: None,
+            keyboard: None,
+            rumble: Vec::new(),
+            rumble_changed: false,
+            av_info: None,
+            av_info_changed: false,
+            geometry_changed: false,
+            frame_count: 0,
+            core_crashed: false,
+            should_quit: false,
+            game_info: None,
+            game_info_ext: None,
+            system_directory: None,
+            language: 0,
+            rotation: 0,
+            performance_level: None,
+            frame_time_callback: None,
+            memory_descriptors: Vec::new(),
+            subsystems: Vec::new(),
+            controller_types: Vec::new(),
+            core_variables: Vec::new(),
+            core_variable_values: BTreeMap::new(),
+            core_option_values: BTreeMap::new(),
+            loaded_content: Vec::new(),
+        }
+    }
+}
+
+pub(crate) static mut CURRENT_EMULATOR_STATE: EmulatorState = EmulatorState::new();
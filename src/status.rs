@@ -0,0 +1,65 @@
+use libretro_sys::PixelFormat;
+use std::fs::File;
+
+// One --status-json line, written to stdout once a second for tools wrapping the emulator. The field names
+// are a stable interface, add new ones rather than renaming them.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Status {
+    pub fps: f64,
+    pub frame: u64,
+    pub save_slot: u8,
+    pub pixel_format: PixelFormat,
+    pub width: u32,
+    pub height: u32,
+    pub paused: bool,
+    pub muted: bool,
+}
+
+impl Status {
+    // Nothing in it needs escaping so it's written by hand
+    pub fn to_json(&self) -> String {
+        format!(
+            "{{\"fps\":{:.2},\"frame\":{},\"save_slot\":{},\"pixel_format\":\"{}\",\"width\":{},\"height\":{},\"paused\":{},\"muted\":{}}}",
+            self.fps,
+            self.frame,
+            self.save_slot,
+            pixel_format_name(self.pixel_format),
+            self.width,
+            self.height,
+            self.paused,
+            self.muted
+        )
+    }
+}
+
+pub fn pixel_format_name(pixel_format: PixelFormat) -> &'static str {
+    match pixel_format {
+        PixelFormat::ARGB1555 => "0rgb1555",
+        PixelFormat::ARGB8888 => "xrgb8888",
+        PixelFormat::RGB565 => "rgb565",
+    }
+}
+
+// Points stdout at stderr so the frontend's println!s and anything the core prints go there, and returns
+// the real stdout for the status lines so nothing else is ever mixed in with them
+#[cfg(unix)]
+pub fn take_stdout() -> Result<File, String> {
+    use std::io::Write;
+    use std::os::fd::FromRawFd;
+    std::io::stdout().flush().ok();
+    unsafe {
+        let stdout = libc::dup(libc::STDOUT_FILENO);
+        if stdout < 0 || libc::dup2(libc::STDERR_FILENO, libc::STDOUT_FILENO) < 0 {
+            return Err(format!(
+                "Failed to move the logs to stderr: {}",
+                std::io::Error::last_os_error()
+            ));
+        }
+        Ok(File::from_raw_fd(stdout))
+    }
+}
+
+#[cfg(not(unix))]
+pub fn take_stdout() -> Result<File, String> {
+    Err("--status-json isn't supported on this platform yet".to_string())
+}
@@ -0,0 +1,72 @@
+use crate::core_info::c_string_to_string;
+use libretro_sys::SubsystemInfo;
+
+// One of the ROMs a subsystem is loaded with, e.g. the Game Boy cartridge for the Super Game Boy
+#[derive(Clone, Debug)]
+pub struct SubsystemRom {
+    pub description: String,
+    pub valid_extensions: Vec<String>,
+    pub need_fullpath: bool,
+    pub block_extract: bool,
+    pub required: bool,
+}
+
+// A multi ROM configuration the core advertised through ENVIRONMENT_SET_SUBSYSTEM_INFO, loaded with
+// retro_load_game_special
+#[derive(Clone, Debug)]
+pub struct Subsystem {
+    pub description: String,
+    pub ident: String,
+    pub id: u32,
+    pub roms: Vec<SubsystemRom>,
+}
+
+// Copies the core's array of subsystems, which ends with an entry that has a null description
+/// # Safety
+/// `info` must be null or point to an array of SubsystemInfo terminated by a zeroed entry.
+pub(crate) unsafe fn parse_subsystem_info(info: *const SubsystemInfo) -> Vec<Subsystem> {
+    let mut subsystems = Vec::new();
+    if info.is_null() {
+        return subsystems;
+    }
+    let mut index = 0;
+    loop {
+        let subsystem = &*info.add(index);
+        if subsystem.desc.is_null() {
+            break;
+        }
+        let roms = if subsystem.roms.is_null() {
+            Vec::new()
+        } else {
+            std::slice::from_raw_parts(subsystem.roms, subsystem.num_roms as usize)
+                .iter()
+                .map(|rom| SubsystemRom {
+                    description: c_string_to_string(rom.desc),
+                    valid_extensions: c_string_to_string(rom.valid_extensions)
+                        .split('|')
+                        .filter(|extension| !extension.is_empty())
+                        .map(|extension| extension.to_lowercase())
+                        .collect(),
+                    need_fullpath: rom.need_fullpath,
+                    block_extract: rom.block_extract,
+                    required: rom.required,
+                })
+                .collect()
+        };
+        subsystems.push(Subsystem {
+            description: c_string_to_string(subsystem.desc),
+            ident: c_string_to_string(subsystem.ident),
+            id: subsystem.id,
+            roms,
+        });
+        index += 1;
+    }
+    subsystems
+}
+
+// The subsystem can be given by its ident (e.g. "sgb") or its numeric id
+pub fn find_subsystem<'a>(subsystems: &'a [Subsystem], name: &str) -> Option<&'a Subsystem> {
+    subsystems.iter().find(|subsystem| {
+        subsystem.ident.eq_ignore_ascii_case(name) || subsystem.id.to_string() == name
+    })
+}
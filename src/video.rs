@@ -0,0 +1,694 @@
+use rayon::prelude::*;
+
+// Byte order of the 16 bit pixels a core sends. libretro frames are in the host's byte order so that's
+// the default, video_rgb565_byte_order (or video_rgb565_swap_bytes) overrides it for cores that get it wrong.
+// libretro has no way for a core to report this itself.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ByteOrder {
+    Little,
+    Big,
+}
+
+impl ByteOrder {
+    pub const fn native() -> ByteOrder {
+        if cfg!(target_endian = "big") {
+            ByteOrder::Big
+        } else {
+            ByteOrder::Little
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<ByteOrder> {
+        match name.to_lowercase().as_str() {
+            "native" => Some(ByteOrder::native()),
+            "little" => Some(ByteOrder::Little),
+            "big" => Some(ByteOrder::Big),
+            _ => None,
+        }
+    }
+
+    pub fn swapped(self) -> ByteOrder {
+        match self {
+            ByteOrder::Little => ByteOrder::Big,
+            ByteOrder::Big => ByteOrder::Little,
+        }
+    }
+
+    fn read_u16(self, bytes: [u8; 2]) -> u16 {
+        match self {
+            ByteOrder::Little => u16::from_le_bytes(bytes),
+            ByteOrder::Big => u16::from_be_bytes(bytes),
+        }
+    }
+}
+
+pub fn convert_pixel_array_from_rgb565_to_xrgb8888(
+    color_array: &[u8],
+    byte_order: ByteOrder,
+) -> Box<[u32]> {
+    println!("convert_pixel_array_from_rgb565_to_xrgb8888");
+    warn_about_odd_rgb565_length(color_array);
+    let mut result = vec![0u32; color_array.len() / 2];
+    convert_rgb565_into(color_array, byte_order, &mut result);
+    result.into_boxed_slice()
+}
+
+// video_threaded_convert, the same conversion with the rows (row_bytes apart, the pitch) shared out over
+// rayon's thread pool, which is started once rather than every frame. Converting takes about 1.1ns a pixel
+// on one thread, benches/rgb565_convert.rs measures from what frame size the pool beats that.
+pub fn convert_pixel_array_from_rgb565_to_xrgb8888_threaded(
+    color_array: &[u8],
+    byte_order: ByteOrder,
+    row_bytes: usize,
+) -> Box<[u32]> {
+    warn_about_odd_rgb565_length(color_array);
+    let mut result = vec![0u32; color_array.len() / 2];
+    let rows = color_array.len().checked_div(row_bytes).unwrap_or(0);
+    // Odd pitches can't be split between pixels so they're converted on this thread
+    if rows < 2 || !row_bytes.is_multiple_of(2) {
+        convert_rgb565_into(color_array, byte_order, &mut result);
+        return result.into_boxed_slice();
+    }
+    color_array
+        .par_chunks(row_bytes)
+        .zip(result.par_chunks_mut(row_bytes / 2))
+        .for_each(|(bytes, pixels)| convert_rgb565_into(bytes, byte_order, pixels));
+    result.into_boxed_slice()
+}
+
+// A malformed frame shouldn't take down the frontend, the odd byte at the end is dropped
+fn warn_about_odd_rgb565_length(color_array: &[u8]) {
+    if !color_array.len().is_multiple_of(2) {
+        println!(
+            "RGB565 frame of {} bytes isn't a whole number of pixels, ignoring the last byte",
+            color_array.len()
+        );
+    }
+}
+
+fn convert_rgb565_into(color_array: &[u8], byte_order: ByteOrder, pixels: &mut [u32]) {
+    for (bytes, pixel) in color_array.chunks_exact(2).zip(pixels) {
+        let color = byte_order.read_u16([bytes[0], bytes[1]]) as u32;
+        // 5 bits of red, 6 of green then 5 of blue from the most significant bit down
+        let red = (color >> 11) & 0b1_1111;
+        let green = (color >> 5) & 0b11_1111;
+        let blue = color & 0b1_1111;
+
+        // Use high bits for empty low bits as we have more bits available in XRGB8888
+        let red = (red << 3) | (red >> 2);
+        let green = (green << 2) | (green >> 3);
+        let blue = (blue << 3) | (blue >> 2);
+
+        *pixel = (red << 16) | (green << 8) | blue;
+    }
+}
+
+// XRGB8888's top byte is padding that cores may leave as anything, it's cleared so every converted frame
+// has a zero top byte like the 16 bit formats give and nothing downstream takes it for alpha
+pub fn convert_pixel_array_from_xrgb8888(color_array: &[u8]) -> Box<[u32]> {
+    color_array
+        .chunks_exact(4)
+        .map(|bytes| u32::from_ne_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) & 0x00FF_FFFF)
+        .collect()
+}
+
+// libretro's default format, used by cores that never call ENVIRONMENT_SET_PIXEL_FORMAT. Read in the same
+// byte order as RGB565 as both are 16 bits a pixel.
+pub fn convert_pixel_array_from_0rgb1555_to_xrgb8888(
+    color_array: &[u8],
+    byte_order: ByteOrder,
+) -> Box<[u32]> {
+    color_array
+        .chunks_exact(2)
+        .map(|bytes| {
+            let color = byte_order.read_u16([bytes[0], bytes[1]]) as u32;
+            // The top bit is unused then 5 bits each of red, green and blue
+            let red = (color >> 10) & 0b1_1111;
+            let green = (color >> 5) & 0b1_1111;
+            let blue = color & 0b1_1111;
+            let expand = |channel: u32| (channel << 3) | (channel >> 2);
+            (expand(red) << 16) | (expand(green) << 8) | expand(blue)
+        })
+        .collect()
+}
+
+// Swaps the red and blue channels of XRGB8888 pixels in place (video_swap_rb)
+pub fn swap_red_blue(pixels: &mut [u32]) {
+    for pixel in pixels {
+        *pixel = (*pixel & 0xFF00FF00) | ((*pixel >> 16) & 0xFF) | ((*pixel & 0xFF) << 16);
+    }
+}
+
+// video_gamma and video_brightness as a lookup table from each 0-255 channel value to its corrected value,
+// 255 * brightness * (value / 255) ^ (1 / gamma). Gamma above 1 lifts the midtones for dim displays, below 1
+// darkens them. 1.0 for both leaves the frame as it is.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ColorCorrection {
+    table: [u8; 256],
+}
+
+impl ColorCorrection {
+    pub fn new(gamma: f64, brightness: f64) -> ColorCorrection {
+        let mut table = [0; 256];
+        for (value, corrected) in table.iter_mut().enumerate() {
+            let level = (value as f64 / 255.0).powf(1.0 / gamma) * brightness;
+            *corrected = (level * 255.0).round().clamp(0.0, 255.0) as u8;
+        }
+        ColorCorrection { table }
+    }
+
+    pub fn is_identity(&self) -> bool {
+        self.table.iter().enumerate().all(|(value, corrected)| *corrected as usize == value)
+    }
+
+    pub fn correct(&self, value: u8) -> u8 {
+        self.table[value as usize]
+    }
+
+    // Corrects the red, green and blue channels of XRGB8888 pixels in place
+    pub fn apply(&self, pixels: &mut [u32]) {
+        if self.is_identity() {
+            return;
+        }
+        for pixel in pixels {
+            *pixel = self.correct_pixel(*pixel);
+        }
+    }
+
+    pub fn correct_pixel(&self, pixel: u32) -> u32 {
+        let channel = |shift: u32| (self.table[((pixel >> shift) & 0xFF) as usize] as u32) << shift;
+        (pixel & 0xFF000000) | channel(16) | channel(8) | channel(0)
+    }
+}
+
+// The rows of a frame buffer are `stride` pixels apart, which for some cores is wider than the visible
+// width as the pitch includes padding. Returns just the visible width x height pixels, or None when the
+// buffer is too small to hold them.
+pub fn crop_frame(
+    frame_buffer: &[u32],
+    width: usize,
+    height: usize,
+    stride: usize,
+) -> Option<Vec<u32>> {
+    if width == 0
+        || height == 0
+        || stride < width
+        || frame_buffer.len() < (height - 1) * stride + width
+    {
+        return None;
+    }
+    if stride == width {
+        return Some(frame_buffer[..width * height].to_vec());
+    }
+    let mut visible = Vec::with_capacity(width * height);
+    for row in frame_buffer.chunks(stride).take(height) {
+        visible.extend_from_slice(&row[..width]);
+    }
+    Some(visible)
+}
+
+// Pixels to cut off each edge of the frame to hide the border some systems kept in the overscan area
+// (video_crop_top etc.)
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct OverscanCrop {
+    pub top: usize,
+    pub bottom: usize,
+    pub left: usize,
+    pub right: usize,
+}
+
+impl OverscanCrop {
+    // When the crop would leave nothing of the frame it's left uncropped
+    pub fn cropped_size(&self, width: usize, height: usize) -> (usize, usize) {
+        if self.left + self.right >= width || self.top + self.bottom >= height {
+            return (width, height);
+        }
+        (width - self.left - self.right, height - self.top - self.bottom)
+    }
+
+    pub fn apply(&self, pixels: &[u32], width: usize, height: usize) -> (Vec<u32>, usize, usize) {
+        let (new_width, new_height) = self.cropped_size(width, height);
+        if (new_width, new_height) == (width, height) || pixels.len() < width * height {
+            return (pixels.to_vec(), width, height);
+        }
+        let mut cropped = Vec::with_capacity(new_width * new_height);
+        for row in pixels.chunks(width).skip(self.top).take(new_height) {
+            cropped.extend_from_slice(&row[self.left..self.left + new_width]);
+        }
+        (cropped, new_width, new_height)
+    }
+}
+
+// The size of a width x height frame once it's been rotated by `rotation` quarter turns
+pub fn rotated_size(width: usize, height: usize, rotation: u32) -> (usize, usize) {
+    if rotation % 2 == 1 {
+        (height, width)
+    } else {
+        (width, height)
+    }
+}
+
+// window_min_size and window_max_size, the window is scaled up or down to fit between them keeping its shape.
+// When they can't both be met the maximum wins so the window still fits on the screen.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct WindowSizeLimits {
+    pub min: Option<(usize, usize)>,
+    pub max: Option<(usize, usize)>,
+}
+
+impl WindowSizeLimits {
+    pub fn clamp(&self, width: usize, height: usize) -> (usize, usize) {
+        if width == 0 || height == 0 {
+            return (width, height);
+        }
+        let mut factor: f64 = 1.0;
+        if let Some((min_width, min_height)) = self.min {
+            factor = factor
+                .max(min_width as f64 / width as f64)
+                .max(min_height as f64 / height as f64);
+        }
+        if let Some((max_width, max_height)) = self.max {
+            factor = factor
+                .min(max_width as f64 / width as f64)
+                .min(max_height as f64 / height as f64);
+        }
+        let scaled = |size: usize| ((size as f64 * factor).round() as usize).max(1);
+        (scaled(width), scaled(height))
+    }
+}
+
+// The next whole video_scale up or down, kept between 1 and max
+pub fn next_video_scale(scale: u32, max: u32, increase: bool) -> u32 {
+    if increase {
+        scale.saturating_add(1).min(max.max(1))
+    } else {
+        scale.saturating_sub(1).clamp(1, max.max(1))
+    }
+}
+
+// Rotates the frame counter-clockwise by `rotation` quarter turns, which is how libretro counts them
+// (ENVIRONMENT_SET_ROTATION or video_rotation). Vertical arcade games draw sideways and rely on this.
+pub fn rotate_frame(
+    pixels: &[u32],
+    width: usize,
+    height: usize,
+    rotation: u32,
+) -> (Vec<u32>, usize, usize) {
+    let rotation = rotation % 4;
+    if rotation == 0 || pixels.len() < width * height {
+        return (pixels.to_vec(), width, height);
+    }
+    let (new_width, new_height) = rotated_size(width, height, rotation);
+    let mut rotated = Vec::with_capacity(new_width * new_height);
+    for y in 0..new_height {
+        for x in 0..new_width {
+            let (source_x, source_y) = match rotation {
+                1 => (width - 1 - y, x),
+                2 => (width - 1 - x, height - 1 - y),
+                _ => (y, height - 1 - x),
+            };
+            rotated.push(pixels[source_y * width + source_x]);
+        }
+    }
+    (rotated, new_width, new_height)
+}
+
+// Applied to the visible frame before it's presented (video_filter or --scale-filter). The window scales
+// whatever it's given with nearest neighbour, so the other filters double the size to smooth that out.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum VideoFilter {
+    Nearest,
+    Bilinear,
+    Scale2x,
+}
+
+impl VideoFilter {
+    pub const NAMES: [&'static str; 3] = ["nearest", "bilinear", "scale2x"];
+
+    pub fn from_name(name: &str) -> Option<VideoFilter> {
+        match name.to_ascii_lowercase().as_str() {
+            "nearest" => Some(VideoFilter::Nearest),
+            "bilinear" => Some(VideoFilter::Bilinear),
+            "scale2x" => Some(VideoFilter::Scale2x),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            VideoFilter::Nearest => VideoFilter::NAMES[0],
+            VideoFilter::Bilinear => VideoFilter::NAMES[1],
+            VideoFilter::Scale2x => VideoFilter::NAMES[2],
+        }
+    }
+
+    // input_toggle_filter flips between crisp and smooth, from scale2x it goes back to nearest
+    pub fn toggled(&self) -> VideoFilter {
+        match self {
+            VideoFilter::Nearest => VideoFilter::Bilinear,
+            VideoFilter::Bilinear | VideoFilter::Scale2x => VideoFilter::Nearest,
+        }
+    }
+
+    pub fn apply(&self, pixels: &[u32], width: usize, height: usize) -> (Vec<u32>, usize, usize) {
+        match self {
+            VideoFilter::Nearest => (pixels.to_vec(), width, height),
+            VideoFilter::Bilinear => bilinear_2x(pixels, width, height),
+            VideoFilter::Scale2x => scale2x(pixels, width, height),
+        }
+    }
+}
+
+// How the frame's shape is kept when it's stretched over the window (video_aspect_mode). Core uses the
+// aspect ratio the core asks for, square shows every pixel square and stretch fills the window.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AspectMode {
+    Core,
+    Square,
+    Stretch,
+}
+
+impl AspectMode {
+    pub const NAMES: [&'static str; 3] = ["core", "square", "stretch"];
+
+    pub fn from_name(name: &str) -> Option<AspectMode> {
+        match name.to_ascii_lowercase().as_str() {
+            "core" => Some(AspectMode::Core),
+            "square" => Some(AspectMode::Square),
+            "stretch" => Some(AspectMode::Stretch),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            AspectMode::Core => AspectMode::NAMES[0],
+            AspectMode::Square => AspectMode::NAMES[1],
+            AspectMode::Stretch => AspectMode::NAMES[2],
+        }
+    }
+
+    // input_toggle_aspect goes core, square, stretch then back to core
+    pub fn next(&self) -> AspectMode {
+        match self {
+            AspectMode::Core => AspectMode::Square,
+            AspectMode::Square => AspectMode::Stretch,
+            AspectMode::Stretch => AspectMode::Core,
+        }
+    }
+
+    // The width / height the frame should be shown at, None to fill the window. core_aspect_ratio is the
+    // core's geometry.aspect_ratio, 0 or less means square pixels. A frame turned on its side (`rotated`)
+    // turns the core's aspect ratio with it.
+    pub fn aspect_ratio(
+        &self,
+        width: usize,
+        height: usize,
+        core_aspect_ratio: f32,
+        rotated: bool,
+    ) -> Option<f64> {
+        let square = width as f64 / height.max(1) as f64;
+        match self {
+            AspectMode::Core if core_aspect_ratio > 0.0 => {
+                let aspect_ratio = core_aspect_ratio as f64;
+                Some(if rotated {
+                    1.0 / aspect_ratio
+                } else {
+                    aspect_ratio
+                })
+            }
+            AspectMode::Core | AspectMode::Square => Some(square),
+            AspectMode::Stretch => None,
+        }
+    }
+}
+
+// minifb stretches whatever it's given over the whole window, so to show the frame at `aspect_ratio` it's
+// padded with black bars (top and bottom or left and right) until the buffer has the window's shape. The
+// buffer is built afresh every time so nothing is left in the bars from a previous mode or frame.
+pub fn letterbox(
+    pixels: &[u32],
+    width: usize,
+    height: usize,
+    aspect_ratio: f64,
+    window_width: usize,
+    window_height: usize,
+) -> (Vec<u32>, usize, usize) {
+    if width == 0 || height == 0 || window_width == 0 || window_height == 0 {
+        return (pixels.to_vec(), width, height);
+    }
+    let window_aspect_ratio = window_width as f64 / window_height as f64;
+    let (new_width, new_height) = if aspect_ratio > window_aspect_ratio {
+        let new_height = (height as f64 * aspect_ratio / window_aspect_ratio).round() as usize;
+        (width, new_height.max(height))
+    } else {
+        let new_width = (width as f64 * window_aspect_ratio / aspect_ratio).round() as usize;
+        (new_width.max(width), height)
+    };
+    if (new_width, new_height) == (width, height) {
+        return (pixels.to_vec(), width, height);
+    }
+    let left = (new_width - width) / 2;
+    let top = (new_height - height) / 2;
+    let mut letterboxed = vec![0; new_width * new_height];
+    for (row, source) in pixels.chunks(width).take(height).enumerate() {
+        let start = (top + row) * new_width + left;
+        letterboxed[start..start + width].copy_from_slice(source);
+    }
+    (letterboxed, new_width, new_height)
+}
+
+// Doubles the frame, each output pixel is a blend of the four source pixels around its centre
+pub fn bilinear_2x(pixels: &[u32], width: usize, height: usize) -> (Vec<u32>, usize, usize) {
+    let (new_width, new_height) = (width * 2, height * 2);
+    let mut result = Vec::with_capacity(new_width * new_height);
+    // Output pixel centres land a quarter of a source pixel either side of the source pixel centres
+    let source_position = |output: usize, size: usize| {
+        let position = ((output as f32 + 0.5) / 2.0 - 0.5).clamp(0.0, (size - 1) as f32);
+        let index = position as usize;
+        (index, (index + 1).min(size - 1), position - index as f32)
+    };
+    for y in 0..new_height {
+        let (top, bottom, y_weight) = source_position(y, height);
+        for x in 0..new_width {
+            let (left, right, x_weight) = source_position(x, width);
+            let corners = [
+                pixels[top * width + left],
+                pixels[top * width + right],
+                pixels[bottom * width + left],
+                pixels[bottom * width + right],
+            ];
+            result.push(bilinear_blend(corners, x_weight, y_weight));
+        }
+    }
+    (result, new_width, new_height)
+}
+
+// Blends the top left, top right, bottom left and bottom right pixels around a point that's x_weight of
+// the way across and y_weight of the way down between them
+fn bilinear_blend(corners: [u32; 4], x_weight: f32, y_weight: f32) -> u32 {
+    let mut pixel = 0;
+    for shift in [16, 8, 0] {
+        let channel = |corner: usize| ((corners[corner] >> shift) & 0xFF) as f32;
+        let top_value = channel(0) * (1.0 - x_weight) + channel(1) * x_weight;
+        let bottom_value = channel(2) * (1.0 - x_weight) + channel(3) * x_weight;
+        let value = top_value * (1.0 - y_weight) + bottom_value * y_weight;
+        pixel |= (value.round() as u32) << shift;
+    }
+    pixel
+}
+
+// The Scale2x (AdvMAME2x) pixel art scaler. Each pixel E becomes 2x2 pixels, a corner takes the colour
+// of its two neighbouring edges when they match, so diagonal lines are smoothed without blurring.
+// Pixels past the edge of the frame are treated as copies of the edge pixel.
+pub fn scale2x(pixels: &[u32], width: usize, height: usize) -> (Vec<u32>, usize, usize) {
+    let (new_width, new_height) = (width * 2, height * 2);
+    let mut result = vec![0; new_width * new_height];
+    let pixel = |x: usize, y: usize| pixels[y * width + x];
+    for y in 0..height {
+        for x in 0..width {
+            let [e0, e1, e2, e3] = scale2x_pixel(&pixel, x, y, width, height);
+            let top_left = (y * 2) * new_width + x * 2;
+            result[top_left] = e0;
+            result[top_left + 1] = e1;
+            result[top_left + new_width] = e2;
+            result[top_left + new_width + 1] = e3;
+        }
+    }
+    (result, new_width, new_height)
+}
+
+// The 2x2 pixels (top left, top right, bottom left, bottom right) Scale2x turns the pixel at x, y into
+fn scale2x_pixel(
+    pixel: &impl Fn(usize, usize) -> u32,
+    x: usize,
+    y: usize,
+    width: usize,
+    height: usize,
+) -> [u32; 4] {
+    let e = pixel(x, y);
+    let b = pixel(x, y.saturating_sub(1));
+    let d = pixel(x.saturating_sub(1), y);
+    let f = pixel((x + 1).min(width - 1), y);
+    let h = pixel(x, (y + 1).min(height - 1));
+    if b != h && d != f {
+        [
+            if d == b { d } else { e },
+            if b == f { f } else { e },
+            if d == h { d } else { e },
+            if h == f { f } else { e },
+        ]
+    } else {
+        [e, e, e, e]
+    }
+}
+
+// Everything present_frame does to a frame on its way from the core to the window
+#[derive(Clone, Debug, PartialEq)]
+pub struct PresentOptions {
+    pub overscan: OverscanCrop,
+    pub rotation: u32,
+    pub color_correction: ColorCorrection,
+    pub filter: VideoFilter,
+    // The width / height the frame is shown at inside the window, None to fill it
+    pub aspect_ratio: Option<f64>,
+    // Only scale by whole multiples of the frame's height (video_scale_integer)
+    pub integer_scale: bool,
+}
+
+impl PresentOptions {
+    // The size of the frame once it's been cropped, rotated and filtered, what present_frame gives back
+    // when it's not asked to scale it to a window
+    pub fn frame_size(&self, width: usize, height: usize) -> (usize, usize) {
+        let (width, height) = self.overscan.cropped_size(width, height);
+        let (width, height) = rotated_size(width, height, self.rotation % 4);
+        match self.filter {
+            VideoFilter::Nearest => (width, height),
+            VideoFilter::Bilinear | VideoFilter::Scale2x => (width * 2, height * 2),
+        }
+    }
+
+    // Where the frame goes inside the window: left, top, width and height
+    fn destination(
+        &self,
+        frame_width: usize,
+        frame_height: usize,
+        window_width: usize,
+        window_height: usize,
+    ) -> (usize, usize, usize, usize) {
+        let (mut width, mut height) = match self.aspect_ratio {
+            Some(aspect_ratio) if aspect_ratio > window_width as f64 / window_height as f64 => {
+                let height = (window_width as f64 / aspect_ratio).round() as usize;
+                (window_width, height.clamp(1, window_height))
+            }
+            Some(aspect_ratio) => {
+                let width = (window_height as f64 * aspect_ratio).round() as usize;
+                (width.clamp(1, window_width), window_height)
+            }
+            None => (window_width, window_height),
+        };
+        if self.integer_scale {
+            // The largest whole multiple that still fits the window once the width is worked out
+            let fits = |height: usize| match self.aspect_ratio {
+                Some(aspect_ratio) => (height as f64 * aspect_ratio).round() as usize <= window_width,
+                None => true,
+            };
+            let mut scale = (height / frame_height).max(1);
+            while scale > 1 && !fits(frame_height * scale) {
+                scale -= 1;
+            }
+            let scaled_height = (frame_height * scale).min(window_height);
+            width = match self.aspect_ratio {
+                Some(aspect_ratio) => (scaled_height as f64 * aspect_ratio).round() as usize,
+                None => frame_width * (width / frame_width).max(1),
+            }
+            .clamp(1, window_width);
+            height = scaled_height;
+        }
+        ((window_width - width) / 2, (window_height - height) / 2, width, height)
+    }
+}
+
+// Crops the visible width x height out of the core's frame buffer (rows `stride` pixels apart), crops the
+// overscan, rotates, colour corrects and scales it with the filter to window_width x window_height in one
+// go, with black bars around it when it's kept at an aspect ratio. Only the returned frame is allocated.
+// A window size of 0 gives the frame at its own size (PresentOptions::frame_size), as recordings want it.
+// None when the buffer is too small to hold the frame.
+pub fn present_frame(
+    frame_buffer: &[u32],
+    width: usize,
+    height: usize,
+    stride: usize,
+    window_width: usize,
+    window_height: usize,
+    options: &PresentOptions,
+) -> Option<Vec<u32>> {
+    if width == 0
+        || height == 0
+        || stride < width
+        || frame_buffer.len() < (height - 1) * stride + width
+    {
+        return None;
+    }
+    let (cropped_width, cropped_height) = options.overscan.cropped_size(width, height);
+    let (left, top) = match (cropped_width, cropped_height) == (width, height) {
+        true => (0, 0),
+        false => (options.overscan.left, options.overscan.top),
+    };
+    let rotation = options.rotation % 4;
+    let (frame_width, frame_height) = rotated_size(cropped_width, cropped_height, rotation);
+    let corrected = !options.color_correction.is_identity();
+    let source = |x: usize, y: usize| {
+        let (source_x, source_y) = match rotation {
+            0 => (x, y),
+            1 => (cropped_width - 1 - y, x),
+            2 => (cropped_width - 1 - x, cropped_height - 1 - y),
+            _ => (y, cropped_height - 1 - x),
+        };
+        let pixel = frame_buffer[(top + source_y) * stride + left + source_x];
+        match corrected {
+            true => options.color_correction.correct_pixel(pixel),
+            false => pixel,
+        }
+    };
+
+    let (window_width, window_height) = match window_width == 0 || window_height == 0 {
+        true => options.frame_size(width, height),
+        false => (window_width, window_height),
+    };
+    let (destination_left, destination_top, destination_width, destination_height) =
+        options.destination(frame_width, frame_height, window_width, window_height);
+    let mut presented = vec![0; window_width * window_height];
+    // Output pixel centres mapped back onto the frame, the same as bilinear_2x when scaling by 2
+    let source_position = |output: usize, output_size: usize, size: usize| {
+        let position = ((output as f32 + 0.5) * size as f32 / output_size as f32 - 0.5)
+            .clamp(0.0, (size - 1) as f32);
+        let index = position as usize;
+        (index, (index + 1).min(size - 1), position - index as f32)
+    };
+    for y in 0..destination_height {
+        let row_start = (destination_top + y) * window_width + destination_left;
+        let row = &mut presented[row_start..row_start + destination_width];
+        for (x, pixel) in row.iter_mut().enumerate() {
+            *pixel = match options.filter {
+                VideoFilter::Nearest => source(
+                    x * frame_width / destination_width,
+                    y * frame_height / destination_height,
+                ),
+                VideoFilter::Bilinear => {
+                    let (left, right, x_weight) = source_position(x, destination_width, frame_width);
+                    let (top, bottom, y_weight) = source_position(y, destination_height, frame_height);
+                    let corners = [source(left, top), source(right, top), source(left, bottom), source(right, bottom)];
+                    bilinear_blend(corners, x_weight, y_weight)
+                }
+                VideoFilter::Scale2x => {
+                    let scaled_x = x * frame_width * 2 / destination_width;
+                    let scaled_y = y * frame_height * 2 / destination_height;
+                    let quarter = (scaled_y % 2) * 2 + scaled_x % 2;
+                    scale2x_pixel(&source, scaled_x / 2, scaled_y / 2, frame_width, frame_height)[quarter]
+                }
+            };
+        }
+    }
+    Some(presented)
+}
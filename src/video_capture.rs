@@ -0,0 +1,115 @@
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+// Raw video file layout (all integers little-endian):
+//   "RRVD" magic, u8 version, u32 width, u32 height, f64 frames per second,
+//   then every frame's pixels as XRGB8888, which is B, G, R, X in memory (ffmpeg's bgr0).
+// The frames have no headers of their own so after skipping the header ffmpeg can read it as rawvideo:
+//   ffmpeg -f rawvideo -pixel_format bgr0 -video_size 160x144 -framerate 60 -skip_initial_bytes 21 \
+//     -i gameplay.raw -c:v libx264 -pix_fmt yuv420p gameplay.mp4
+// and to mux it with audio recorded alongside it add the audio as a second input:
+//   ffmpeg -f rawvideo -pixel_format bgr0 -video_size 160x144 -framerate 60 -skip_initial_bytes 21 \
+//     -i gameplay.raw -i gameplay.wav -c:v libx264 -pix_fmt yuv420p -c:a aac -shortest gameplay.mp4
+const VIDEO_MAGIC: &[u8; 4] = b"RRVD";
+const VIDEO_VERSION: u8 = 1;
+pub const VIDEO_HEADER_SIZE: usize = 21;
+
+// Writes every presented frame to a raw video file. The size is taken from the first frame as rawvideo
+// can't change size part way through, frames of any other size are replaced by the last frame so the
+// video keeps its timing.
+pub struct VideoRecorder {
+    writer: BufWriter<File>,
+    fps: f64,
+    size: Option<(usize, usize)>,
+    frame_bytes: Vec<u8>, // the last frame written, reused so each frame doesn't allocate
+    frames_written: u64,
+    frames_skipped: u64,
+}
+
+impl VideoRecorder {
+    pub fn create(path: &Path, fps: f64) -> Result<VideoRecorder, String> {
+        let file = File::create(path).map_err(|e| format!("Failed to create video file: {}", e))?;
+        Ok(VideoRecorder {
+            writer: BufWriter::new(file),
+            fps,
+            size: None,
+            frame_bytes: Vec::new(),
+            frames_written: 0,
+            frames_skipped: 0,
+        })
+    }
+
+    pub fn record_frame(
+        &mut self,
+        pixels: &[u32],
+        width: usize,
+        height: usize,
+    ) -> Result<(), String> {
+        match self.size {
+            None => {
+                self.writer
+                    .write_all(VIDEO_MAGIC)
+                    .and_then(|_| self.writer.write_all(&[VIDEO_VERSION]))
+                    .and_then(|_| self.writer.write_all(&(width as u32).to_le_bytes()))
+                    .and_then(|_| self.writer.write_all(&(height as u32).to_le_bytes()))
+                    .and_then(|_| self.writer.write_all(&self.fps.to_le_bytes()))
+                    .map_err(|e| format!("Failed to write video header: {}", e))?;
+                self.size = Some((width, height));
+                println!(
+                    "Recording {}x{} video at {:.2} fps",
+                    width, height, self.fps
+                );
+            }
+            Some(size) if size != (width, height) => {
+                if self.frames_skipped == 0 {
+                    println!(
+                        "Frame size changed to {}x{}, repeating the last frame in place of frames that aren't {}x{}",
+                        width, height, size.0, size.1
+                    );
+                }
+                self.frames_skipped += 1;
+                return self.write_frame_bytes();
+            }
+            Some(_) => {}
+        }
+        self.frame_bytes.clear();
+        self.frame_bytes.extend(
+            pixels[..width * height]
+                .iter()
+                .flat_map(|pixel| pixel.to_le_bytes()),
+        );
+        self.write_frame_bytes()
+    }
+
+    fn write_frame_bytes(&mut self) -> Result<(), String> {
+        self.writer
+            .write_all(&self.frame_bytes)
+            .map_err(|e| format!("Failed to write video frame: {}", e))?;
+        self.frames_written += 1;
+        Ok(())
+    }
+
+    // The size of the frames in the file, None until the first frame is written
+    pub fn size(&self) -> Option<(usize, usize)> {
+        self.size
+    }
+
+    pub fn fps(&self) -> f64 {
+        self.fps
+    }
+
+    // Returns the number of frames in the file
+    pub fn finish(mut self) -> Result<u64, String> {
+        if self.frames_skipped > 0 {
+            println!(
+                "{} frames of a different size were replaced by the frame before them",
+                self.frames_skipped
+            );
+        }
+        self.writer
+            .flush()
+            .map_err(|e| format!("Failed to flush video file: {}", e))?;
+        Ok(self.frames_written)
+    }
+}
@@ -0,0 +1,42 @@
+use rustro_arch::video::{letterbox, AspectMode};
+
+#[test]
+fn frames_are_padded_to_the_window_shape() {
+    // A square 2x2 frame in a 2:1 window gets a column of black on each side
+    let (pixels, width, height) = letterbox(&[1, 2, 3, 4], 2, 2, 1.0, 400, 200);
+    assert_eq!((width, height), (4, 2));
+    assert_eq!(pixels, vec![0, 1, 2, 0, 0, 3, 4, 0]);
+    // A 2:1 frame in a square window gets rows of black above and below
+    let (pixels, width, height) = letterbox(&[1, 2, 3, 4], 2, 2, 2.0, 300, 300);
+    assert_eq!((width, height), (2, 4));
+    assert_eq!(pixels, vec![0, 0, 1, 2, 3, 4, 0, 0]);
+    // Already the right shape
+    assert_eq!(letterbox(&[1, 2], 2, 1, 2.0, 200, 100), (vec![1, 2], 2, 1));
+}
+
+#[test]
+fn each_mode_picks_its_aspect_ratio() {
+    // A 256x224 frame the core wants shown at 4:3
+    assert_eq!(
+        AspectMode::Core.aspect_ratio(256, 224, 4.0 / 3.0, false),
+        Some((4.0f32 / 3.0) as f64)
+    );
+    assert_eq!(
+        AspectMode::Core.aspect_ratio(224, 256, 4.0 / 3.0, true),
+        Some(1.0 / (4.0f32 / 3.0) as f64)
+    );
+    assert_eq!(
+        AspectMode::Square.aspect_ratio(256, 224, 4.0 / 3.0, false),
+        Some(256.0 / 224.0)
+    );
+    // Without an aspect ratio from the core its pixels are square
+    assert_eq!(
+        AspectMode::Core.aspect_ratio(256, 224, 0.0, false),
+        Some(256.0 / 224.0)
+    );
+    assert_eq!(
+        AspectMode::Stretch.aspect_ratio(256, 224, 4.0 / 3.0, false),
+        None
+    );
+    assert_eq!(AspectMode::Stretch.next(), AspectMode::Core);
+}
@@ -0,0 +1,40 @@
+// A tiny core that sends its audio in batches of different sizes and as single samples, and remembers
+// how many frames each batch call said it consumed
+mod common;
+
+use common::{audio_sample, audio_sample_batch, fake_core_api, start_emulator, test_config};
+use libretro_sys::*;
+use std::sync::Mutex;
+
+static CONSUMED: Mutex<Vec<libc::size_t>> = Mutex::new(Vec::new());
+
+// Three stereo frames then one
+const FIRST_BATCH: [i16; 6] = [1, -1, 2, -2, 3, -3];
+const SECOND_BATCH: [i16; 2] = [4, -4];
+
+unsafe extern "C" fn run() {
+    let batch = audio_sample_batch();
+    let mut consumed = CONSUMED.lock().unwrap();
+    consumed.push(batch(FIRST_BATCH.as_ptr(), 3));
+    audio_sample()(5, -5);
+    consumed.push(batch(SECOND_BATCH.as_ptr(), 1));
+    consumed.push(batch(std::ptr::null(), 0));
+}
+
+// Only one Emulator can exist at a time so everything is checked in a single test
+#[test]
+fn every_batch_is_consumed_and_kept_in_order() {
+    let core_api = CoreAPI {
+        retro_run: run,
+        ..fake_core_api()
+    };
+    let mut emulator = start_emulator(core_api, "audio_batch", &[0; 16], test_config());
+
+    emulator.run_frame().unwrap();
+    assert_eq!(*CONSUMED.lock().unwrap(), [3, 1, 0]);
+    assert_eq!(emulator.audio_data(), [1, -1, 2, -2, 3, -3, 5, -5, 4, -4]);
+
+    // Each frame's audio starts afresh
+    emulator.run_frame().unwrap();
+    assert_eq!(emulator.audio_data().len(), 10);
+}
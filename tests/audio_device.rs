@@ -0,0 +1,19 @@
+use rustro_arch::audio::matching_device_name;
+
+#[test]
+fn devices_are_matched_by_part_of_their_name_ignoring_case() {
+    let names = [
+        "Built-in Audio Analog Stereo".to_string(),
+        "HDA NVidia HDMI 0".to_string(),
+        "HDA NVidia HDMI 1".to_string(),
+    ];
+    assert_eq!(
+        matching_device_name(&names, "hdmi"),
+        Some(&"HDA NVidia HDMI 0".to_string())
+    );
+    assert_eq!(
+        matching_device_name(&names, "HDMI 1"),
+        Some(&"HDA NVidia HDMI 1".to_string())
+    );
+    assert_eq!(matching_device_name(&names, "usb"), None);
+}
@@ -0,0 +1,29 @@
+use rustro_arch::audio::{rate_control_factor, AudioRingBuffer, LinearResampler};
+
+#[test]
+fn the_rate_is_nudged_towards_a_half_full_buffer_within_the_skew() {
+    assert_eq!(rate_control_factor(0.5, 0.005), 1.0);
+    // Empty, the input is consumed 0.5% slower so there are more output frames
+    assert!((rate_control_factor(0.0, 0.005) - 0.995).abs() < 1e-12);
+    assert!((rate_control_factor(1.0, 0.005) - 1.005).abs() < 1e-12);
+    assert!((rate_control_factor(5.0, 0.005) - 1.005).abs() < 1e-12);
+    assert_eq!(rate_control_factor(0.0, 0.0), 1.0);
+
+    let buffer = AudioRingBuffer::new(100);
+    assert_eq!(buffer.fill_level(), 0.0);
+    buffer.push_samples(&[0; 50]);
+    assert_eq!(buffer.fill_level(), 0.25);
+}
+
+#[test]
+fn a_slower_rate_makes_more_output_frames() {
+    let input = vec![0i16; 2 * 10_000];
+    let frames = |factor: f64| {
+        let mut resampler = LinearResampler::new(48000, 48000);
+        resampler.set_rate_factor(factor);
+        resampler.process(&input).len() / 2
+    };
+    assert_eq!(frames(1.0), 10_000);
+    assert_eq!(frames(0.995), 10_051);
+    assert_eq!(frames(1.005), 9_951);
+}
@@ -0,0 +1,48 @@
+use rustro_arch::audio::LinearResampler;
+
+// One second of a 440 Hz stereo sine at the given sample rate
+fn sine(sample_rate: u32) -> Vec<i16> {
+    (0..sample_rate)
+        .flat_map(|frame| {
+            let time = frame as f64 / sample_rate as f64;
+            let sample = ((time * 440.0 * std::f64::consts::TAU).sin() * 16000.0) as i16;
+            [sample, sample]
+        })
+        .collect()
+}
+
+#[test]
+fn a_second_of_audio_is_a_second_at_the_output_rate() {
+    for input_rate in [32040, 44100] {
+        let output = LinearResampler::new(input_rate, 48000).process(&sine(input_rate));
+        let frames = output.len() / 2;
+        assert!(
+            frames.abs_diff(48000) <= 1,
+            "{} Hz made {} frames",
+            input_rate,
+            frames
+        );
+        // Interpolating can't go past the loudest input sample
+        assert!(output.iter().all(|sample| sample.abs() <= 16000));
+    }
+}
+
+#[test]
+fn resampling_in_batches_matches_resampling_in_one_go() {
+    for (input_rate, batch_frames) in [(32040, 534), (44100, 735)] {
+        let input = sine(input_rate);
+        let whole = LinearResampler::new(input_rate, 48000).process(&input);
+        let mut resampler = LinearResampler::new(input_rate, 48000);
+        let batched: Vec<i16> = input
+            .chunks(batch_frames * 2)
+            .flat_map(|batch| resampler.process(batch))
+            .collect();
+        // The position carries over between batches so only floating point rounding can differ, by at
+        // most a frame at the end and by one in the samples
+        assert!(batched.len().abs_diff(whole.len()) <= 2);
+        assert!(batched
+            .iter()
+            .zip(&whole)
+            .all(|(a, b)| (*a as i32 - *b as i32).abs() <= 1));
+    }
+}
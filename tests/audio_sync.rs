@@ -0,0 +1,20 @@
+use rustro_arch::audio::AudioRingBuffer;
+use std::time::{Duration, Instant};
+
+#[test]
+fn a_buffer_at_most_half_full_needs_no_waiting() {
+    let buffer = AudioRingBuffer::new(100);
+    buffer.push_samples(&[0; 100]);
+    assert_eq!(buffer.queued_frames(), 50);
+    assert!(buffer.wait_for_playback(Duration::from_secs(5)));
+}
+
+#[test]
+fn waiting_gives_up_if_the_device_never_plays() {
+    let buffer = AudioRingBuffer::new(100);
+    buffer.push_samples(&[0; 160]);
+    assert_eq!(buffer.queued_frames(), 80);
+    let start = Instant::now();
+    assert!(!buffer.wait_for_playback(Duration::from_millis(10)));
+    assert!(start.elapsed() >= Duration::from_millis(10));
+}
@@ -0,0 +1,39 @@
+use rustro_arch::config::get_color_correction;
+use rustro_arch::video::ColorCorrection;
+use std::collections::HashMap;
+
+#[test]
+fn gamma_and_brightness_of_one_change_nothing() {
+    let correction = ColorCorrection::new(1.0, 1.0);
+    assert!(correction.is_identity());
+    let mut pixels = vec![0x00123456, 0x00FF8000, 0x00000000, 0x00FFFFFF];
+    correction.apply(&mut pixels);
+    assert_eq!(pixels, [0x00123456, 0x00FF8000, 0x00000000, 0x00FFFFFF]);
+}
+
+#[test]
+fn gamma_lifts_mid_gray_and_brightness_scales_it() {
+    // 255 * (128 / 255) ^ (1 / 2) = 180.7
+    let correction = ColorCorrection::new(2.0, 1.0);
+    assert_eq!(correction.correct(128), 181);
+    assert_eq!((correction.correct(0), correction.correct(255)), (0, 255));
+    let mut pixels = vec![0x00808080];
+    correction.apply(&mut pixels);
+    assert_eq!(pixels, [0x00B5B5B5]);
+    // Brightness is clamped at white
+    let correction = ColorCorrection::new(1.0, 1.5);
+    assert_eq!((correction.correct(100), correction.correct(200)), (150, 255));
+}
+
+#[test]
+fn invalid_values_fall_back_to_no_change() {
+    let config = |gamma: &str| {
+        HashMap::from([
+            ("video_gamma".to_string(), gamma.to_string()),
+            ("video_brightness".to_string(), "1.0".to_string()),
+        ])
+    };
+    assert!(get_color_correction(&config("0")).is_identity());
+    assert!(get_color_correction(&config("bright")).is_identity());
+    assert!(!get_color_correction(&config("2.2")).is_identity());
+}
@@ -0,0 +1,224 @@
+// Not every test binary uses every helper in here
+#![allow(dead_code)]
+
+use libretro_sys::*;
+use rustro_arch::config::default_config;
+use rustro_arch::core::LibretroCore;
+use rustro_arch::Emulator;
+use std::collections::HashMap;
+use std::ffi::c_void;
+use std::sync::Mutex;
+
+// A stand in for a real core, its whole state is a frame counter plus some RAM that run() changes
+#[derive(Default)]
+pub struct MockCore {
+    pub frame_count: u32,
+    pub ram: Vec<u8>,
+}
+
+impl MockCore {
+    pub fn new() -> MockCore {
+        MockCore {
+            frame_count: 0,
+            ram: vec![0; 8],
+        }
+    }
+}
+
+impl LibretroCore for MockCore {
+    fn run(&mut self) {
+        self.frame_count += 1;
+        let index = self.frame_count as usize % self.ram.len();
+        self.ram[index] = self.ram[index].wrapping_add(1);
+    }
+
+    fn reset(&mut self) {
+        *self = MockCore::new();
+    }
+
+    fn serialize_size(&self) -> usize {
+        4 + self.ram.len()
+    }
+
+    fn serialize(&self, data: &mut [u8]) -> bool {
+        if data.len() < self.serialize_size() {
+            return false;
+        }
+        data[..4].copy_from_slice(&self.frame_count.to_le_bytes());
+        data[4..4 + self.ram.len()].copy_from_slice(&self.ram);
+        true
+    }
+
+    fn unserialize(&mut self, data: &[u8]) -> bool {
+        if data.len() != self.serialize_size() {
+            return false;
+        }
+        self.frame_count = u32::from_le_bytes(data[..4].try_into().unwrap());
+        self.ram.copy_from_slice(&data[4..]);
+        true
+    }
+}
+
+// A do-nothing libretro core built into the test, see fake_core_api. The frontend's callbacks are kept
+// here for the test's own core functions to call.
+static ENVIRONMENT: Mutex<Option<EnvironmentFn>> = Mutex::new(None);
+static VIDEO_REFRESH: Mutex<Option<VideoRefreshFn>> = Mutex::new(None);
+static AUDIO_SAMPLE: Mutex<Option<AudioSampleFn>> = Mutex::new(None);
+static AUDIO_SAMPLE_BATCH: Mutex<Option<AudioSampleBatchFn>> = Mutex::new(None);
+static INPUT_POLL: Mutex<Option<InputPollFn>> = Mutex::new(None);
+static INPUT_STATE: Mutex<Option<InputStateFn>> = Mutex::new(None);
+
+pub fn environment() -> EnvironmentFn {
+    ENVIRONMENT
+        .lock()
+        .unwrap()
+        .expect("no environment callback")
+}
+pub fn video_refresh() -> VideoRefreshFn {
+    VIDEO_REFRESH
+        .lock()
+        .unwrap()
+        .expect("no video refresh callback")
+}
+pub fn audio_sample() -> AudioSampleFn {
+    AUDIO_SAMPLE
+        .lock()
+        .unwrap()
+        .expect("no audio sample callback")
+}
+pub fn audio_sample_batch() -> AudioSampleBatchFn {
+    AUDIO_SAMPLE_BATCH
+        .lock()
+        .unwrap()
+        .expect("no audio sample batch callback")
+}
+pub fn input_poll() -> InputPollFn {
+    INPUT_POLL.lock().unwrap().expect("no input poll callback")
+}
+pub fn input_state() -> InputStateFn {
+    INPUT_STATE
+        .lock()
+        .unwrap()
+        .expect("no input state callback")
+}
+
+// Sets the environment variable the core asked for, returns whether the frontend accepted it
+pub unsafe fn set_environment_value<T>(command: libc::c_uint, value: &mut T) -> bool {
+    environment()(command, value as *mut T as *mut c_void)
+}
+
+unsafe extern "C" fn set_environment(callback: EnvironmentFn) {
+    *ENVIRONMENT.lock().unwrap() = Some(callback);
+}
+unsafe extern "C" fn set_video_refresh(callback: VideoRefreshFn) {
+    *VIDEO_REFRESH.lock().unwrap() = Some(callback);
+}
+unsafe extern "C" fn set_audio_sample(callback: AudioSampleFn) {
+    *AUDIO_SAMPLE.lock().unwrap() = Some(callback);
+}
+unsafe extern "C" fn set_audio_sample_batch(callback: AudioSampleBatchFn) {
+    *AUDIO_SAMPLE_BATCH.lock().unwrap() = Some(callback);
+}
+unsafe extern "C" fn set_input_poll(callback: InputPollFn) {
+    *INPUT_POLL.lock().unwrap() = Some(callback);
+}
+unsafe extern "C" fn set_input_state(callback: InputStateFn) {
+    *INPUT_STATE.lock().unwrap() = Some(callback);
+}
+unsafe extern "C" fn init() {}
+unsafe extern "C" fn deinit() {}
+unsafe extern "C" fn api_version() -> libc::c_uint {
+    API_VERSION
+}
+unsafe extern "C" fn get_system_info(_: *mut SystemInfo) {}
+unsafe extern "C" fn get_system_av_info(info: *mut SystemAvInfo) {
+    write_av_info(info, 1, 1);
+}
+// A 60 fps core with 44.1kHz audio and the given base size
+pub unsafe fn write_av_info(info: *mut SystemAvInfo, width: u32, height: u32) {
+    (*info).geometry.base_width = width;
+    (*info).geometry.base_height = height;
+    (*info).timing.fps = 60.0;
+    (*info).timing.sample_rate = 44100.0;
+}
+unsafe extern "C" fn set_controller_port_device(_: libc::c_uint, _: libc::c_uint) {}
+unsafe extern "C" fn reset() {}
+unsafe extern "C" fn run() {}
+unsafe extern "C" fn serialize_size() -> libc::size_t {
+    0
+}
+unsafe extern "C" fn serialize(_: *mut c_void, _: libc::size_t) {}
+unsafe extern "C" fn unserialize(_: *const c_void, _: libc::size_t) -> bool {
+    true
+}
+unsafe extern "C" fn cheat_reset() {}
+unsafe extern "C" fn cheat_set(_: libc::c_uint, _: bool, _: *const libc::c_char) {}
+unsafe extern "C" fn load_game(_: *const GameInfo) -> bool {
+    true
+}
+unsafe extern "C" fn load_game_special(
+    _: libc::c_uint,
+    _: *const GameInfo,
+    _: libc::size_t,
+) -> bool {
+    false
+}
+unsafe extern "C" fn unload_game() {}
+unsafe extern "C" fn get_region() -> libc::c_uint {
+    0
+}
+unsafe extern "C" fn get_memory_data(_: libc::c_uint) -> *mut c_void {
+    std::ptr::null_mut()
+}
+unsafe extern "C" fn get_memory_size(_: libc::c_uint) -> libc::size_t {
+    0
+}
+
+// Tests swap in the functions their core needs, e.g. CoreAPI { retro_run: run, ..fake_core_api() }
+pub fn fake_core_api() -> CoreAPI {
+    CoreAPI {
+        retro_set_environment: set_environment,
+        retro_set_video_refresh: set_video_refresh,
+        retro_set_audio_sample: set_audio_sample,
+        retro_set_audio_sample_batch: set_audio_sample_batch,
+        retro_set_input_poll: set_input_poll,
+        retro_set_input_state: set_input_state,
+        retro_init: init,
+        retro_deinit: deinit,
+        retro_api_version: api_version,
+        retro_get_system_info: get_system_info,
+        retro_get_system_av_info: get_system_av_info,
+        retro_set_controller_port_device: set_controller_port_device,
+        retro_reset: reset,
+        retro_run: run,
+        retro_serialize_size: serialize_size,
+        retro_serialize: serialize,
+        retro_unserialize: unserialize,
+        retro_cheat_reset: cheat_reset,
+        retro_cheat_set: cheat_set,
+        retro_load_game: load_game,
+        retro_load_game_special: load_game_special,
+        retro_unload_game: unload_game,
+        retro_get_region: get_region,
+        retro_get_memory_data: get_memory_data,
+        retro_get_memory_size: get_memory_size,
+    }
+}
+
+// The frontend's built in defaults, tests change the ones they're about
+pub fn test_config() -> HashMap<String, String> {
+    default_config()
+}
+
+// Loads rustro_arch_{name}.rom, holding `rom`, into the core. Only one Emulator can exist at a time
+// so each test binary starts one.
+pub fn start_emulator(
+    core_api: CoreAPI,
+    name: &str,
+    rom: &[u8],
+    config: HashMap<String, String>,
+) -> Emulator {
+    let rom_path = std::env::temp_dir().join(format!("rustro_arch_{}.rom", name));
+    std::fs::write(&rom_path, rom).unwrap();
+    unsafe { Emulator::from_core_api(core_api, rom_path.to_str().unwrap(), config) }.unwrap()
+}
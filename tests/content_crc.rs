@@ -0,0 +1,12 @@
+// A tiny core that loads anything, to check the CRC32 of the ROM it's given
+mod common;
+
+use common::{fake_core_api, start_emulator, test_config};
+
+#[test]
+fn the_roms_crc32_is_worked_out_at_load() {
+    let emulator = start_emulator(fake_core_api(), "content_crc", b"123456789", test_config());
+
+    // The standard CRC32 check value
+    assert_eq!(emulator.content_crc32(), Some(0xCBF43926));
+}
@@ -0,0 +1,60 @@
+use libretro_sys::{DEVICE_ANALOG, DEVICE_JOYPAD, DEVICE_LIGHTGUN, DEVICE_NONE, DEVICE_TYPE_SHIFT};
+use rustro_arch::controller_info::{default_device, describe_device, parse_device, ControllerType};
+
+const DUALSHOCK: u32 = (1 << DEVICE_TYPE_SHIFT) | DEVICE_ANALOG;
+
+fn controller(description: &str, id: u32) -> ControllerType {
+    ControllerType {
+        description: description.to_string(),
+        id,
+    }
+}
+
+fn playstation_port() -> Vec<ControllerType> {
+    vec![
+        controller("None", DEVICE_NONE),
+        controller("PlayStation Controller", DEVICE_JOYPAD),
+        controller("DualShock", DUALSHOCK),
+        controller("Guncon", DEVICE_LIGHTGUN),
+    ]
+}
+
+#[test]
+fn the_analog_pad_is_preferred_then_the_joypad() {
+    assert_eq!(default_device(&playstation_port()), Some(DUALSHOCK));
+    let digital = [
+        controller("None", DEVICE_NONE),
+        controller("Gamepad", DEVICE_JOYPAD),
+    ];
+    assert_eq!(default_device(&digital), Some(DEVICE_JOYPAD));
+    let guns_only = [
+        controller("None", DEVICE_NONE),
+        controller("Zapper", DEVICE_LIGHTGUN),
+    ];
+    assert_eq!(default_device(&guns_only), Some(DEVICE_LIGHTGUN));
+    // Nothing listed leaves the core's default alone
+    assert_eq!(default_device(&[]), None);
+}
+
+#[test]
+fn devices_are_picked_by_number_generic_name_or_description() {
+    let port = playstation_port();
+    assert_eq!(parse_device("", &port), Ok(Some(DUALSHOCK)));
+    assert_eq!(parse_device("1", &port), Ok(Some(DEVICE_JOYPAD)));
+    assert_eq!(parse_device("lightgun", &port), Ok(Some(DEVICE_LIGHTGUN)));
+    assert_eq!(
+        parse_device("playstation controller", &port),
+        Ok(Some(DEVICE_JOYPAD))
+    );
+    assert!(parse_device("steering wheel", &port).is_err());
+}
+
+#[test]
+fn devices_are_described_by_the_cores_name_for_them() {
+    let port = playstation_port();
+    assert_eq!(
+        describe_device(DUALSHOCK, &port),
+        format!("DualShock (id {})", DUALSHOCK)
+    );
+    assert_eq!(describe_device(DEVICE_LIGHTGUN, &[]), "lightgun (id 4)");
+}
@@ -0,0 +1,41 @@
+use rustro_arch::core_info::CoreInfo;
+
+fn core_info(valid_extensions: &[&str]) -> CoreInfo {
+    CoreInfo {
+        library_name: "Gambatte".to_string(),
+        library_version: "v0.5.0".to_string(),
+        valid_extensions: valid_extensions
+            .iter()
+            .map(|extension| extension.to_string())
+            .collect(),
+        need_fullpath: false,
+        block_extract: false,
+    }
+}
+
+#[test]
+fn roms_with_a_listed_extension_pass_whatever_the_case() {
+    let gambatte = core_info(&["gb", "gbc", "dmg"]);
+    assert!(gambatte.check_rom_extension("/roms/Tetris.gb").is_ok());
+    assert!(gambatte
+        .check_rom_extension("roms/Pokemon Crystal.GBC")
+        .is_ok());
+    assert!(gambatte
+        .check_rom_extension("https://example.com/roms/game.gb?v=2")
+        .is_ok());
+    // A core that lists nothing takes anything
+    assert!(core_info(&[]).check_rom_extension("/roms/game.bin").is_ok());
+}
+
+#[test]
+fn other_roms_are_refused_with_the_extensions_the_core_loads() {
+    let gambatte = core_info(&["gb", "gbc", "dmg"]);
+    assert_eq!(
+        gambatte.check_rom_extension("/roms/Sonic.md"),
+        Err(
+            "/roms/Sonic.md doesn't look like a ROM for Gambatte, it loads .gb .gbc .dmg"
+                .to_string()
+        )
+    );
+    assert!(gambatte.check_rom_extension("/roms/gb").is_err());
+}
@@ -0,0 +1,87 @@
+use rustro_arch::core_options::{
+    apply_core_option_values, core_option, game_options_path, load_game_options,
+    parse_core_option_argument, parse_variable_definition, save_game_options,
+};
+use std::collections::{BTreeMap, HashMap};
+
+#[test]
+fn core_option_arguments_are_split_on_the_first_equals() {
+    assert_eq!(
+        parse_core_option_argument("mgba_palette=a=b"),
+        Ok(("mgba_palette".to_string(), "a=b".to_string()))
+    );
+    assert!(parse_core_option_argument("mgba_palette").is_err());
+    assert!(parse_core_option_argument("=green").is_err());
+}
+
+#[test]
+fn only_values_the_core_listed_are_applied() {
+    let mut variables = vec![
+        parse_variable_definition("palette", "Palette; green|grey|amber"),
+        parse_variable_definition("speed", "Speed; normal|fast"),
+    ];
+    let values = BTreeMap::from([
+        ("palette".to_string(), "amber".to_string()),
+        ("speed".to_string(), "ludicrous".to_string()),
+    ]);
+    apply_core_option_values(&mut variables, &values);
+    assert_eq!(variables[0].value, "amber");
+    assert_eq!(variables[1].value, "normal");
+}
+
+#[test]
+fn structured_options_use_their_default_value() {
+    let values = vec!["green".to_string(), "grey".to_string()];
+    assert_eq!(
+        core_option("palette", "Palette", values.clone(), "grey").value,
+        "grey"
+    );
+    // A default that isn't one of the values falls back to the first like the legacy variables
+    assert_eq!(
+        core_option("palette", "Palette", values.clone(), "").value,
+        "green"
+    );
+    assert_eq!(
+        core_option("palette", "Palette", values, "amber").value,
+        "green"
+    );
+    assert_eq!(
+        parse_variable_definition("palette", "Palette; green|grey"),
+        core_option(
+            "palette",
+            "Palette",
+            vec!["green".to_string(), "grey".to_string()],
+            ""
+        )
+    );
+}
+
+#[test]
+fn game_options_are_saved_next_to_the_rom_and_read_back() {
+    let rom_path = std::env::temp_dir().join("rustro_arch_game_options.gb");
+    let path = game_options_path(rom_path.to_str().unwrap());
+    assert_eq!(
+        path,
+        std::env::temp_dir().join("rustro_arch_game_options.opt")
+    );
+    let _ = std::fs::remove_file(&path);
+    assert!(load_game_options(&path).unwrap().is_empty());
+
+    let mut variables = vec![
+        parse_variable_definition("speed", "Speed; normal|fast"),
+        parse_variable_definition("palette", "Palette; green|grey|amber"),
+    ];
+    variables[1].value = "amber".to_string();
+    save_game_options(&path, &variables).unwrap();
+    assert_eq!(
+        std::fs::read_to_string(&path).unwrap(),
+        "palette = \"amber\"\nspeed = \"normal\"\n"
+    );
+    assert_eq!(
+        load_game_options(&path).unwrap(),
+        HashMap::from([
+            ("palette".to_string(), "amber".to_string()),
+            ("speed".to_string(), "normal".to_string()),
+        ])
+    );
+}
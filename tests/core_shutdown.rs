@@ -0,0 +1,29 @@
+// A tiny core that asks the frontend to shut down (ENVIRONMENT_SHUTDOWN) on its third frame
+mod common;
+
+use common::{environment, fake_core_api, start_emulator, test_config};
+use libretro_sys::*;
+use std::sync::atomic::{AtomicU32, Ordering};
+
+static FRAMES_RUN: AtomicU32 = AtomicU32::new(0);
+
+unsafe extern "C" fn run() {
+    if FRAMES_RUN.fetch_add(1, Ordering::SeqCst) == 2 {
+        environment()(ENVIRONMENT_SHUTDOWN, std::ptr::null_mut());
+    }
+}
+
+// Only one Emulator can exist at a time so everything is checked in a single test
+#[test]
+fn the_core_can_ask_to_shut_down() {
+    let core_api = CoreAPI {
+        retro_run: run,
+        ..fake_core_api()
+    };
+    let mut emulator = start_emulator(core_api, "core_shutdown", &[0; 16], test_config());
+
+    emulator.run_to_frame(2, None).unwrap();
+    assert!(!emulator.should_quit());
+    emulator.run_frame().unwrap();
+    assert!(emulator.should_quit());
+}
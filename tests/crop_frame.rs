@@ -0,0 +1,42 @@
+use rustro_arch::video::crop_frame;
+
+const PADDING: u32 = 0x00DEAD00;
+
+// A width x height frame with rows `stride` pixels apart, the visible pixels numbered from 1 and the
+// padding after each row set to PADDING
+fn padded_frame(width: usize, height: usize, stride: usize) -> Vec<u32> {
+    (0..stride * height)
+        .map(|index| {
+            let (row, column) = (index / stride, index % stride);
+            if column < width {
+                (row * width + column + 1) as u32
+            } else {
+                PADDING
+            }
+        })
+        .collect()
+}
+
+#[test]
+fn the_padding_past_the_width_is_dropped() {
+    let frame = padded_frame(3, 2, 5);
+    assert_eq!(crop_frame(&frame, 3, 2, 5), Some(vec![1, 2, 3, 4, 5, 6]));
+    // Cores may not send the padding after the last row
+    assert_eq!(crop_frame(&frame[..8], 3, 2, 5), Some(vec![1, 2, 3, 4, 5, 6]));
+    assert_eq!(crop_frame(&frame, 3, 1, 5), Some(vec![1, 2, 3]));
+}
+
+#[test]
+fn a_stride_of_the_width_is_the_frame_as_it_is() {
+    let frame = padded_frame(3, 2, 3);
+    assert_eq!(crop_frame(&frame, 3, 2, 3), Some(frame.clone()));
+}
+
+#[test]
+fn frames_too_small_for_their_size_are_none() {
+    let frame = padded_frame(3, 2, 5);
+    assert_eq!(crop_frame(&frame[..7], 3, 2, 5), None);
+    assert_eq!(crop_frame(&frame, 3, 2, 2), None);
+    assert_eq!(crop_frame(&frame, 0, 2, 5), None);
+    assert_eq!(crop_frame(&frame, 3, 0, 5), None);
+}
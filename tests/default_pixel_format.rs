@@ -0,0 +1,33 @@
+// A tiny core that never calls ENVIRONMENT_SET_PIXEL_FORMAT and draws a 2x1 frame of red then blue in
+// 0RGB1555, the format libretro says a frontend should assume
+mod common;
+
+use common::{fake_core_api, start_emulator, test_config, video_refresh, write_av_info};
+use libretro_sys::*;
+use std::ffi::c_void;
+
+unsafe extern "C" fn get_system_av_info(info: *mut SystemAvInfo) {
+    write_av_info(info, 2, 1);
+}
+unsafe extern "C" fn run() {
+    let frame: [u16; 2] = [0x7C00, 0x001F];
+    video_refresh()(frame.as_ptr() as *const c_void, 2, 1, 4);
+}
+
+#[test]
+fn frames_are_read_as_0rgb1555_when_the_core_never_picks_a_format() {
+    let core_api = CoreAPI {
+        retro_get_system_av_info: get_system_av_info,
+        retro_run: run,
+        ..fake_core_api()
+    };
+    let mut emulator = start_emulator(core_api, "default_pixel_format", &[0; 16], test_config());
+
+    assert_eq!(emulator.pixel_format(), PixelFormat::ARGB1555);
+    assert_eq!(emulator.bytes_per_pixel(), 2);
+    emulator.run_frame().unwrap();
+    assert_eq!(
+        emulator.visible_frame().unwrap(),
+        vec![0x00FF0000, 0x000000FF]
+    );
+}
@@ -0,0 +1,19 @@
+use rustro_arch::input::ExitConfirmation;
+use std::time::{Duration, Instant};
+
+#[test]
+fn a_second_press_within_the_window_quits() {
+    let start = Instant::now();
+    let mut exit_confirmation = ExitConfirmation::new(Duration::from_secs(1));
+    assert!(!exit_confirmation.press(start));
+    assert!(exit_confirmation.press(start + Duration::from_millis(500)));
+}
+
+#[test]
+fn a_late_second_press_starts_over() {
+    let start = Instant::now();
+    let mut exit_confirmation = ExitConfirmation::new(Duration::from_secs(1));
+    assert!(!exit_confirmation.press(start));
+    assert!(!exit_confirmation.press(start + Duration::from_secs(2)));
+    assert!(exit_confirmation.press(start + Duration::from_millis(2500)));
+}
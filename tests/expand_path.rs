@@ -0,0 +1,49 @@
+use rustro_arch::config::expand_path;
+use std::path::PathBuf;
+
+#[test]
+fn tilde_expands_to_the_home_directory() {
+    let home = std::env::var("HOME").unwrap();
+    assert_eq!(
+        expand_path("~/roms/game.gb"),
+        PathBuf::from(format!("{}/roms/game.gb", home))
+    );
+    assert_eq!(expand_path("~"), PathBuf::from(&home));
+    // Only a leading ~ on its own is the home directory
+    assert_eq!(expand_path("~user/game.gb"), PathBuf::from("~user/game.gb"));
+    assert_eq!(
+        expand_path("roms/~/game.gb"),
+        PathBuf::from("roms/~/game.gb")
+    );
+}
+
+#[test]
+fn environment_variables_expand_in_every_style() {
+    std::env::set_var("RUSTRO_ARCH_TEST_CORES", "/opt/cores");
+    assert_eq!(
+        expand_path("$RUSTRO_ARCH_TEST_CORES/gambatte.so"),
+        PathBuf::from("/opt/cores/gambatte.so")
+    );
+    assert_eq!(
+        expand_path("${RUSTRO_ARCH_TEST_CORES}_old/a.so"),
+        PathBuf::from("/opt/cores_old/a.so")
+    );
+    assert_eq!(
+        expand_path("%RUSTRO_ARCH_TEST_CORES%\\a.dll"),
+        PathBuf::from("/opt/cores\\a.dll")
+    );
+}
+
+#[test]
+fn unset_variables_are_left_alone() {
+    std::env::remove_var("RUSTRO_ARCH_TEST_UNSET");
+    assert_eq!(
+        expand_path("$RUSTRO_ARCH_TEST_UNSET/a.gb"),
+        PathBuf::from("$RUSTRO_ARCH_TEST_UNSET/a.gb")
+    );
+    assert_eq!(
+        expand_path("%RUSTRO_ARCH_TEST_UNSET%/a.gb"),
+        PathBuf::from("%RUSTRO_ARCH_TEST_UNSET%/a.gb")
+    );
+    assert_eq!(expand_path("100% $ {x}.gb"), PathBuf::from("100% $ {x}.gb"));
+}
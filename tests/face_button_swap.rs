@@ -0,0 +1,49 @@
+use libretro_sys::{
+    DEVICE_ID_JOYPAD_A, DEVICE_ID_JOYPAD_B, DEVICE_ID_JOYPAD_X, DEVICE_ID_JOYPAD_Y,
+};
+use rustro_arch::input::FaceButtonSwap;
+use std::collections::HashMap;
+
+fn pressed(buttons: &[u32]) -> Vec<i16> {
+    let mut pressed = vec![0; 16];
+    for button in buttons {
+        pressed[*button as usize] = 1;
+    }
+    pressed
+}
+
+#[test]
+fn swapping_moves_each_press_to_the_other_button() {
+    let mut buttons = pressed(&[DEVICE_ID_JOYPAD_A, DEVICE_ID_JOYPAD_X]);
+    FaceButtonSwap {
+        ab: true,
+        xy: false,
+    }
+    .apply(&mut buttons);
+    assert_eq!(buttons, pressed(&[DEVICE_ID_JOYPAD_B, DEVICE_ID_JOYPAD_X]));
+    FaceButtonSwap { ab: true, xy: true }.apply(&mut buttons);
+    assert_eq!(buttons, pressed(&[DEVICE_ID_JOYPAD_A, DEVICE_ID_JOYPAD_Y]));
+    // Both held stays both held
+    let mut buttons = pressed(&[DEVICE_ID_JOYPAD_A, DEVICE_ID_JOYPAD_B]);
+    FaceButtonSwap { ab: true, xy: true }.apply(&mut buttons);
+    assert_eq!(buttons, pressed(&[DEVICE_ID_JOYPAD_A, DEVICE_ID_JOYPAD_B]));
+}
+
+#[test]
+fn nothing_is_swapped_by_default() {
+    let config = HashMap::from([
+        ("input_swap_ab".to_string(), "false".to_string()),
+        ("input_swap_xy".to_string(), "true".to_string()),
+    ]);
+    let swap = FaceButtonSwap::from_config(&config);
+    assert_eq!(
+        swap,
+        FaceButtonSwap {
+            ab: false,
+            xy: true
+        }
+    );
+    let mut buttons = pressed(&[DEVICE_ID_JOYPAD_A]);
+    FaceButtonSwap::default().apply(&mut buttons);
+    assert_eq!(buttons, pressed(&[DEVICE_ID_JOYPAD_A]));
+}
@@ -0,0 +1,17 @@
+use rustro_arch::frame_hash::frame_crc32;
+
+#[test]
+fn frames_hash_their_xrgb8888_bytes() {
+    // zlib.crc32 of the pixels as little-endian bytes
+    assert_eq!(frame_crc32(&[0x00FF0000, 0x000000FF]), 0x5fe37254);
+    assert_eq!(frame_crc32(&[]), 0);
+}
+
+#[test]
+fn any_changed_pixel_changes_the_hash() {
+    let frame = vec![0x00123456; 160 * 144];
+    let mut changed = frame.clone();
+    changed[160 * 144 - 1] = 0x00123457;
+    assert_ne!(frame_crc32(&frame), frame_crc32(&changed));
+    assert_eq!(frame_crc32(&frame), frame_crc32(&frame.clone()));
+}
@@ -0,0 +1,55 @@
+// A tiny core that asks for ENVIRONMENT_SET_FRAME_TIME_CALLBACK and remembers the frame time it was
+// given before each retro_run
+mod common;
+
+use common::{fake_core_api, set_environment_value, start_emulator, test_config};
+use libretro_sys::*;
+use rustro_arch::Emulator;
+use std::sync::Mutex;
+use std::time::Duration;
+
+const REFERENCE_USEC: Usec = 16_667;
+
+static LAST_FRAME_TIME: Mutex<Option<Usec>> = Mutex::new(None);
+
+unsafe extern "C" fn frame_time(usec: Usec) {
+    *LAST_FRAME_TIME.lock().unwrap() = Some(usec);
+}
+
+unsafe extern "C" fn load_game(_: *const GameInfo) -> bool {
+    let mut callback = FrameTimeCallback {
+        callback: frame_time,
+        reference: REFERENCE_USEC,
+    };
+    set_environment_value(ENVIRONMENT_SET_FRAME_TIME_CALLBACK, &mut callback)
+}
+
+fn run_and_get_frame_time(emulator: &mut Emulator) -> Usec {
+    emulator.run_frame().unwrap();
+    LAST_FRAME_TIME.lock().unwrap().take().unwrap()
+}
+
+// Only one Emulator can exist at a time so everything is checked in a single test
+#[test]
+fn the_core_gets_the_real_frame_time_unless_it_is_fixed() {
+    let core_api = CoreAPI {
+        retro_load_game: load_game,
+        ..fake_core_api()
+    };
+    let mut emulator = start_emulator(core_api, "frame_time", &[0; 16], test_config());
+
+    // Nothing to measure from on the first frame
+    assert_eq!(run_and_get_frame_time(&mut emulator), REFERENCE_USEC);
+    std::thread::sleep(Duration::from_millis(50));
+    assert!(run_and_get_frame_time(&mut emulator) >= 50_000);
+
+    // Fast forward or pause, then the first frame afterwards doesn't count the time spent paused
+    emulator.set_fixed_frame_time(true);
+    assert_eq!(run_and_get_frame_time(&mut emulator), REFERENCE_USEC);
+    std::thread::sleep(Duration::from_millis(50));
+    emulator.set_fixed_frame_time(false);
+    assert_eq!(run_and_get_frame_time(&mut emulator), REFERENCE_USEC);
+    std::thread::sleep(Duration::from_millis(20));
+    let usec = run_and_get_frame_time(&mut emulator);
+    assert!((20_000..50_000).contains(&usec));
+}
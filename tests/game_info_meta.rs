@@ -0,0 +1,34 @@
+use rustro_arch::game_info_meta;
+use std::ffi::CString;
+
+fn rom_path(name: &str) -> String {
+    let rom_path = std::env::temp_dir().join(name);
+    std::fs::write(&rom_path, [0u8; 16]).unwrap();
+    rom_path.to_string_lossy().into_owned()
+}
+
+#[test]
+fn meta_is_null_unless_given_or_next_to_the_rom() {
+    let rom = rom_path("rustro_arch_no_meta.rom");
+    std::fs::remove_file(format!("{}.meta", rom)).ok();
+    assert_eq!(game_info_meta(&rom, None), Ok(None));
+    assert_eq!(
+        game_info_meta(&rom, Some("region=jp")),
+        Ok(Some(CString::new("region=jp").unwrap()))
+    );
+}
+
+#[test]
+fn a_meta_file_is_used_and_the_command_line_wins() {
+    let rom = rom_path("rustro_arch_meta.rom");
+    std::fs::write(format!("{}.meta", rom), "disc=2\n").unwrap();
+    assert_eq!(
+        game_info_meta(&rom, None),
+        Ok(Some(CString::new("disc=2").unwrap()))
+    );
+    assert_eq!(
+        game_info_meta(&rom, Some("disc=1")),
+        Ok(Some(CString::new("disc=1").unwrap()))
+    );
+    assert!(game_info_meta(&rom, Some("nul\0byte")).is_err());
+}
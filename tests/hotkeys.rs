@@ -0,0 +1,91 @@
+use minifb::Key;
+use rustro_arch::config::{get_max_save_slots, get_quick_swap_slot};
+use rustro_arch::input::{find_key_collisions, KeyCollision, KeyPressTracker, HOTKEY_BINDINGS};
+use rustro_arch::save_state::{next_save_slot, quick_swap_save_slot};
+use std::collections::HashMap;
+
+#[test]
+fn holding_a_key_only_presses_it_once() {
+    let mut tracker = KeyPressTracker::new();
+    assert_eq!(tracker.newly_pressed(&[Key::F7]), vec![Key::F7]);
+    for _ in 0..30 {
+        assert!(tracker.newly_pressed(&[Key::F7]).is_empty());
+    }
+    assert!(tracker.newly_pressed(&[]).is_empty());
+    assert_eq!(tracker.newly_pressed(&[Key::F7]), vec![Key::F7]);
+}
+
+#[test]
+fn a_new_key_is_pressed_while_another_is_held() {
+    let mut tracker = KeyPressTracker::new();
+    tracker.newly_pressed(&[Key::A]);
+    assert_eq!(tracker.newly_pressed(&[Key::A, Key::F2]), vec![Key::F2]);
+    assert!(tracker.newly_pressed(&[Key::F2]).is_empty());
+}
+
+// One tap of the slot increase key held over several frames, as it would be at 60 fps
+#[test]
+fn a_held_slot_key_changes_the_slot_by_one() {
+    let config = HashMap::from([("savestate_max_slots".to_string(), "10".to_string())]);
+    let max_save_slots = get_max_save_slots(&config);
+    let mut tracker = KeyPressTracker::new();
+    let mut current_save_slot = 0;
+    let held_frames = std::iter::repeat_n(vec![Key::F7], 12).chain([vec![], vec![]]);
+    for held_keys in held_frames {
+        for _ in tracker.newly_pressed(&held_keys) {
+            current_save_slot = next_save_slot(current_save_slot, max_save_slots, true, true);
+        }
+    }
+    assert_eq!(current_save_slot, 1);
+}
+
+// Slot 0 and the quick swap slot alternate, from a slot the increase key moved to it goes back to 0
+#[test]
+fn the_quick_swap_key_alternates_between_slot_0_and_the_quick_swap_slot() {
+    let config = HashMap::from([
+        ("savestate_max_slots".to_string(), "10".to_string()),
+        ("quick_swap_slot".to_string(), "5".to_string()),
+    ]);
+    let quick_swap_slot = get_quick_swap_slot(&config, get_max_save_slots(&config));
+    assert_eq!(quick_swap_slot, 5);
+    let mut current_save_slot = quick_swap_save_slot(0, quick_swap_slot);
+    assert_eq!(current_save_slot, 5);
+    current_save_slot = quick_swap_save_slot(current_save_slot, quick_swap_slot);
+    assert_eq!(current_save_slot, 0);
+    current_save_slot = next_save_slot(current_save_slot, 10, true, true);
+    assert_eq!(quick_swap_save_slot(current_save_slot, quick_swap_slot), 0);
+
+    // It has to be another slot that exists
+    let config = HashMap::from([("quick_swap_slot".to_string(), "10".to_string())]);
+    assert_eq!(get_quick_swap_slot(&config, 10), 1);
+}
+
+#[test]
+fn keys_bound_to_a_game_button_and_a_hotkey_are_found() {
+    let buttons = ["a", "b", "x", "y", "l", "r", "down", "up", "right", "left", "start", "select"];
+    let mut config: HashMap<String, String> = buttons
+        .iter()
+        .map(|button| format!("input_player1_{}", button))
+        .chain(HOTKEY_BINDINGS.iter().map(|hotkey| hotkey.to_string()))
+        .map(|key| (key, "nul".to_string()))
+        .collect();
+    config.insert("input_player1_select".to_string(), "space".to_string());
+    config.insert("input_player1_start".to_string(), "enter,f2".to_string());
+    config.insert("input_save_state".to_string(), "f2".to_string());
+    config.insert("input_reset".to_string(), "h,space".to_string());
+    assert_eq!(
+        find_key_collisions(&config),
+        vec![
+            KeyCollision {
+                key: "f2".to_string(),
+                game_input: "input_player1_start".to_string(),
+                hotkey: "input_save_state".to_string(),
+            },
+            KeyCollision {
+                key: "space".to_string(),
+                game_input: "input_player1_select".to_string(),
+                hotkey: "input_reset".to_string(),
+            },
+        ]
+    );
+}
@@ -0,0 +1,54 @@
+use rustro_arch::input_server::{spawn_input_server, InputServerEvent, RemoteInput};
+use std::io::Write;
+use std::net::TcpStream;
+use std::time::Duration;
+
+#[test]
+fn packets_are_forwarded_until_the_client_disconnects() {
+    let address = "127.0.0.1:47613";
+    let events = spawn_input_server(address).unwrap();
+    let mut client = TcpStream::connect(address).unwrap();
+    for (frame, mask) in [(1, 0b1), (1, 0b10), (2, 0b100)] {
+        client
+            .write_all(&RemoteInput { frame, mask }.to_bytes())
+            .unwrap();
+    }
+    drop(client);
+
+    let next_event = || events.recv_timeout(Duration::from_secs(5)).unwrap();
+    assert_eq!(
+        next_event(),
+        InputServerEvent::Input(RemoteInput {
+            frame: 1,
+            mask: 0b1
+        })
+    );
+    // Frame 1 again is stale so it's dropped
+    assert_eq!(
+        next_event(),
+        InputServerEvent::Input(RemoteInput {
+            frame: 2,
+            mask: 0b100
+        })
+    );
+    assert_eq!(next_event(), InputServerEvent::Disconnected);
+
+    // The next client starts counting frames again
+    let mut client = TcpStream::connect(address).unwrap();
+    client
+        .write_all(
+            &RemoteInput {
+                frame: 0,
+                mask: 0xFFFF,
+            }
+            .to_bytes(),
+        )
+        .unwrap();
+    assert_eq!(
+        next_event(),
+        InputServerEvent::Input(RemoteInput {
+            frame: 0,
+            mask: 0xFFFF
+        })
+    );
+}
@@ -0,0 +1,119 @@
+use gilrs::{Axis, Button};
+use rustro_arch::input::{
+    describe_input, joypad_button_name, setup_joypad_axis_bindings, setup_joypad_device_map,
+    AxisBinding,
+};
+use std::collections::HashMap;
+
+fn config(bindings: &[(&str, &str)]) -> HashMap<String, String> {
+    bindings
+        .iter()
+        .map(|(key, value)| (key.to_string(), value.to_string()))
+        .collect()
+}
+
+#[test]
+fn button_bindings_replace_the_default_button() {
+    let joypad_device_map = setup_joypad_device_map(&config(&[
+        ("input_player1_a_btn", "1"),
+        ("input_player1_b_btn", "0"),
+        ("input_player1_start_btn", "5"),
+        ("input_player1_up_btn", "h0up"),
+    ]));
+    let a = libretro_sys::DEVICE_ID_JOYPAD_A as usize;
+    let b = libretro_sys::DEVICE_ID_JOYPAD_B as usize;
+    let start = libretro_sys::DEVICE_ID_JOYPAD_START as usize;
+    assert_eq!(joypad_device_map.get(&Button::East), Some(&a));
+    assert_eq!(joypad_device_map.get(&Button::South), Some(&b));
+    assert_eq!(joypad_device_map.get(&Button::Mode), Some(&start));
+    assert_eq!(joypad_device_map.get(&Button::Start), None);
+    assert_eq!(
+        joypad_device_map.get(&Button::DPadUp),
+        Some(&(libretro_sys::DEVICE_ID_JOYPAD_UP as usize))
+    );
+}
+
+#[test]
+fn unset_and_unknown_bindings_keep_the_defaults() {
+    let joypad_device_map = setup_joypad_device_map(&config(&[
+        ("input_player1_a_btn", "nul"),
+        ("input_player1_b_btn", ""),
+        ("input_player1_x_btn", "99"),
+        ("input_player1_y_btn", "h1left"),
+    ]));
+    assert_eq!(joypad_device_map, setup_joypad_device_map(&HashMap::new()));
+    assert_eq!(
+        joypad_device_map.get(&Button::South),
+        Some(&(libretro_sys::DEVICE_ID_JOYPAD_A as usize))
+    );
+}
+
+#[test]
+fn axis_bindings_flip_the_y_axes() {
+    let bindings = setup_joypad_axis_bindings(&config(&[
+        ("input_player1_l2_axis", "+4"),
+        ("input_player1_up_axis", "-1"),
+        ("input_player1_right_axis", "+0"),
+        ("input_player1_down_axis", "1"),
+        ("input_player1_left_axis", "nul"),
+    ]));
+    assert_eq!(
+        bindings,
+        vec![
+            AxisBinding {
+                axis: Axis::LeftZ,
+                positive: true,
+                button: libretro_sys::DEVICE_ID_JOYPAD_L2 as usize
+            },
+            AxisBinding {
+                axis: Axis::LeftStickY,
+                positive: true,
+                button: libretro_sys::DEVICE_ID_JOYPAD_UP as usize
+            },
+            AxisBinding {
+                axis: Axis::LeftStickX,
+                positive: true,
+                button: libretro_sys::DEVICE_ID_JOYPAD_RIGHT as usize
+            },
+        ]
+    );
+    assert!(bindings[1].is_pressed(0.8, 0.5));
+    assert!(!bindings[1].is_pressed(-0.8, 0.5));
+    assert!(!bindings[1].is_pressed(0.3, 0.5));
+}
+
+// Only a sign then one of the six SDL axis numbers is an axis, the rest are skipped with a warning
+#[test]
+fn malformed_and_disabled_axis_bindings_are_ignored() {
+    let bindings = setup_joypad_axis_bindings(&config(&[
+        ("input_player1_a_axis", "+"),
+        ("input_player1_b_axis", "-6"),
+        ("input_player1_x_axis", "+-1"),
+        ("input_player1_y_axis", "h0up"),
+        ("input_player1_l_axis", ""),
+        ("input_player1_r_axis", "nul"),
+        ("input_player1_left_axis", " -0 "),
+    ]));
+    assert_eq!(
+        bindings,
+        vec![AxisBinding {
+            axis: Axis::LeftStickX,
+            positive: false,
+            button: libretro_sys::DEVICE_ID_JOYPAD_LEFT as usize
+        }]
+    );
+    assert!(bindings[0].is_pressed(-0.8, 0.5));
+    assert!(setup_joypad_axis_bindings(&HashMap::new()).is_empty());
+}
+
+#[test]
+fn inputs_are_described_by_the_button_they_press() {
+    let a = libretro_sys::DEVICE_ID_JOYPAD_A as usize;
+    assert_eq!(joypad_button_name(a), "a");
+    assert_eq!(
+        joypad_button_name(libretro_sys::DEVICE_ID_JOYPAD_L2 as usize),
+        "l2"
+    );
+    assert_eq!(describe_input("key x", Some(a)), "key x: a id 8");
+    assert_eq!(describe_input("key f1", None), "key f1: not bound");
+}
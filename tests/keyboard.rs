@@ -0,0 +1,51 @@
+// A tiny core that reads the A key of a RETRO_DEVICE_KEYBOARD every frame, like a home computer core
+mod common;
+
+use common::{fake_core_api, input_state, start_emulator, test_config};
+use libretro_sys::*;
+use minifb::Key;
+use rustro_arch::input::key_to_retrok;
+use std::sync::atomic::{AtomicI16, Ordering};
+
+static A_PRESSED: AtomicI16 = AtomicI16::new(-1);
+
+const RETROK_A: libc::c_uint = 97;
+
+unsafe extern "C" fn run() {
+    let pressed = input_state()(0, DEVICE_KEYBOARD, 0, RETROK_A);
+    A_PRESSED.store(pressed, Ordering::SeqCst);
+}
+
+#[test]
+fn keys_map_to_retrok_ids() {
+    assert_eq!(key_to_retrok(&Key::A), Some(97));
+    assert_eq!(key_to_retrok(&Key::Z), Some(122));
+    assert_eq!(key_to_retrok(&Key::Key0), Some(48));
+    assert_eq!(key_to_retrok(&Key::NumPad9), Some(265));
+    assert_eq!(key_to_retrok(&Key::F15), Some(296));
+    assert_eq!(key_to_retrok(&Key::Enter), Some(13));
+    assert_eq!(key_to_retrok(&Key::Left), Some(276));
+    assert_eq!(key_to_retrok(&Key::LeftShift), Some(304));
+    assert_eq!(key_to_retrok(&Key::Unknown), None);
+}
+
+#[test]
+fn held_keys_reach_a_port_set_to_a_keyboard() {
+    let core_api = CoreAPI {
+        retro_run: run,
+        ..fake_core_api()
+    };
+    let mut config = test_config();
+    config.insert(
+        "input_libretro_device_p1".to_string(),
+        "keyboard".to_string(),
+    );
+    let mut emulator = start_emulator(core_api, "keyboard", &[0; 16], config);
+
+    emulator.set_keyboard(&[Key::A, Key::LeftShift]);
+    emulator.run_frame().unwrap();
+    assert_eq!(A_PRESSED.load(Ordering::SeqCst), 1);
+    emulator.set_keyboard(&[Key::B]);
+    emulator.run_frame().unwrap();
+    assert_eq!(A_PRESSED.load(Ordering::SeqCst), 0);
+}
@@ -0,0 +1,30 @@
+use rustro_arch::config::get_frontend_language;
+use rustro_arch::language::{language_from_name, LANGUAGE_ENGLISH};
+use std::collections::HashMap;
+
+#[test]
+fn names_map_to_the_retro_language_values() {
+    assert_eq!(language_from_name("english"), Some(LANGUAGE_ENGLISH));
+    assert_eq!(language_from_name("Japanese"), Some(1));
+    assert_eq!(language_from_name("french"), Some(2));
+    assert_eq!(language_from_name("german"), Some(4));
+    assert_eq!(language_from_name("Portuguese Brazil"), Some(7));
+    assert_eq!(language_from_name("chinese-simplified"), Some(12));
+    assert_eq!(language_from_name("british_english"), Some(30));
+}
+
+#[test]
+fn retroarch_numbers_are_accepted_if_in_range() {
+    assert_eq!(language_from_name("3"), Some(3));
+    assert_eq!(language_from_name("32"), Some(32));
+    assert_eq!(language_from_name("33"), None);
+    assert_eq!(language_from_name("klingon"), None);
+}
+
+#[test]
+fn an_invalid_config_falls_back_to_english() {
+    let config =
+        |language: &str| HashMap::from([("frontend_language".to_string(), language.to_string())]);
+    assert_eq!(get_frontend_language(&config("spanish")), 3);
+    assert_eq!(get_frontend_language(&config("")), LANGUAGE_ENGLISH);
+}
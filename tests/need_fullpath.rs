@@ -0,0 +1,40 @@
+// A core that loads from the path (need_fullpath), it should be handed just the path however big the ROM is
+mod common;
+
+use common::{fake_core_api, start_emulator, test_config};
+use libretro_sys::*;
+use std::ffi::CStr;
+use std::sync::Mutex;
+
+// The data pointer's null-ness, size and path of the GameInfo the core was given
+static LOADED: Mutex<Option<(bool, usize, String)>> = Mutex::new(None);
+
+unsafe extern "C" fn get_system_info(info: *mut SystemInfo) {
+    (*info).need_fullpath = true;
+}
+unsafe extern "C" fn load_game(game: *const GameInfo) -> bool {
+    let path = CStr::from_ptr((*game).path).to_string_lossy().into_owned();
+    *LOADED.lock().unwrap() = Some(((*game).data.is_null(), (*game).size, path));
+    true
+}
+
+#[test]
+fn roms_over_max_rom_size_mb_are_handed_to_the_core_by_path() {
+    let mut config = test_config();
+    config.insert("max_rom_size_mb".to_string(), "1".to_string());
+    config.insert("rom_use_mmap".to_string(), "false".to_string());
+    let core_api = CoreAPI {
+        retro_get_system_info: get_system_info,
+        retro_load_game: load_game,
+        ..fake_core_api()
+    };
+    let rom = vec![0x55; 1024 * 1024 + 1];
+    let emulator = start_emulator(core_api, "need_fullpath", &rom, config);
+
+    let (data_is_null, size, path) = LOADED.lock().unwrap().clone().unwrap();
+    assert!(data_is_null);
+    assert_eq!(size, 0);
+    assert!(path.ends_with("rustro_arch_need_fullpath.rom"));
+    // The CRC32 is still worked out, from the file as the ROM isn't in memory
+    assert_eq!(emulator.content_crc32(), Some(crc32fast::hash(&rom)));
+}
@@ -0,0 +1,27 @@
+use rustro_arch::osd::{draw_text, OsdMessage, TEXT_HEIGHT};
+use std::time::Duration;
+
+const WHITE: u32 = 0x00FFFFFF;
+
+#[test]
+fn letters_are_drawn_in_a_box() {
+    let mut pixels = vec![0x00123456; 10 * 10];
+    draw_text(&mut pixels, 10, 10, 0, 0, "lt");
+    // The box is one pixel wider than the two 3 pixel glyphs and their 1 pixel gaps
+    assert_eq!(pixels[9], 0x00123456);
+    assert_eq!(pixels[TEXT_HEIGHT * 10], 0x00123456);
+    // L is a vertical line with a foot, T a bar with a stem
+    let row = |y: usize| &pixels[(y + 1) * 10 + 1..(y + 1) * 10 + 8];
+    assert_eq!(row(0), &[WHITE, 0, 0, 0, WHITE, WHITE, WHITE]);
+    assert_eq!(row(4), &[WHITE, WHITE, WHITE, 0, 0, WHITE, 0]);
+}
+
+#[test]
+fn messages_disappear_once_their_time_is_up() {
+    let mut message = OsdMessage::default();
+    assert_eq!(message.current(), None);
+    message.show("Filter: bilinear", Duration::from_secs(60));
+    assert_eq!(message.current(), Some("Filter: bilinear"));
+    message.show("Filter: nearest", Duration::ZERO);
+    assert_eq!(message.current(), None);
+}
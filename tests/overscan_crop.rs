@@ -0,0 +1,38 @@
+use rustro_arch::video::OverscanCrop;
+
+// A 4x3 frame where each pixel is its index
+fn frame() -> Vec<u32> {
+    (0..12).collect()
+}
+
+#[test]
+fn crop_removes_the_border() {
+    let crop = OverscanCrop {
+        top: 1,
+        bottom: 0,
+        left: 1,
+        right: 1,
+    };
+    assert_eq!(crop.apply(&frame(), 4, 3), (vec![5, 6, 9, 10], 2, 2));
+}
+
+#[test]
+fn no_crop_keeps_the_frame() {
+    assert_eq!(OverscanCrop::default().apply(&frame(), 4, 3), (frame(), 4, 3));
+}
+
+#[test]
+fn crop_bigger_than_the_frame_is_ignored() {
+    let crop = OverscanCrop {
+        top: 0,
+        bottom: 0,
+        left: 2,
+        right: 2,
+    };
+    assert_eq!(crop.apply(&frame(), 4, 3), (frame(), 4, 3));
+    let crop = OverscanCrop {
+        top: 5,
+        ..OverscanCrop::default()
+    };
+    assert_eq!(crop.apply(&frame(), 4, 3), (frame(), 4, 3));
+}
@@ -0,0 +1,29 @@
+// A tiny core that tells the frontend how demanding it is when the game is loaded
+mod common;
+
+use common::{environment, fake_core_api, set_environment_value, start_emulator, test_config};
+use libretro_sys::*;
+
+unsafe extern "C" fn load_game(_: *const GameInfo) -> bool {
+    let mut level: libc::c_uint = 7;
+    assert!(set_environment_value(
+        ENVIRONMENT_SET_PERFORMANCE_LEVEL,
+        &mut level
+    ));
+    assert!(!environment()(
+        ENVIRONMENT_SET_PERFORMANCE_LEVEL,
+        std::ptr::null_mut()
+    ));
+    true
+}
+
+#[test]
+fn the_performance_level_is_acknowledged_and_kept() {
+    let core_api = CoreAPI {
+        retro_load_game: load_game,
+        ..fake_core_api()
+    };
+    let emulator = start_emulator(core_api, "performance_level", &[0; 16], test_config());
+
+    assert_eq!(emulator.performance_level(), Some(7));
+}
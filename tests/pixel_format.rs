@@ -0,0 +1,98 @@
+use rustro_arch::config::{default_config, get_rgb565_byte_order};
+use rustro_arch::video::{
+    convert_pixel_array_from_0rgb1555_to_xrgb8888, convert_pixel_array_from_rgb565_to_xrgb8888,
+    convert_pixel_array_from_xrgb8888, swap_red_blue, ByteOrder,
+};
+
+#[test]
+fn rgb565_converts_to_xrgb8888() {
+    // 0xF800 (red) then 0x001F (blue), high byte first
+    let pixels =
+        convert_pixel_array_from_rgb565_to_xrgb8888(&[0xF8, 0x00, 0x00, 0x1F], ByteOrder::Big);
+    assert_eq!(&*pixels, &[0x00FF0000, 0x000000FF]);
+}
+
+#[test]
+fn rgb565_with_an_odd_length_drops_the_last_byte() {
+    let pixels = convert_pixel_array_from_rgb565_to_xrgb8888(
+        &[0xF8, 0x00, 0x00, 0x1F, 0xFF],
+        ByteOrder::Big,
+    );
+    assert_eq!(&*pixels, &[0x00FF0000, 0x000000FF]);
+    assert!(convert_pixel_array_from_rgb565_to_xrgb8888(&[0xFF], ByteOrder::Big).is_empty());
+}
+
+#[test]
+fn the_same_bytes_decode_by_byte_order() {
+    // Little endian reads these as 0x00F8 then 0x1F00, big endian as 0xF800 then 0x001F
+    let bytes = [0xF8, 0x00, 0x00, 0x1F];
+    assert_eq!(
+        &*convert_pixel_array_from_rgb565_to_xrgb8888(&bytes, ByteOrder::Little),
+        &[0x00001CC6, 0x0018E700]
+    );
+    assert_eq!(
+        &*convert_pixel_array_from_rgb565_to_xrgb8888(&bytes, ByteOrder::Big),
+        &[0x00FF0000, 0x000000FF]
+    );
+    // Cores send pixels in the host's byte order
+    let red = 0xF800u16.to_ne_bytes();
+    assert_eq!(
+        &*convert_pixel_array_from_rgb565_to_xrgb8888(&red, ByteOrder::native()),
+        &[0x00FF0000]
+    );
+    assert_eq!(ByteOrder::from_name("native"), Some(ByteOrder::native()));
+    assert_eq!(ByteOrder::Little.swapped(), ByteOrder::Big);
+}
+
+#[test]
+fn video_rgb565_swap_bytes_reads_the_other_byte_order() {
+    let mut config = default_config();
+    assert_eq!(get_rgb565_byte_order(&config), ByteOrder::native());
+    config.insert("video_rgb565_swap_bytes".to_string(), "true".to_string());
+    assert_eq!(get_rgb565_byte_order(&config), ByteOrder::native().swapped());
+    config.insert("video_rgb565_byte_order".to_string(), "big".to_string());
+    assert_eq!(get_rgb565_byte_order(&config), ByteOrder::Little);
+
+    // A core sending red (0xF800) in the wrong byte order shows it as 0x00F8 until the bytes are swapped
+    let swapped_red = 0xF800u16.swap_bytes().to_ne_bytes();
+    assert_eq!(
+        &*convert_pixel_array_from_rgb565_to_xrgb8888(&swapped_red, ByteOrder::native()),
+        &[0x00001CC6]
+    );
+    assert_eq!(
+        &*convert_pixel_array_from_rgb565_to_xrgb8888(&swapped_red, ByteOrder::native().swapped()),
+        &[0x00FF0000]
+    );
+}
+
+#[test]
+fn swap_red_blue_leaves_green_and_the_top_byte() {
+    let mut pixels = [0x00FF0000, 0x000000FF, 0x0000FF00, 0xAA123456];
+    swap_red_blue(&mut pixels);
+    assert_eq!(pixels, [0x000000FF, 0x00FF0000, 0x0000FF00, 0xAA563412]);
+    swap_red_blue(&mut pixels);
+    assert_eq!(pixels, [0x00FF0000, 0x000000FF, 0x0000FF00, 0xAA123456]);
+}
+
+#[test]
+fn xrgb1555_converts_to_xrgb8888() {
+    // 0x7C00 (red), 0x03E0 (green) then 0x801F (blue, the unused top bit set)
+    let pixels = convert_pixel_array_from_0rgb1555_to_xrgb8888(
+        &[0x7C, 0x00, 0x03, 0xE0, 0x80, 0x1F, 0xFF],
+        ByteOrder::Big,
+    );
+    assert_eq!(&*pixels, &[0x00FF0000, 0x0000FF00, 0x000000FF]);
+}
+
+#[test]
+fn xrgb8888_padding_is_cleared() {
+    // Opaque white with the padding byte set, as an ARGB8888 core would send it, then with it clear
+    let bytes: Vec<u8> = [0xFFFFFFFFu32, 0x00FFFFFF]
+        .iter()
+        .flat_map(|pixel| pixel.to_ne_bytes())
+        .collect();
+    assert_eq!(
+        &*convert_pixel_array_from_xrgb8888(&bytes),
+        &[0x00FFFFFF, 0x00FFFFFF]
+    );
+}
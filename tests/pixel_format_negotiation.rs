@@ -0,0 +1,43 @@
+// A tiny core that asks for a pixel format libretro doesn't define while loading, then falls back to XRGB8888
+mod common;
+
+use common::{fake_core_api, set_environment_value, start_emulator, test_config};
+use libretro_sys::*;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static UNKNOWN_FORMAT_ACCEPTED: AtomicBool = AtomicBool::new(true);
+static XRGB8888_ACCEPTED: AtomicBool = AtomicBool::new(false);
+
+unsafe extern "C" fn load_game(_: *const GameInfo) -> bool {
+    let mut unknown_format: u32 = 7;
+    UNKNOWN_FORMAT_ACCEPTED.store(
+        set_environment_value(ENVIRONMENT_SET_PIXEL_FORMAT, &mut unknown_format),
+        Ordering::SeqCst,
+    );
+    let mut xrgb8888 = PixelFormat::ARGB8888 as u32;
+    XRGB8888_ACCEPTED.store(
+        set_environment_value(ENVIRONMENT_SET_PIXEL_FORMAT, &mut xrgb8888),
+        Ordering::SeqCst,
+    );
+    true
+}
+
+// Only one Emulator can exist at a time so everything is checked in a single test
+#[test]
+fn unsupported_pixel_formats_are_refused_and_the_core_can_pick_another() {
+    let core_api = CoreAPI {
+        retro_load_game: load_game,
+        ..fake_core_api()
+    };
+    let emulator = start_emulator(
+        core_api,
+        "pixel_format_negotiation",
+        &[0; 16],
+        test_config(),
+    );
+
+    assert!(!UNKNOWN_FORMAT_ACCEPTED.load(Ordering::SeqCst));
+    assert!(XRGB8888_ACCEPTED.load(Ordering::SeqCst));
+    assert_eq!(emulator.pixel_format(), PixelFormat::ARGB8888);
+    assert_eq!(emulator.bytes_per_pixel(), 4);
+}
@@ -0,0 +1,133 @@
+use rustro_arch::video::{
+    crop_frame, present_frame, rotate_frame, ColorCorrection, OverscanCrop, PresentOptions,
+    VideoFilter,
+};
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+
+// Counts the allocations made on the current thread so tests running alongside don't add to them
+struct CountingAllocator;
+
+thread_local! {
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let _ = ALLOCATIONS.try_with(|count| count.set(count.get() + 1));
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+fn allocations<T>(f: impl FnOnce() -> T) -> (T, usize) {
+    let before = ALLOCATIONS.with(Cell::get);
+    let result = f();
+    (result, ALLOCATIONS.with(Cell::get) - before)
+}
+
+// A 6x5 frame with rows 8 pixels apart, every pixel a different colour
+fn frame_buffer() -> Vec<u32> {
+    (0..8 * 5).map(|index| index * 0x00050301).collect()
+}
+
+fn options(filter: VideoFilter) -> PresentOptions {
+    PresentOptions {
+        overscan: OverscanCrop {
+            top: 1,
+            bottom: 0,
+            left: 1,
+            right: 2,
+        },
+        rotation: 1,
+        color_correction: ColorCorrection::new(1.5, 1.0),
+        filter,
+        aspect_ratio: None,
+        integer_scale: false,
+    }
+}
+
+// The crop, overscan, colour correction, rotation and filter the main loop used to apply one by one
+fn step_by_step(options: &PresentOptions) -> (Vec<u32>, usize, usize) {
+    let visible = crop_frame(&frame_buffer(), 6, 5, 8).unwrap();
+    let (mut pixels, width, height) = options.overscan.apply(&visible, 6, 5);
+    options.color_correction.apply(&mut pixels);
+    let (pixels, width, height) = rotate_frame(&pixels, width, height, options.rotation);
+    options.filter.apply(&pixels, width, height)
+}
+
+#[test]
+fn at_its_own_size_the_frame_matches_the_step_by_step_transforms() {
+    for filter in [
+        VideoFilter::Nearest,
+        VideoFilter::Bilinear,
+        VideoFilter::Scale2x,
+    ] {
+        let options = options(filter);
+        let (expected, width, height) = step_by_step(&options);
+        assert_eq!(options.frame_size(6, 5), (width, height));
+        let (presented, count) =
+            allocations(|| present_frame(&frame_buffer(), 6, 5, 8, 0, 0, &options).unwrap());
+        assert_eq!(presented, expected, "{}", filter.name());
+        // The frame_buffer() copy and the frame itself, one by one the transforms take 4
+        assert_eq!(count, 2);
+        assert_eq!(allocations(|| step_by_step(&options)).1, 5);
+    }
+    let short = vec![0; 8 * 4];
+    assert_eq!(
+        present_frame(&short, 6, 5, 8, 0, 0, &options(VideoFilter::Nearest)),
+        None
+    );
+}
+
+#[test]
+fn frames_are_scaled_into_the_window_with_bars_around_them() {
+    // A 2x1 frame shown square in a 6x4 window is 6x3 with a row of black below
+    let options = PresentOptions {
+        overscan: OverscanCrop::default(),
+        rotation: 0,
+        color_correction: ColorCorrection::new(1.0, 1.0),
+        filter: VideoFilter::Nearest,
+        aspect_ratio: Some(2.0),
+        integer_scale: false,
+    };
+    let (presented, count) = allocations(|| present_frame(&[1, 2], 2, 1, 2, 6, 4, &options));
+    assert_eq!(count, 1);
+    assert_eq!(
+        presented.unwrap(),
+        [
+            1, 1, 1, 2, 2, 2, //
+            1, 1, 1, 2, 2, 2, //
+            1, 1, 1, 2, 2, 2, //
+            0, 0, 0, 0, 0, 0,
+        ]
+    );
+    // Whole multiples only, 3x would be too wide for the 5x4 window so it's 4x2 in the middle
+    let integer = PresentOptions {
+        integer_scale: true,
+        ..options.clone()
+    };
+    assert_eq!(
+        present_frame(&[1, 2], 2, 1, 2, 5, 4, &integer).unwrap(),
+        [
+            0, 0, 0, 0, 0, //
+            1, 1, 2, 2, 0, //
+            1, 1, 2, 2, 0, //
+            0, 0, 0, 0, 0,
+        ]
+    );
+    // Bilinear blends across a fractional scale
+    let bilinear = PresentOptions {
+        filter: VideoFilter::Bilinear,
+        aspect_ratio: None,
+        ..options
+    };
+    let presented = present_frame(&[0, 0x00FFFFFF], 2, 1, 2, 3, 1, &bilinear).unwrap();
+    assert_eq!(presented, [0, 0x00808080, 0x00FFFFFF]);
+}
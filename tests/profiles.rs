@@ -0,0 +1,29 @@
+use rustro_arch::config::{find_core_profile, parse_retroarch_config};
+use std::path::Path;
+
+#[test]
+fn profiles_are_matched_by_library_name_ignoring_case() {
+    let profiles = Path::new(env!("CARGO_MANIFEST_DIR")).join("profiles");
+    let profile = find_core_profile(&profiles, "gambatte").unwrap();
+    assert_eq!(profile.file_name().unwrap(), "Gambatte.cfg");
+    assert!(find_core_profile(&profiles, "Gambatte Plus").is_none());
+    assert!(find_core_profile(&profiles.join("missing"), "Gambatte").is_none());
+}
+
+#[test]
+fn shipped_profiles_only_set_known_options() {
+    let profiles = Path::new(env!("CARGO_MANIFEST_DIR")).join("profiles");
+    for library_name in ["Gambatte", "mGBA", "Snes9x"] {
+        let profile = find_core_profile(&profiles, library_name).unwrap();
+        let config = parse_retroarch_config(&profile).unwrap();
+        assert!(!config.is_empty());
+        for key in config.keys() {
+            assert!(
+                key.starts_with("video_") || key.starts_with("run_ahead_"),
+                "{} sets {}",
+                library_name,
+                key
+            );
+        }
+    }
+}
@@ -0,0 +1,21 @@
+use rustro_arch::recent::{load_last_game, save_last_game, LastGame};
+
+#[test]
+fn the_last_game_round_trips() {
+    let path = std::env::temp_dir().join("rustro_arch_last_game.cfg");
+    let last_game = LastGame {
+        core_path: "/cores/gambatte_libretro.so".to_string(),
+        rom_path: "/roms/Pokemon - Red Version (USA, Europe).gb".to_string(),
+    };
+    save_last_game(&path, &last_game).unwrap();
+    assert_eq!(load_last_game(&path).unwrap(), Some(last_game));
+}
+
+#[test]
+fn no_file_means_no_game_and_a_broken_one_is_an_error() {
+    let path = std::env::temp_dir().join("rustro_arch_no_last_game.cfg");
+    let _ = std::fs::remove_file(&path);
+    assert_eq!(load_last_game(&path).unwrap(), None);
+    std::fs::write(&path, "core_path = \"/cores/a.so\"\n").unwrap();
+    assert!(load_last_game(&path).is_err());
+}
@@ -0,0 +1,101 @@
+use rustro_arch::recording::{AvRecorder, RawAudioDump, RecordingSummary};
+
+const SAMPLE_RATE: u32 = 32040;
+const SAMPLES_PER_FRAME: usize = 534 * 2; // 32040 Hz / 60 fps, interleaved stereo
+
+#[test]
+fn video_and_audio_stay_the_same_length() {
+    let path = std::env::temp_dir().join("rustro_arch_recording");
+    let mut recorder = AvRecorder::create(&path, 60.0, SAMPLE_RATE, 10).unwrap();
+    let pixels = [0u32; 4];
+    let frame = Some((&pixels[..], 2, 2));
+    let one_frame_of_audio = vec![0i16; SAMPLES_PER_FRAME];
+    // A normal frame, a fast forward of 4 frames, a paused frame then 2 frames that couldn't be shown
+    // followed by one that could
+    recorder.record(11, frame, &one_frame_of_audio).unwrap();
+    recorder
+        .record(15, frame, &one_frame_of_audio.repeat(4))
+        .unwrap();
+    recorder.record(15, frame, &one_frame_of_audio).unwrap();
+    recorder.record(16, None, &one_frame_of_audio).unwrap();
+    recorder.record(17, None, &one_frame_of_audio).unwrap();
+    recorder.record(18, frame, &one_frame_of_audio).unwrap();
+    assert!(recorder
+        .ffmpeg_command("out.mp4")
+        .unwrap()
+        .contains("-video_size 2x2"));
+
+    let summary = recorder.finish().unwrap();
+    assert_eq!(
+        summary,
+        RecordingSummary {
+            video_frames: 8,
+            fps: 60.0,
+            audio_frames: 8 * 534,
+            sample_rate: SAMPLE_RATE,
+        }
+    );
+    assert!(summary.is_in_sync());
+
+    let wav = std::fs::read(path.with_extension("wav")).unwrap();
+    assert_eq!(&wav[0..4], b"RIFF");
+    assert_eq!(
+        u32::from_le_bytes(wav[40..44].try_into().unwrap()),
+        8 * 534 * 4
+    );
+    assert_eq!(wav.len(), 44 + 8 * 534 * 4);
+    std::fs::remove_file(path.with_extension("wav")).ok();
+    std::fs::remove_file(path.with_extension("raw")).ok();
+}
+
+#[test]
+fn a_missing_second_of_audio_is_out_of_sync() {
+    let summary = RecordingSummary {
+        video_frames: 120,
+        fps: 60.0,
+        audio_frames: SAMPLE_RATE as u64,
+        sample_rate: SAMPLE_RATE,
+    };
+    assert_eq!(summary.video_seconds(), 2.0);
+    assert!(!summary.is_in_sync());
+}
+
+#[test]
+fn a_reset_frame_counter_keeps_recording() {
+    let path = std::env::temp_dir().join("rustro_arch_recording_reset");
+    let mut recorder = AvRecorder::create(&path, 60.0, SAMPLE_RATE, 10).unwrap();
+    let pixels = [0u32; 4];
+    let frame = Some((&pixels[..], 2, 2));
+    let one_frame_of_audio = vec![0i16; SAMPLES_PER_FRAME];
+    recorder.record(11, frame, &one_frame_of_audio).unwrap();
+    // The core was reset so the next frame is frame 1
+    recorder.record(1, frame, &one_frame_of_audio).unwrap();
+    recorder.record(2, frame, &one_frame_of_audio).unwrap();
+    let summary = recorder.finish().unwrap();
+    assert_eq!(summary.video_frames, 3);
+    assert_eq!(summary.audio_frames, 3 * 534);
+    std::fs::remove_file(path.with_extension("wav")).ok();
+    std::fs::remove_file(path.with_extension("raw")).ok();
+}
+
+#[test]
+fn audio_dumps_are_the_samples_as_s16le_with_no_header() {
+    let path = std::env::temp_dir().join("rustro_arch_audio_dump.raw");
+    let mut dump = RawAudioDump::create(&path).unwrap();
+    assert_eq!(
+        dump.ffplay_command(SAMPLE_RATE),
+        format!(
+            "ffplay -f s16le -ar 32040 -ch_layout stereo {}",
+            path.display()
+        )
+    );
+    dump.write_samples(&[1, -1], SAMPLE_RATE).unwrap();
+    dump.write_samples(&[], SAMPLE_RATE).unwrap();
+    dump.write_samples(&[0x1234, i16::MIN], SAMPLE_RATE)
+        .unwrap();
+    assert_eq!(dump.finish().unwrap(), 2);
+    assert_eq!(
+        std::fs::read(&path).unwrap(),
+        [0x01, 0x00, 0xFF, 0xFF, 0x34, 0x12, 0x00, 0x80]
+    );
+}
@@ -0,0 +1,22 @@
+use rustro_arch::config::get_forced_region;
+use rustro_arch::region::{pacing_fps, Region};
+use std::collections::HashMap;
+
+#[test]
+fn the_region_fps_is_only_used_when_the_core_has_none() {
+    assert_eq!(Region::from_retro_region(0), Some(Region::Ntsc));
+    assert_eq!(Region::from_retro_region(1), Some(Region::Pal));
+    assert_eq!(Region::from_retro_region(2), None);
+    assert_eq!(pacing_fps(59.73, Region::Pal), 59.73);
+    assert_eq!(pacing_fps(0.0, Region::Pal), 50.0);
+    assert_eq!(pacing_fps(f64::NAN, Region::Ntsc), 60.0);
+}
+
+#[test]
+fn force_region_is_empty_or_a_region_name() {
+    let config = |region: &str| HashMap::from([("force_region".to_string(), region.to_string())]);
+    assert_eq!(get_forced_region(&config("")), None);
+    assert_eq!(get_forced_region(&config("PAL")), Some(Region::Pal));
+    assert_eq!(get_forced_region(&config("ntsc")), Some(Region::Ntsc));
+    assert_eq!(get_forced_region(&config("secam")), None);
+}
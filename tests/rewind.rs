@@ -0,0 +1,69 @@
+use rustro_arch::config::{default_config, get_rewind_buffer_size, get_rewind_granularity};
+use rustro_arch::rewind::RewindBuffer;
+use rustro_arch::save_state::StateCompression;
+
+#[test]
+fn snapshots_are_taken_every_granularity_frames_and_popped_newest_first() {
+    let mut rewind = RewindBuffer::new(3, 1024, StateCompression::None);
+    let mut serialized = Vec::new();
+    for frame in 0..7u8 {
+        rewind
+            .record(|| {
+                serialized.push(frame);
+                vec![frame]
+            })
+            .unwrap();
+    }
+    assert_eq!(serialized, [0, 3, 6]);
+    assert_eq!(rewind.pop().unwrap(), Some(vec![6]));
+    assert_eq!(rewind.pop().unwrap(), Some(vec![3]));
+    // After rewinding the next frame is snapshotted straight away
+    rewind.record(|| vec![9]).unwrap();
+    assert_eq!(rewind.pop().unwrap(), Some(vec![9]));
+    assert_eq!(rewind.pop().unwrap(), Some(vec![0]));
+    assert_eq!(rewind.pop().unwrap(), None);
+}
+
+#[test]
+fn the_oldest_snapshots_are_dropped_to_stay_under_the_size_cap() {
+    let mut rewind = RewindBuffer::new(1, 250, StateCompression::None);
+    for frame in 0..5u8 {
+        rewind.record(|| vec![frame; 100]).unwrap();
+    }
+    assert_eq!(rewind.len(), 2);
+    assert_eq!(rewind.used_bytes(), 200);
+    assert_eq!(rewind.pop().unwrap(), Some(vec![4; 100]));
+    assert_eq!(rewind.pop().unwrap(), Some(vec![3; 100]));
+    assert!(rewind.record(|| vec![0; 300]).is_err());
+    assert!(rewind.is_empty());
+}
+
+#[test]
+fn compressed_snapshots_fit_more_history_and_come_back_the_same() {
+    let state = |frame: u8| [vec![frame; 16], vec![0; 4096]].concat();
+    let mut raw = RewindBuffer::new(1, 64 * 1024, StateCompression::None);
+    let mut compressed = RewindBuffer::new(1, 64 * 1024, StateCompression::Zstd);
+    for frame in 0..100u8 {
+        raw.record(|| state(frame)).unwrap();
+        compressed.record(|| state(frame)).unwrap();
+    }
+    assert!(compressed.len() > raw.len());
+    assert_eq!(compressed.pop().unwrap(), Some(state(99)));
+}
+
+#[test]
+fn rewind_settings_are_clamped_and_fall_back_when_invalid() {
+    let mut config = default_config();
+    assert_eq!(get_rewind_granularity(&config), 1);
+    assert_eq!(get_rewind_buffer_size(&config), 20 * 1024 * 1024);
+    for (granularity, size_mb, expected_granularity, expected_mb) in [
+        ("0", "0", 1, 1),
+        ("5", "100000", 5, 4096),
+        ("often", "-1", 1, 20),
+    ] {
+        config.insert("rewind_granularity".to_string(), granularity.to_string());
+        config.insert("rewind_buffer_size_mb".to_string(), size_mb.to_string());
+        assert_eq!(get_rewind_granularity(&config), expected_granularity);
+        assert_eq!(get_rewind_buffer_size(&config), expected_mb * 1024 * 1024);
+    }
+}
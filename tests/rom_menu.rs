@@ -0,0 +1,38 @@
+use rustro_arch::menu::RomMenu;
+use std::path::PathBuf;
+
+fn roms(names: &[&str]) -> Vec<PathBuf> {
+    names
+        .iter()
+        .map(|name| PathBuf::from("/roms").join(name))
+        .collect()
+}
+
+#[test]
+fn the_selection_wraps_and_scrolls_into_view() {
+    let mut menu = RomMenu::new(roms(&["a.gb", "b.gb", "c.gb"]));
+    assert_eq!(menu.visible_lines(2), ["> a.gb", "  b.gb"]);
+    menu.move_selection(false);
+    assert_eq!(menu.selected(), Some(PathBuf::from("/roms/c.gb").as_path()));
+    assert_eq!(menu.visible_lines(2), ["  b.gb", "> c.gb"]);
+    menu.move_selection(true);
+    assert_eq!(menu.selected(), Some(PathBuf::from("/roms/a.gb").as_path()));
+
+    let mut empty = RomMenu::new(Vec::new());
+    empty.move_selection(true);
+    assert_eq!(empty.selected(), None);
+    assert!(empty.visible_lines(5).is_empty());
+}
+
+#[test]
+fn scanning_lists_the_roms_sorted_without_side_files() {
+    let directory = std::env::temp_dir().join("rustro_arch_rom_menu");
+    let _ = std::fs::remove_dir_all(&directory);
+    std::fs::create_dir_all(directory.join("saves")).unwrap();
+    for file in ["Tetris.gb", "Metroid.nes", "Tetris.gb.watch", ".hidden"] {
+        std::fs::write(directory.join(file), [0u8; 4]).unwrap();
+    }
+    let menu = RomMenu::scan(&directory).unwrap();
+    assert_eq!(menu.visible_lines(10), ["> Metroid.nes", "  Tetris.gb"]);
+    assert!(RomMenu::scan(&directory.join("missing")).is_err());
+}
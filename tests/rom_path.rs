@@ -0,0 +1,58 @@
+use rustro_arch::resolve_rom_path;
+use std::path::PathBuf;
+
+fn roms_directory(name: &str, files: &[&str]) -> PathBuf {
+    let directory = std::env::temp_dir().join(name);
+    let _ = std::fs::remove_dir_all(&directory);
+    std::fs::create_dir_all(&directory).unwrap();
+    for file in files {
+        std::fs::write(directory.join(file), [0u8; 4]).unwrap();
+    }
+    directory
+}
+
+#[test]
+fn bare_file_names_are_found_in_the_roms_directory() {
+    let directory = roms_directory("rustro_arch_roms_exact", &["Tetris.gb", "tetris.gbc"]);
+    let roms = directory.to_str().unwrap();
+    assert_eq!(
+        resolve_rom_path("Tetris.gb", roms).unwrap(),
+        directory.join("Tetris.gb")
+    );
+    assert_eq!(
+        resolve_rom_path("TETRIS.GBC", roms).unwrap(),
+        directory.join("tetris.gbc")
+    );
+    // Absolute paths and an empty roms_directory are left alone even if the file doesn't exist
+    let missing = directory.join("missing.gb");
+    assert_eq!(
+        resolve_rom_path(missing.to_str().unwrap(), roms).unwrap(),
+        missing
+    );
+    assert_eq!(
+        resolve_rom_path("missing.gb", "").unwrap(),
+        PathBuf::from("missing.gb")
+    );
+}
+
+#[test]
+fn partial_names_use_the_closest_match() {
+    let directory = roms_directory(
+        "rustro_arch_roms_fuzzy",
+        &[
+            "Super Mario Land (World).gb",
+            "Super Mario Land 2 - 6 Golden Coins (USA, Europe).gb",
+            "Pokemon - Red Version (USA, Europe).gb",
+        ],
+    );
+    let roms = directory.to_str().unwrap();
+    assert_eq!(
+        resolve_rom_path("super_mario_land", roms).unwrap(),
+        directory.join("Super Mario Land (World).gb")
+    );
+    assert_eq!(
+        resolve_rom_path("pokemon red", roms).unwrap(),
+        directory.join("Pokemon - Red Version (USA, Europe).gb")
+    );
+    assert!(resolve_rom_path("zelda", roms).is_err());
+}
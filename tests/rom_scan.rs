@@ -0,0 +1,57 @@
+use rustro_arch::core_info::CoreInfo;
+use rustro_arch::rom_scan::{roms_to_json, scan_roms};
+use std::fs;
+use std::path::PathBuf;
+
+fn core_info(valid_extensions: &[&str]) -> CoreInfo {
+    CoreInfo {
+        library_name: "Test".to_string(),
+        library_version: "1".to_string(),
+        valid_extensions: valid_extensions.iter().map(|e| e.to_string()).collect(),
+        need_fullpath: false,
+        block_extract: false,
+    }
+}
+
+#[test]
+fn only_the_cores_extensions_are_listed_and_subdirectories_when_recursive() {
+    let directory = std::env::temp_dir().join("rustro_arch_rom_scan");
+    let _ = fs::remove_dir_all(&directory);
+    fs::create_dir_all(directory.join("more")).unwrap();
+    for file in ["b.gb", "a.GBC", "notes.txt", ".hidden.gb", "more/c.gb"] {
+        fs::write(directory.join(file), b"").unwrap();
+    }
+    let game_boy = core_info(&["gb", "gbc"]);
+    assert_eq!(
+        scan_roms(&directory, &game_boy, false).unwrap(),
+        [directory.join("a.GBC"), directory.join("b.gb")]
+    );
+    assert_eq!(
+        scan_roms(&directory, &game_boy, true).unwrap(),
+        [
+            directory.join("a.GBC"),
+            directory.join("b.gb"),
+            directory.join("more/c.gb")
+        ]
+    );
+    // A core without extensions loads anything
+    assert_eq!(
+        scan_roms(&directory, &core_info(&[]), false).unwrap().len(),
+        3
+    );
+    assert!(scan_roms(&directory.join("missing"), &game_boy, false).is_err());
+    fs::remove_dir_all(&directory).unwrap();
+}
+
+#[test]
+fn json_lists_escaped_paths() {
+    let roms = [
+        PathBuf::from("roms/a.gb"),
+        PathBuf::from("roms/\"quoted\"\\b.gb"),
+    ];
+    assert_eq!(
+        roms_to_json(&roms),
+        r#"["roms/a.gb","roms/\"quoted\"\\b.gb"]"#
+    );
+    assert_eq!(roms_to_json(&[]), "[]");
+}
@@ -0,0 +1,55 @@
+use rustro_arch::{download_rom, is_url, resolve_rom_path, url_file_name};
+use std::io::{Read, Write};
+use std::net::TcpListener;
+use std::path::PathBuf;
+
+// Serves a single request with the given status line and body, returns the URL to ask for
+fn serve_once(status: &'static str, body: &'static [u8]) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap();
+    std::thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        let mut request = [0u8; 1024];
+        let _ = stream.read(&mut request);
+        let header = format!(
+            "HTTP/1.1 {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+            status,
+            body.len()
+        );
+        let _ = stream.write_all(header.as_bytes());
+        let _ = stream.write_all(body);
+    });
+    format!("http://{}/roms/game.gb?v=2", address)
+}
+
+#[test]
+fn urls_are_recognised_and_named_after_their_last_path_segment() {
+    assert!(is_url("https://example.com/roms/game.gb"));
+    assert!(is_url("HTTP://example.com/game.gb"));
+    assert!(!is_url("roms/game.gb"));
+    assert!(!is_url("ftp://example.com/game.gb"));
+    assert_eq!(
+        url_file_name("https://example.com/roms/game.gb?v=2#top"),
+        "game.gb"
+    );
+    assert_eq!(url_file_name("https://example.com/"), "download");
+    assert_eq!(url_file_name("https://example.com"), "download");
+    // URLs aren't looked up in roms_directory
+    assert_eq!(
+        resolve_rom_path("https://example.com/game.gb", "roms").unwrap(),
+        PathBuf::from("https://example.com/game.gb")
+    );
+}
+
+#[test]
+fn downloads_are_read_into_memory_and_failures_are_errors() {
+    assert_eq!(
+        download_rom(&serve_once("200 OK", b"ROM!"), 0).unwrap(),
+        b"ROM!"
+    );
+    let error = download_rom(&serve_once("404 Not Found", b""), 0).unwrap_err();
+    assert!(error.starts_with("Failed to download ROM"), "{}", error);
+    let big = &[0u8; 1024 * 1024 + 1];
+    let error = download_rom(&serve_once("200 OK", big), 1).unwrap_err();
+    assert!(error.contains("max_rom_size_mb"), "{}", error);
+}
@@ -0,0 +1,43 @@
+use rustro_arch::config::get_video_rotation;
+use rustro_arch::video::{rotate_frame, rotated_size};
+use std::collections::HashMap;
+
+// A 3x2 frame where each pixel is its index
+//   0 1 2
+//   3 4 5
+fn frame() -> Vec<u32> {
+    (0..6).collect()
+}
+
+#[test]
+fn frames_rotate_counter_clockwise_by_quarter_turns() {
+    assert_eq!(rotate_frame(&frame(), 3, 2, 0), (frame(), 3, 2));
+    assert_eq!(
+        rotate_frame(&frame(), 3, 2, 1),
+        (vec![2, 5, 1, 4, 0, 3], 2, 3)
+    );
+    assert_eq!(
+        rotate_frame(&frame(), 3, 2, 2),
+        (vec![5, 4, 3, 2, 1, 0], 3, 2)
+    );
+    assert_eq!(
+        rotate_frame(&frame(), 3, 2, 3),
+        (vec![3, 0, 4, 1, 5, 2], 2, 3)
+    );
+    // Four quarter turns is back where it started
+    let (once, width, height) = rotate_frame(&frame(), 3, 2, 1);
+    let (twice, width, height) = rotate_frame(&once, width, height, 1);
+    let (thrice, width, height) = rotate_frame(&twice, width, height, 1);
+    assert_eq!(rotate_frame(&thrice, width, height, 1), (frame(), 3, 2));
+    assert_eq!(rotated_size(640, 480, 3), (480, 640));
+}
+
+#[test]
+fn video_rotation_is_in_degrees_and_empty_uses_the_core() {
+    let config =
+        |rotation: &str| HashMap::from([("video_rotation".to_string(), rotation.to_string())]);
+    assert_eq!(get_video_rotation(&config("")), None);
+    assert_eq!(get_video_rotation(&config("0")), Some(0));
+    assert_eq!(get_video_rotation(&config("270")), Some(3));
+    assert_eq!(get_video_rotation(&config("45")), None);
+}
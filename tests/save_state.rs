@@ -0,0 +1,134 @@
+mod common;
+
+use common::MockCore;
+use rustro_arch::core::LibretroCore;
+use rustro_arch::save_state::{
+    auto_index_slot, get_save_state_path, highest_occupied_slot, load_state, nearest_occupied_slot,
+    next_save_slot, save_state, write_file_atomically, StateCompression,
+};
+
+fn save_directory(test_name: &str) -> String {
+    let directory = std::env::temp_dir().join(format!("rustro_arch_{}", test_name));
+    std::fs::remove_dir_all(&directory).ok();
+    directory.to_string_lossy().into_owned()
+}
+
+fn run_frames(core: &mut MockCore, frames: u32) {
+    for _ in 0..frames {
+        core.run();
+    }
+}
+
+#[test]
+fn loading_a_slot_restores_the_state_it_was_saved_with() {
+    let save_directory = save_directory("restores_state");
+    let mut core = MockCore::new();
+    run_frames(&mut core, 10);
+    save_state(
+        &core,
+        &save_directory,
+        "roms/Some Game.gb",
+        3,
+        StateCompression::None,
+    );
+    let saved_ram = core.ram.clone();
+
+    run_frames(&mut core, 5);
+    assert_ne!(core.ram, saved_ram);
+    load_state(&mut core, &save_directory, "roms/Some Game.gb", 3).unwrap();
+    assert_eq!(core.frame_count, 10);
+    assert_eq!(core.ram, saved_ram);
+}
+
+#[test]
+fn slots_are_saved_to_separate_files() {
+    let save_directory = save_directory("separate_slots");
+    let mut core = MockCore::new();
+    run_frames(&mut core, 1);
+    save_state(&core, &save_directory, "game.gb", 0, StateCompression::None);
+    run_frames(&mut core, 1);
+    save_state(&core, &save_directory, "game.gb", 1, StateCompression::Zstd);
+
+    load_state(&mut core, &save_directory, "game.gb", 0).unwrap();
+    assert_eq!(core.frame_count, 1);
+    load_state(&mut core, &save_directory, "game.gb", 1).unwrap();
+    assert_eq!(core.frame_count, 2);
+    let slot_path = get_save_state_path(&save_directory, "game.gb", 1).unwrap();
+    assert!(slot_path.ends_with("game_1.state"));
+}
+
+#[test]
+fn compressed_states_load_and_are_smaller() {
+    let save_directory = save_directory("compressed");
+    let mut core = MockCore::new();
+    core.ram = vec![7; 4096];
+    save_state(&core, &save_directory, "game.gb", 0, StateCompression::Zstd);
+    let slot_path = get_save_state_path(&save_directory, "game.gb", 0).unwrap();
+    assert!(std::fs::metadata(slot_path).unwrap().len() < 4096);
+
+    core.ram = vec![0; 4096];
+    load_state(&mut core, &save_directory, "game.gb", 0).unwrap();
+    assert_eq!(core.ram, vec![7; 4096]);
+}
+
+#[test]
+fn saves_replace_the_old_file_without_leaving_temporary_files() {
+    let save_directory = save_directory("atomic_writes");
+    let mut core = MockCore::new();
+    save_state(&core, &save_directory, "game.gb", 0, StateCompression::None);
+    run_frames(&mut core, 3);
+    save_state(&core, &save_directory, "game.gb", 0, StateCompression::None);
+    load_state(&mut core, &save_directory, "game.gb", 0).unwrap();
+    assert_eq!(core.frame_count, 3);
+    let files = std::fs::read_dir(&save_directory).unwrap().count();
+    assert_eq!(files, 1);
+
+    // A write that can't finish leaves whatever was there alone
+    let blocked_path = std::path::Path::new(&save_directory).join("blocked");
+    std::fs::create_dir(&blocked_path).unwrap();
+    assert!(write_file_atomically(&blocked_path, b"new").is_err());
+    assert!(blocked_path.is_dir());
+    assert_eq!(std::fs::read_dir(&save_directory).unwrap().count(), 2);
+}
+
+#[test]
+fn loading_an_empty_slot_leaves_the_core_alone() {
+    let save_directory = save_directory("empty_slot");
+    let mut core = MockCore::new();
+    run_frames(&mut core, 4);
+    assert_eq!(
+        load_state(&mut core, &save_directory, "game.gb", 7),
+        Err("Save state slot 7 is empty".to_string())
+    );
+    assert_eq!(core.frame_count, 4);
+}
+
+#[test]
+fn save_slots_wrap_or_stop_at_the_ends() {
+    assert_eq!(next_save_slot(9, 10, true, true), 0);
+    assert_eq!(next_save_slot(9, 10, false, true), 9);
+    assert_eq!(next_save_slot(0, 10, true, false), 9);
+    assert_eq!(next_save_slot(0, 10, false, false), 0);
+    assert_eq!(next_save_slot(4, 10, true, true), 5);
+    // A slot past the end (e.g. after lowering savestate_max_slots) is clamped first
+    assert_eq!(next_save_slot(200, 10, false, false), 8);
+}
+
+#[test]
+fn the_nearest_occupied_slot_prefers_the_lower_one() {
+    let occupied = |slots: &'static [u8]| move |slot: u8| slots.contains(&slot);
+    assert_eq!(nearest_occupied_slot(5, 10, occupied(&[3, 7])), Some(3));
+    assert_eq!(nearest_occupied_slot(5, 10, occupied(&[2, 6])), Some(6));
+    assert_eq!(nearest_occupied_slot(0, 10, occupied(&[9])), Some(9));
+    assert_eq!(nearest_occupied_slot(5, 10, occupied(&[])), None);
+}
+
+#[test]
+fn auto_index_saves_after_the_highest_occupied_slot_and_wraps() {
+    let occupied = |slots: &'static [u8]| move |slot: u8| slots.contains(&slot);
+    assert_eq!(highest_occupied_slot(10, occupied(&[2, 7])), Some(7));
+    assert_eq!(highest_occupied_slot(10, occupied(&[])), None);
+    assert_eq!(auto_index_slot(10, occupied(&[2, 7])), 8);
+    assert_eq!(auto_index_slot(10, occupied(&[])), 0);
+    assert_eq!(auto_index_slot(10, occupied(&[0, 9])), 0);
+}
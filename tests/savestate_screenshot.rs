@@ -0,0 +1,66 @@
+// A tiny core that draws a 2x1 frame of red then blue and has a 4 byte save state
+mod common;
+
+use common::{fake_core_api, start_emulator, test_config, video_refresh, write_av_info};
+use libretro_sys::*;
+use std::ffi::c_void;
+use std::time::Duration;
+
+unsafe extern "C" fn get_system_av_info(info: *mut SystemAvInfo) {
+    write_av_info(info, 2, 1);
+}
+unsafe extern "C" fn run() {
+    let frame: [u16; 2] = [0x7C00, 0x001F];
+    video_refresh()(frame.as_ptr() as *const c_void, 2, 1, 4);
+}
+unsafe extern "C" fn serialize_size() -> libc::size_t {
+    4
+}
+
+// Only one Emulator can exist at a time so everything is checked in a single test
+#[test]
+fn a_full_size_screenshot_is_saved_next_to_the_state() {
+    let directory = std::env::temp_dir().join("rustro_arch_savestate_screenshot");
+    let _ = std::fs::remove_dir_all(&directory);
+    let core_api = CoreAPI {
+        retro_get_system_av_info: get_system_av_info,
+        retro_run: run,
+        retro_serialize_size: serialize_size,
+        ..fake_core_api()
+    };
+    let mut config = test_config();
+    config.extend([
+        (
+            "savestate_directory".to_string(),
+            directory.to_str().unwrap().to_string(),
+        ),
+        ("savestate_compression".to_string(), "none".to_string()),
+        ("screenshot_on_savestate".to_string(), "true".to_string()),
+    ]);
+    let mut emulator = start_emulator(core_api, "savestate_screenshot", &[0; 16], config);
+
+    emulator.run_frame().unwrap();
+    emulator.save_state(3);
+    assert!(directory
+        .join("rustro_arch_savestate_screenshot_3.state")
+        .exists());
+    // The PNG is written on another thread, so it may not be there (or be complete) straight away
+    let screenshot_path = directory.join("rustro_arch_savestate_screenshot_3.png");
+    let read_size = || {
+        let mut reader = png::Decoder::new(std::fs::File::open(&screenshot_path).ok()?)
+            .read_info()
+            .ok()?;
+        let mut pixels = vec![0; reader.output_buffer_size()];
+        let frame = reader.next_frame(&mut pixels).ok()?;
+        Some((frame.width, frame.height))
+    };
+    let mut size = None;
+    for _ in 0..100 {
+        size = read_size();
+        if size.is_some() {
+            break;
+        }
+        std::thread::sleep(Duration::from_millis(20));
+    }
+    assert_eq!(size, Some((2, 1)));
+}
@@ -0,0 +1,27 @@
+use libretro_sys::PixelFormat;
+use rustro_arch::status::{pixel_format_name, Status};
+
+#[test]
+fn status_is_one_line_of_json() {
+    let status = Status {
+        fps: 59.7275,
+        frame: 1200,
+        save_slot: 3,
+        pixel_format: PixelFormat::RGB565,
+        width: 160,
+        height: 144,
+        paused: false,
+        muted: true,
+    };
+    assert_eq!(
+        status.to_json(),
+        "{\"fps\":59.73,\"frame\":1200,\"save_slot\":3,\"pixel_format\":\"rgb565\",\"width\":160,\"height\":144,\"paused\":false,\"muted\":true}"
+    );
+}
+
+#[test]
+fn pixel_formats_use_the_libretro_names() {
+    assert_eq!(pixel_format_name(PixelFormat::ARGB1555), "0rgb1555");
+    assert_eq!(pixel_format_name(PixelFormat::ARGB8888), "xrgb8888");
+    assert_eq!(pixel_format_name(PixelFormat::RGB565), "rgb565");
+}
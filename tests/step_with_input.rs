@@ -0,0 +1,88 @@
+// Drives a tiny core written in Rust through the public Emulator API. The core draws a 4x2 frame with a
+// padded pitch in XRGB8888, red while joypad A is held and blue otherwise.
+mod common;
+
+use common::{
+    fake_core_api, input_poll, input_state, set_environment_value, start_emulator, test_config,
+    video_refresh, write_av_info,
+};
+use libretro_sys::*;
+use rustro_arch::replay::InputPlayback;
+use std::ffi::c_void;
+
+const WIDTH: usize = 4;
+const HEIGHT: usize = 2;
+const STRIDE: usize = 6; // pixels per row including 2 pixels of padding
+const RED: u32 = 0x00FF0000;
+const BLUE: u32 = 0x000000FF;
+const PADDING: u32 = 0x00FFFFFF;
+
+static mut FRAME: [u32; STRIDE * HEIGHT] = [0; STRIDE * HEIGHT];
+
+unsafe extern "C" fn get_system_av_info(info: *mut SystemAvInfo) {
+    write_av_info(info, WIDTH as u32, HEIGHT as u32);
+}
+unsafe extern "C" fn run() {
+    input_poll()();
+    let a_pressed = input_state()(0, DEVICE_JOYPAD, 0, DEVICE_ID_JOYPAD_A) != 0;
+    let frame = &mut *std::ptr::addr_of_mut!(FRAME);
+    for (index, pixel) in frame.iter_mut().enumerate() {
+        *pixel = match (index % STRIDE < WIDTH, a_pressed) {
+            (false, _) => PADDING,
+            (true, true) => RED,
+            (true, false) => BLUE,
+        };
+    }
+    video_refresh()(
+        frame.as_ptr() as *const c_void,
+        WIDTH as u32,
+        HEIGHT as u32,
+        STRIDE * 4,
+    );
+}
+unsafe extern "C" fn load_game(_: *const GameInfo) -> bool {
+    let mut format = PixelFormat::ARGB8888 as u32;
+    set_environment_value(ENVIRONMENT_SET_PIXEL_FORMAT, &mut format)
+}
+
+// Only one Emulator can exist at a time so everything is checked in a single test
+#[test]
+fn step_with_input_returns_the_frame_for_that_input() {
+    let core_api = CoreAPI {
+        retro_get_system_av_info: get_system_av_info,
+        retro_run: run,
+        retro_load_game: load_game,
+        ..fake_core_api()
+    };
+    let mut emulator = start_emulator(core_api, "step_with_input", &[0; 16], test_config());
+
+    let mut buttons = vec![0; 16];
+    buttons[DEVICE_ID_JOYPAD_A as usize] = 1;
+    let frame = emulator.step_with_input(0, &buttons).unwrap();
+    assert_eq!(frame.len(), STRIDE * HEIGHT);
+    assert_eq!(frame[0], RED);
+    assert_eq!(frame[WIDTH], PADDING);
+    assert_eq!(emulator.visible_frame(), Some(vec![RED; WIDTH * HEIGHT]));
+
+    emulator.step_with_input(0, &[0; 16]).unwrap();
+    assert_eq!(emulator.visible_frame(), Some(vec![BLUE; WIDTH * HEIGHT]));
+    assert_eq!(emulator.frame_stride(), STRIDE);
+    assert_eq!(emulator.frame_count(), 2);
+
+    // 3 frames holding A then the recording ends and nothing is pressed
+    let mut replay = b"RRIN\x02\x00\x00\x00\x00".to_vec();
+    replay.extend_from_slice(&3u32.to_le_bytes());
+    replay.extend_from_slice(&(1u16 << DEVICE_ID_JOYPAD_A).to_le_bytes());
+    let mut playback = InputPlayback::parse(&replay).unwrap();
+    let frame = emulator.run_to_frame(5, Some(&mut playback)).unwrap();
+    assert_eq!(frame[0], RED);
+    assert_eq!(emulator.frame_count(), 5);
+    let frame = emulator.run_to_frame(6, Some(&mut playback)).unwrap();
+    assert_eq!(frame[0], BLUE);
+    // Seeking backwards runs nothing
+    emulator.run_to_frame(3, None).unwrap();
+    assert_eq!(emulator.frame_count(), 6);
+    // The count starts again when the game does
+    emulator.reset();
+    assert_eq!(emulator.frame_count(), 0);
+}
@@ -0,0 +1,47 @@
+use rand::Rng;
+use rayon::ThreadPoolBuilder;
+use rustro_arch::video::{
+    convert_pixel_array_from_rgb565_to_xrgb8888,
+    convert_pixel_array_from_rgb565_to_xrgb8888_threaded, ByteOrder,
+};
+
+#[test]
+fn threaded_conversion_matches_the_scalar_path_for_random_frames() {
+    let mut rng = rand::thread_rng();
+    for _ in 0..200 {
+        let width = rng.gen_range(1..64);
+        let height = rng.gen_range(1..64);
+        let threads = rng.gen_range(1..9);
+        let pool = ThreadPoolBuilder::new().num_threads(threads).build().unwrap();
+        // Padded rows, sometimes to an odd pitch, and now and then a stray byte at the end
+        let row_bytes = width * 2 + rng.gen_range(0..5);
+        let length = row_bytes * height + rng.gen_range(0..2);
+        let bytes: Vec<u8> = (0..length).map(|_| rng.gen()).collect();
+        for byte_order in [ByteOrder::Little, ByteOrder::Big] {
+            assert_eq!(
+                pool.install(|| convert_pixel_array_from_rgb565_to_xrgb8888_threaded(
+                    &bytes, byte_order, row_bytes
+                )),
+                convert_pixel_array_from_rgb565_to_xrgb8888(&bytes, byte_order),
+                "{}x{} pitch {} length {} on {} threads",
+                width,
+                height,
+                row_bytes,
+                length,
+                threads
+            );
+        }
+    }
+}
+
+#[test]
+fn a_pitch_of_zero_or_a_single_row_is_converted_in_one_go() {
+    let bytes = [0xFF, 0xFF, 0x00, 0xF8];
+    let expected = [0x00FFFFFF, 0x00FF0000];
+    for row_bytes in [0, 4] {
+        assert_eq!(
+            *convert_pixel_array_from_rgb565_to_xrgb8888_threaded(&bytes, ByteOrder::Little, row_bytes),
+            expected
+        );
+    }
+}
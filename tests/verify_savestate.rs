@@ -0,0 +1,61 @@
+// A tiny core whose whole state is a counter that run() adds one to and draws as the frame's colour.
+// UNSERIALIZE_BROKEN makes it ignore the state it's given.
+mod common;
+
+use common::{fake_core_api, start_emulator, test_config, video_refresh};
+use libretro_sys::*;
+use rustro_arch::save_state::StateVerification;
+use std::ffi::c_void;
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+
+static COUNTER: AtomicU32 = AtomicU32::new(0);
+static UNSERIALIZE_BROKEN: AtomicBool = AtomicBool::new(false);
+
+unsafe extern "C" fn run() {
+    let frame = [COUNTER.fetch_add(1, Ordering::SeqCst) + 1];
+    video_refresh()(frame.as_ptr() as *const c_void, 1, 1, 4);
+}
+unsafe extern "C" fn serialize_size() -> libc::size_t {
+    4
+}
+unsafe extern "C" fn serialize(data: *mut c_void, size: libc::size_t) {
+    let state = COUNTER.load(Ordering::SeqCst).to_le_bytes();
+    std::slice::from_raw_parts_mut(data as *mut u8, size).copy_from_slice(&state);
+}
+unsafe extern "C" fn unserialize(data: *const c_void, size: libc::size_t) -> bool {
+    if !UNSERIALIZE_BROKEN.load(Ordering::SeqCst) {
+        let state = std::slice::from_raw_parts(data as *const u8, size);
+        COUNTER.store(
+            u32::from_le_bytes(state.try_into().unwrap()),
+            Ordering::SeqCst,
+        );
+    }
+    true
+}
+
+// Only one Emulator can exist at a time so everything is checked in a single test
+#[test]
+fn save_states_that_round_trip_pass_and_ones_that_dont_fail() {
+    let core_api = CoreAPI {
+        retro_run: run,
+        retro_serialize_size: serialize_size,
+        retro_serialize: serialize,
+        retro_unserialize: unserialize,
+        ..fake_core_api()
+    };
+    let mut emulator = start_emulator(core_api, "verify_savestate", &[0; 16], test_config());
+    emulator.run_frame().unwrap();
+
+    assert_eq!(
+        emulator.verify_save_state(10, None),
+        Ok(StateVerification::Passed)
+    );
+    // The frame counter is back where it would be after running the frames once
+    assert_eq!(emulator.frame_count(), 11);
+
+    UNSERIALIZE_BROKEN.store(true, Ordering::SeqCst);
+    assert_eq!(
+        emulator.verify_save_state(10, None),
+        Ok(StateVerification::FrameMismatch(12))
+    );
+}
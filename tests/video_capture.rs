@@ -0,0 +1,34 @@
+use rustro_arch::video_capture::{VideoRecorder, VIDEO_HEADER_SIZE};
+
+#[test]
+fn frames_are_written_after_the_header_and_other_sizes_repeat_the_last_frame() {
+    let path = std::env::temp_dir().join("rustro_arch_video_capture.raw");
+    let mut recorder = VideoRecorder::create(&path, 60.0).unwrap();
+    recorder
+        .record_frame(&[0x00112233, 0x00445566], 2, 1)
+        .unwrap();
+    recorder.record_frame(&[0x00FFFFFF; 4], 2, 2).unwrap();
+    // Anything past width * height (e.g. pitch padding) isn't written
+    recorder
+        .record_frame(&[0x00AABBCC, 0x00DDEEFF, 0x00FFFFFF], 2, 1)
+        .unwrap();
+    assert_eq!(recorder.finish(), Ok(3));
+
+    let contents = std::fs::read(&path).unwrap();
+    assert_eq!(&contents[0..4], b"RRVD");
+    assert_eq!(u32::from_le_bytes(contents[5..9].try_into().unwrap()), 2);
+    assert_eq!(u32::from_le_bytes(contents[9..13].try_into().unwrap()), 1);
+    assert_eq!(
+        f64::from_le_bytes(contents[13..21].try_into().unwrap()),
+        60.0
+    );
+    assert_eq!(
+        &contents[VIDEO_HEADER_SIZE..],
+        &[
+            0x33, 0x22, 0x11, 0x00, 0x66, 0x55, 0x44, 0x00, // first frame
+            0x33, 0x22, 0x11, 0x00, 0x66, 0x55, 0x44, 0x00, // the 2x2 frame repeats it
+            0xCC, 0xBB, 0xAA, 0x00, 0xFF, 0xEE, 0xDD, 0x00,
+        ]
+    );
+    std::fs::remove_file(path).ok();
+}
@@ -0,0 +1,63 @@
+use rustro_arch::video::{bilinear_2x, scale2x, VideoFilter};
+
+const BLACK: u32 = 0x00000000;
+const WHITE: u32 = 0x00FFFFFF;
+
+#[test]
+fn scale2x_keeps_a_flat_colour_flat() {
+    let (pixels, width, height) = scale2x(&[WHITE; 6], 3, 2);
+    assert_eq!((width, height), (6, 4));
+    assert_eq!(pixels, vec![WHITE; 24]);
+}
+
+#[test]
+fn scale2x_rounds_the_corners_of_a_diagonal() {
+    // W B
+    // B B
+    // The black neighbours above and left of the bottom right pixel fill in its top left corner etc.
+    let (pixels, width, _) = scale2x(&[WHITE, BLACK, BLACK, BLACK], 2, 2);
+    assert_eq!(width, 4);
+    #[rustfmt::skip]
+    let expected = vec![
+        WHITE, WHITE, BLACK, BLACK,
+        WHITE, BLACK, BLACK, BLACK,
+        BLACK, BLACK, BLACK, BLACK,
+        BLACK, BLACK, BLACK, BLACK,
+    ];
+    assert_eq!(pixels, expected);
+}
+
+#[test]
+fn scale2x_handles_a_single_pixel() {
+    assert_eq!(scale2x(&[WHITE], 1, 1), (vec![WHITE; 4], 2, 2));
+}
+
+#[test]
+fn bilinear_blends_between_pixels() {
+    let (pixels, width, height) = bilinear_2x(&[BLACK, WHITE], 2, 1);
+    assert_eq!((width, height), (4, 2));
+    // The outer pixels sit past the source pixel centres so keep their colour
+    assert_eq!(pixels[0], BLACK);
+    assert_eq!(pixels[3], WHITE);
+    assert_eq!(pixels[1], 0x00404040);
+    assert_eq!(pixels[2], 0x00BFBFBF);
+    assert_eq!(pixels[..4], pixels[4..]);
+}
+
+#[test]
+fn nearest_leaves_the_frame_alone() {
+    let frame = [BLACK, WHITE, WHITE, BLACK];
+    assert_eq!(VideoFilter::Nearest.apply(&frame, 2, 2), (frame.to_vec(), 2, 2));
+    assert_eq!(VideoFilter::from_name("Scale2x"), Some(VideoFilter::Scale2x));
+    assert_eq!(VideoFilter::from_name("hq4x"), None);
+}
+
+#[test]
+fn toggling_flips_between_nearest_and_bilinear() {
+    assert_eq!(VideoFilter::Nearest.toggled(), VideoFilter::Bilinear);
+    assert_eq!(VideoFilter::Bilinear.toggled(), VideoFilter::Nearest);
+    assert_eq!(VideoFilter::Scale2x.toggled(), VideoFilter::Nearest);
+    for name in VideoFilter::NAMES {
+        assert_eq!(VideoFilter::from_name(name).unwrap().name(), name);
+    }
+}
@@ -0,0 +1,54 @@
+use rustro_arch::config::{get_video_scale, get_window_size_limits};
+use rustro_arch::video::{next_video_scale, WindowSizeLimits};
+use std::collections::HashMap;
+
+#[test]
+fn the_scale_steps_by_one_between_1_and_the_max() {
+    assert_eq!(next_video_scale(2, 6, true), 3);
+    assert_eq!(next_video_scale(6, 6, true), 6);
+    assert_eq!(next_video_scale(2, 6, false), 1);
+    assert_eq!(next_video_scale(1, 6, false), 1);
+    // A window bigger than the max comes back down to it
+    assert_eq!(next_video_scale(9, 6, false), 6);
+}
+
+#[test]
+fn an_empty_video_scale_keeps_the_default_window() {
+    let config = |scale: &str| {
+        HashMap::from([
+            ("video_scale".to_string(), scale.to_string()),
+            ("video_scale_max".to_string(), "4".to_string()),
+        ])
+    };
+    assert_eq!(get_video_scale(&config("")), None);
+    assert_eq!(get_video_scale(&config("3")), Some(3));
+    assert_eq!(get_video_scale(&config("8")), Some(4));
+    assert_eq!(get_video_scale(&config("0")), None);
+}
+
+#[test]
+fn window_sizes_are_kept_between_the_limits_at_the_same_shape() {
+    let config = |min: &str, max: &str| {
+        HashMap::from([
+            ("window_min_size".to_string(), min.to_string()),
+            ("window_max_size".to_string(), max.to_string()),
+        ])
+    };
+    let limits = get_window_size_limits(&config("480x432", "1920X1080"));
+    assert_eq!(limits.min, Some((480, 432)));
+    assert_eq!(limits.max, Some((1920, 1080)));
+    // A Game Boy at 1x is scaled up, a 640x480 core at 4x down
+    assert_eq!(limits.clamp(160, 144), (480, 432));
+    assert_eq!(limits.clamp(2560, 1920), (1440, 1080));
+    assert_eq!(limits.clamp(640, 480), (640, 480));
+    // The max wins when the two can't both be met
+    let conflicting = WindowSizeLimits {
+        min: Some((1000, 1000)),
+        max: Some((500, 500)),
+    };
+    assert_eq!(conflicting.clamp(100, 50), (500, 250));
+    assert_eq!(
+        get_window_size_limits(&config("", "big")),
+        WindowSizeLimits::default()
+    );
+}